@@ -0,0 +1,155 @@
+use super::hashmap::RHMap;
+use core::hash::{BuildHasher, Hasher};
+
+/// Mixes with hardware AES rounds when the target has them enabled at compile time (e.g. `-C target-cpu=native` on
+/// x86_64), and with a folded 128-bit multiply everywhere else.
+const HARDWARE_AES: bool = cfg!(all(target_arch = "x86_64", target_feature = "aes"));
+
+const KEY: u128 = 0x243f_6a88_85a3_08d3_1319_8a2e_0370_7344;
+
+#[cfg(all(target_arch = "x86_64", target_feature = "aes"))]
+#[inline(always)]
+fn mix(state: u128, key: u128) -> u128 {
+    use core::arch::x86_64::{__m128i, _mm_aesenc_si128};
+
+    // SAFETY: AES is enabled for this target, and u128 and __m128i are both plain 16-byte values.
+    unsafe {
+        let round = _mm_aesenc_si128(
+            core::mem::transmute::<u128, __m128i>(state),
+            core::mem::transmute::<u128, __m128i>(key),
+        );
+        core::mem::transmute::<__m128i, u128>(round)
+    }
+}
+
+#[cfg(not(all(target_arch = "x86_64", target_feature = "aes")))]
+#[inline(always)]
+fn mix(state: u128, key: u128) -> u128 {
+    let fold = |a: u64, b: u64| {
+        let product = (a as u128) * (b as u128);
+        (product as u64) ^ ((product >> 64) as u64)
+    };
+    let (lo, hi) = (state as u64, (state >> 64) as u64);
+    let (key_lo, key_hi) = (key as u64, (key >> 64) as u64);
+    // Chaining the halves keeps them from coming out equal, which would cancel out in `finish`.
+    let mixed_lo = fold(lo ^ key_lo, hi ^ key_hi);
+    let mixed_hi = fold(mixed_lo ^ key_hi, lo.rotate_left(32) ^ key_lo);
+    ((mixed_hi as u128) << 64) | mixed_lo as u128
+}
+
+/// A hasher mixing 128 bits of state with one AES round per word, which modern x86_64 cores run in a few cycles.
+/// Hashes differ between machines with and without hardware AES, so never persist them.
+#[derive(Clone, Copy, Debug)]
+pub struct AesHasher {
+    state: u128,
+    key: u128,
+}
+
+impl Hasher for AesHasher {
+    fn finish(&self) -> u64 {
+        // A single round only mixes within 32-bit columns, two more spread every bit over the whole state.
+        let state = mix(mix(self.state, self.key), self.key);
+        (state as u64) ^ ((state >> 64) as u64)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(16);
+        for chunk in &mut chunks {
+            let mut block = [0; 16];
+            block.copy_from_slice(chunk);
+            self.write_u128(u128::from_le_bytes(block));
+        }
+
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            let mut block = [0; 16];
+            block[..rest.len()].copy_from_slice(rest);
+            // The length goes in the unused top byte, so trailing zero bytes aren't lost in the padding.
+            self.write_u128(u128::from_le_bytes(block) ^ (rest.len() as u128) << 120);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.state = mix(self.state ^ i, self.key);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u128(i as u128);
+    }
+}
+
+/// Builds `AesHasher`s, optionally seeded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AesBuildHasher {
+    seed: u64,
+}
+
+impl AesBuildHasher {
+    /// Creates an unseeded builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a builder that mixes `seed` into every hash.
+    pub fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Whether this build mixes with hardware AES rounds rather than the multiply fallback.
+    pub fn is_hardware_accelerated() -> bool {
+        HARDWARE_AES
+    }
+}
+
+impl BuildHasher for AesBuildHasher {
+    type Hasher = AesHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        AesHasher {
+            state: self.seed as u128,
+            key: KEY ^ ((self.seed as u128) << 64),
+        }
+    }
+}
+
+/// A `RHMap` hashing with `AesHasher`. Create one with `AesHashMap::default()`.
+pub type AesHashMap<K, V> = RHMap<K, V, AesBuildHasher>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_hashes_with_aes_rounds() {
+        let builder = AesBuildHasher::new();
+        let mut map: AesHashMap<String, usize> = AesHashMap::default();
+        for x in 0..1000 {
+            map.insert(x.to_string(), x);
+        }
+
+        assert_eq!(map.get("7"), Some(&7));
+        assert!(map.max_probe_length() < 16);
+        assert_ne!(builder.hash_one(1u64), builder.hash_one(2u64));
+        assert_ne!(builder.hash_one("ab"), builder.hash_one("ab\0"));
+        assert_ne!(
+            builder.hash_one(1u64),
+            AesBuildHasher::with_seed(1).hash_one(1u64)
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use super::hashmap::RHMap;
+use super::no_hash::NoHashBuilder;
+use super::raw_entry::RawEntryMut;
+use alloc::boxed::Box;
+use core::{
+    any::{Any, TypeId},
+    fmt::{self, Debug},
+};
+
+/// A map holding at most one value of each type, looked up by the type itself, e.g. the extensions a plugin host
+/// hands to its plugins.
+///
+/// Keyed by `TypeId`, which is already a hash, so the table uses it as is through `NoHashBuilder`.
+#[derive(Default)]
+pub struct AnyMap {
+    map: RHMap<TypeId, Box<dyn Any>, NoHashBuilder>,
+}
+
+impl AnyMap {
+    /// Creates an empty `AnyMap`.
+    pub fn new() -> Self {
+        Self {
+            map: RHMap::with_hasher(NoHashBuilder),
+        }
+    }
+
+    /// Inserts a value of type `T`, returning the previous value of that type, if any.
+    pub fn insert<T: 'static>(&mut self, value: T) -> Option<T> {
+        let previous = self.remove::<T>();
+        self.map.insert(TypeId::of::<T>(), Box::new(value));
+        previous
+    }
+
+    /// Gets the value of type `T`.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Gets a mutable reference to the value of type `T`.
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    /// Gets the value of type `T`, inserting the one `default` returns if there is none yet.
+    pub fn get_or_insert_with<T: 'static, F: FnOnce() -> T>(&mut self, default: F) -> &mut T {
+        let value = match self.map.raw_entry_mut().from_key(&TypeId::of::<T>()) {
+            RawEntryMut::Occupied(entry) => entry.into_mut(),
+            RawEntryMut::Vacant(entry) => entry.insert(TypeId::of::<T>(), Box::new(default())).1,
+        };
+        value
+            .downcast_mut()
+            .expect("values are stored under their own TypeId")
+    }
+
+    /// Removes the value of type `T` and returns it, if there was one.
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        let (_, value) = self.map.remove_entry(&TypeId::of::<T>())?;
+        value.downcast().ok().map(|value| *value)
+    }
+
+    /// Checks to see if there is a value of type `T`.
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Clears all values but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Gets the number of values, i.e. of distinct types stored.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+/// Shows how many values the map holds, since the values themselves are opaque.
+impl Debug for AnyMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyMap")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Port(u16);
+
+    #[test]
+    fn it_stores_one_value_per_type() {
+        let mut extensions = AnyMap::new();
+        assert_eq!(extensions.insert(Port(80)), None);
+        assert_eq!(extensions.insert("localhost"), None);
+        assert_eq!(extensions.insert(Port(8080)), Some(Port(80)));
+
+        assert_eq!(extensions.get::<Port>(), Some(&Port(8080)));
+        assert_eq!(extensions.get::<&str>(), Some(&"localhost"));
+        assert_eq!(extensions.get::<u32>(), None);
+        extensions.get_mut::<Port>().unwrap().0 += 1;
+        assert_eq!(extensions.remove::<Port>(), Some(Port(8081)));
+        assert!(!extensions.contains::<Port>());
+        assert_eq!(extensions.len(), 1);
+    }
+
+    #[test]
+    fn it_inserts_missing_types_on_demand() {
+        let mut map = AnyMap::new();
+        *map.get_or_insert_with(Vec::new) = vec![1];
+        map.get_or_insert_with(Vec::<i32>::new).push(2);
+
+        assert_eq!(map.get::<Vec<i32>>(), Some(&vec![1, 2]));
+        assert_eq!(map.len(), 1);
+    }
+}
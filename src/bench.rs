@@ -0,0 +1,163 @@
+use super::traits::MapWrite;
+use std::{
+    any,
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+/// Wraps any map backend and records the operation mix, probe counts and time spent while your real workload runs
+/// through it. Reports from different backends are directly comparable, so you can measure which one suits your
+/// workload from inside your own binary.
+pub struct BenchContext<K, V, M: MapWrite<K, V>> {
+    map: M,
+    report: BenchReport,
+    _marker: PhantomData<(K, V)>,
+}
+
+/// The statistics recorded by a `BenchContext`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BenchReport {
+    /// Type name of the wrapped backend.
+    pub backend: &'static str,
+    pub inserts: u64,
+    pub lookups: u64,
+    /// Number of lookups that found their key.
+    pub hits: u64,
+    pub removes: u64,
+    /// Total number of slots inspected by lookups and removes.
+    pub probes: u64,
+    /// The longest probe sequence seen by a single lookup or remove.
+    pub max_probes: usize,
+    /// Total time spent inside the wrapped backend.
+    pub elapsed: Duration,
+}
+
+impl BenchReport {
+    /// Total number of recorded operations.
+    pub fn operations(&self) -> u64 {
+        self.inserts + self.lookups + self.removes
+    }
+
+    /// Average number of slots inspected per lookup or remove.
+    pub fn mean_probes(&self) -> f64 {
+        match self.lookups + self.removes {
+            0 => 0.0,
+            n => self.probes as f64 / n as f64,
+        }
+    }
+
+    /// Fraction of lookups that found their key.
+    pub fn hit_rate(&self) -> f64 {
+        match self.lookups {
+            0 => 0.0,
+            n => self.hits as f64 / n as f64,
+        }
+    }
+}
+
+impl<K, V, M: MapWrite<K, V>> BenchContext<K, V, M> {
+    /// Starts recording operations on the given backend.
+    pub fn new(map: M) -> Self {
+        Self {
+            map,
+            report: BenchReport {
+                backend: any::type_name::<M>(),
+                ..BenchReport::default()
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Records an insert.
+    pub fn insert(&mut self, key: K, value: V) {
+        let start = Instant::now();
+        self.map.insert(key, value);
+        self.report.elapsed += start.elapsed();
+        self.report.inserts += 1;
+    }
+
+    /// Records a lookup.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.record_probes(key);
+
+        let start = Instant::now();
+        let res = self.map.get(key);
+        self.report.elapsed += start.elapsed();
+        self.report.lookups += 1;
+        if res.is_some() {
+            self.report.hits += 1;
+        }
+
+        res
+    }
+
+    /// Records a lookup that only checks for presence.
+    pub fn contains_key(&mut self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Records a remove. Returns `false` if there was no such entry.
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.record_probes(key);
+
+        let start = Instant::now();
+        let removed = self.map.remove(key);
+        self.report.elapsed += start.elapsed();
+        self.report.removes += 1;
+
+        removed
+    }
+
+    /// Gets the statistics recorded so far.
+    pub fn report(&self) -> &BenchReport {
+        &self.report
+    }
+
+    /// Gets a reference to the wrapped backend.
+    pub fn map(&self) -> &M {
+        &self.map
+    }
+
+    /// Stops recording and returns the wrapped backend along with the final report.
+    pub fn finish(self) -> (M, BenchReport) {
+        (self.map, self.report)
+    }
+
+    /// Probe counts are taken outside of the timed section so they don't skew `elapsed`.
+    fn record_probes(&mut self, key: &K) {
+        let probes = self.map.probe_count(key);
+        self.report.probes += probes as u64;
+        self.report.max_probes = self.report.max_probes.max(probes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hashmap::RHMap;
+    use super::*;
+
+    #[test]
+    fn it_records_the_operation_mix() {
+        let mut ctx = BenchContext::new(RHMap::new());
+        for x in 0..10 {
+            ctx.insert(x, x);
+        }
+        for x in 5..15 {
+            ctx.get(&x);
+        }
+        ctx.remove(&0);
+        ctx.remove(&100);
+
+        let (map, report) = ctx.finish();
+        assert_eq!(map.len(), 9);
+        assert!(report.backend.contains("RHMap"));
+        assert_eq!(report.inserts, 10);
+        assert_eq!(report.lookups, 10);
+        assert_eq!(report.hits, 5);
+        assert_eq!(report.removes, 2);
+        assert_eq!(report.operations(), 22);
+        assert_eq!(report.hit_rate(), 0.5);
+        assert!(report.probes >= 11);
+        assert!(report.max_probes >= 1);
+    }
+}
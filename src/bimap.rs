@@ -0,0 +1,204 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+};
+
+/// A `BiMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxBiMap<L, R> = BiMap<L, R, FxBuildHasher>;
+
+/// The pairs `BiMap::insert` removed to keep both sides unique.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Overwritten<L, R> {
+    /// Neither value was in the map.
+    Neither,
+    /// The left value was paired with something else, that pair was removed.
+    Left(L, R),
+    /// The right value was paired with something else, that pair was removed.
+    Right(L, R),
+    /// The exact same pair was already in the map.
+    Pair(L, R),
+    /// Both values were paired with something else, both pairs (the left one first) were removed.
+    Both((L, R), (L, R)),
+}
+
+/// A one to one map that can be looked up from either side, e.g. ids to names and names back to ids. Keeps a
+/// `RHMap` per direction in sync, so each value is stored twice and both sides have to be `Clone`.
+pub struct BiMap<L: Hash + Eq + Clone, R: Hash + Eq + Clone, H: BuildHasher + Clone> {
+    left_to_right: RHMap<L, R, H>,
+    right_to_left: RHMap<R, L, H>,
+}
+
+impl<L: Hash + Eq + Clone, R: Hash + Eq + Clone> BiMap<L, R, FxBuildHasher> {
+    /// Creates a `BiMap` with the default Fx Hasher and an initial capacity of 0.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<L: Hash + Eq + Clone, R: Hash + Eq + Clone, H: BuildHasher + Clone + Default> Default
+    for BiMap<L, R, H>
+{
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<L: Hash + Eq + Clone, R: Hash + Eq + Clone, H: BuildHasher + Clone> BiMap<L, R, H> {
+    /// Creates a `BiMap` with a custom hasher builder (used for both directions) which overrides the default fx
+    /// hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            left_to_right: RHMap::with_hasher(hasher_builder.clone()),
+            right_to_left: RHMap::with_hasher(hasher_builder),
+        }
+    }
+
+    /// Pairs `left` with `right`. Any existing pair containing either of them is removed first and handed back, so
+    /// both sides stay unique.
+    pub fn insert(&mut self, left: L, right: R) -> Overwritten<L, R> {
+        let by_left = self.remove_by_left(&left);
+        let by_right = self.remove_by_right(&right);
+        let overwritten = match (by_left, by_right) {
+            (None, None) => Overwritten::Neither,
+            // Removing by left already took out the right value too when they were paired with each other.
+            (Some((l, r)), None) if r == right => Overwritten::Pair(l, r),
+            (Some((l, r)), None) => Overwritten::Left(l, r),
+            (None, Some((l, r))) => Overwritten::Right(l, r),
+            (Some(by_left), Some(by_right)) => Overwritten::Both(by_left, by_right),
+        };
+
+        self.insert_unchecked(left, right);
+        overwritten
+    }
+
+    /// Pairs `left` with `right` only if neither of them is in the map yet. Otherwise the map is left untouched and
+    /// the pair is handed back.
+    pub fn insert_no_overwrite(&mut self, left: L, right: R) -> Result<(), (L, R)> {
+        if self.contains_left(&left) || self.contains_right(&right) {
+            return Err((left, right));
+        }
+
+        self.insert_unchecked(left, right);
+        Ok(())
+    }
+
+    /// Gets the right value paired with the given left value.
+    pub fn get_by_left<Q: ?Sized + Hash + Equivalent<L>>(&self, left: &Q) -> Option<&R> {
+        self.left_to_right.get(left)
+    }
+
+    /// Gets the left value paired with the given right value.
+    pub fn get_by_right<Q: ?Sized + Hash + Equivalent<R>>(&self, right: &Q) -> Option<&L> {
+        self.right_to_left.get(right)
+    }
+
+    /// Checks to see if the given left value is in the map.
+    pub fn contains_left<Q: ?Sized + Hash + Equivalent<L>>(&self, left: &Q) -> bool {
+        self.left_to_right.contains_key(left)
+    }
+
+    /// Checks to see if the given right value is in the map.
+    pub fn contains_right<Q: ?Sized + Hash + Equivalent<R>>(&self, right: &Q) -> bool {
+        self.right_to_left.contains_key(right)
+    }
+
+    /// Removes the pair with the given left value and returns it, if there was one.
+    pub fn remove_by_left<Q: ?Sized + Hash + Equivalent<L>>(&mut self, left: &Q) -> Option<(L, R)> {
+        let (left, right) = self.left_to_right.remove_entry(left)?;
+        self.right_to_left.remove_entry(&right);
+        Some((left, right))
+    }
+
+    /// Removes the pair with the given right value and returns it, if there was one.
+    pub fn remove_by_right<Q: ?Sized + Hash + Equivalent<R>>(
+        &mut self,
+        right: &Q,
+    ) -> Option<(L, R)> {
+        let (right, left) = self.right_to_left.remove_entry(right)?;
+        self.left_to_right.remove_entry(&left);
+        Some((left, right))
+    }
+
+    /// Clears all pairs but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.left_to_right.clear();
+        self.right_to_left.clear();
+    }
+
+    /// Gets the number of pairs.
+    pub fn len(&self) -> usize {
+        self.left_to_right.len()
+    }
+
+    /// Returns `true` if the map contains no pairs.
+    pub fn is_empty(&self) -> bool {
+        self.left_to_right.is_empty()
+    }
+
+    /// Iterates over every pair in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&L, &R)> + '_ {
+        self.left_to_right.iter()
+    }
+
+    /// Iterates over every left value in arbitrary order.
+    pub fn left_values(&self) -> impl Iterator<Item = &L> + '_ {
+        self.left_to_right.keys()
+    }
+
+    /// Iterates over every right value in arbitrary order.
+    pub fn right_values(&self) -> impl Iterator<Item = &R> + '_ {
+        self.right_to_left.keys()
+    }
+
+    fn insert_unchecked(&mut self, left: L, right: R) {
+        self.left_to_right.insert(left.clone(), right.clone());
+        self.right_to_left.insert(right, left);
+    }
+}
+
+/// Formats the map like the std maps do (`{left: right, ...}`).
+impl<L: Hash + Eq + Clone + Debug, R: Hash + Eq + Clone + Debug, H: BuildHasher + Clone> Debug
+    for BiMap<L, R, H>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_looks_up_both_directions() {
+        let mut ids = FxBiMap::new();
+        assert_eq!(ids.insert(1, "one".to_string()), Overwritten::Neither);
+        assert_eq!(ids.insert(2, "two".to_string()), Overwritten::Neither);
+
+        assert_eq!(ids.get_by_left(&1).map(String::as_str), Some("one"));
+        assert_eq!(ids.get_by_right("two"), Some(&2));
+        assert_eq!(ids.remove_by_right("one"), Some((1, "one".to_string())));
+        assert!(!ids.contains_left(&1));
+        assert_eq!(ids.len(), 1);
+    }
+
+    #[test]
+    fn it_overwrites_or_refuses_conflicting_pairs() {
+        let mut map = FxBiMap::new();
+        map.insert(1, 'a');
+        map.insert(2, 'b');
+
+        assert_eq!(map.insert_no_overwrite(1, 'c'), Err((1, 'c')));
+        assert_eq!(map.insert_no_overwrite(3, 'c'), Ok(()));
+        assert_eq!(map.insert(1, 'a'), Overwritten::Pair(1, 'a'));
+        assert_eq!(map.insert(1, 'd'), Overwritten::Left(1, 'a'));
+        assert_eq!(map.insert(4, 'b'), Overwritten::Right(2, 'b'));
+        assert_eq!(map.insert(1, 'c'), Overwritten::Both((1, 'd'), (3, 'c')));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get_by_right(&'c'), Some(&1));
+        assert_eq!(map.get_by_left(&4), Some(&'b'));
+    }
+}
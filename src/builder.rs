@@ -0,0 +1,147 @@
+use super::fx_build_hasher::FxBuildHasher;
+use super::growth::GrowthPolicy;
+use super::hashmap::{check_load_factor, RHMap, DEFAULT_LOAD_FACTOR};
+use alloc::boxed::Box;
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+};
+
+/// Configures a `RHMap` before creating it, for when more than one of the capacity, load factor and hasher need to be
+/// set.
+///
+/// ```
+/// use rhmap::builder::RHMapBuilder;
+///
+/// let map: rhmap::hashmap::RHMap<u32, u32, _> = RHMapBuilder::new().capacity(100).load_factor(0.9).build();
+/// assert!(map.capacity() >= 100);
+/// ```
+pub struct RHMapBuilder<H: BuildHasher + Clone> {
+    capacity: usize,
+    load_factor: f32,
+    shrink_threshold: Option<f32>,
+    growth: Option<Box<dyn GrowthPolicy + Send + Sync>>,
+    hasher_builder: H,
+}
+
+impl RHMapBuilder<FxBuildHasher> {
+    /// Creates a builder for an empty map with the default Fx Hasher and a load factor of 0.75.
+    pub fn new() -> Self {
+        Self {
+            capacity: 0,
+            load_factor: DEFAULT_LOAD_FACTOR,
+            shrink_threshold: None,
+            growth: None,
+            hasher_builder: FxBuildHasher::new(),
+        }
+    }
+}
+
+impl Default for RHMapBuilder<FxBuildHasher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: BuildHasher + Clone> RHMapBuilder<H> {
+    /// Sets the number of entries the map can hold before it first has to grow, under the configured load factor.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets the fraction of slots that may be occupied before the map grows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `load_factor` is outside of `MIN_LOAD_FACTOR..=MAX_LOAD_FACTOR`.
+    pub fn load_factor(mut self, load_factor: f32) -> Self {
+        check_load_factor(load_factor);
+        self.load_factor = load_factor;
+        self
+    }
+
+    /// Turns on automatic shrinking once occupancy drops below `threshold`, see `RHMap::set_shrink_threshold`.
+    pub fn shrink_threshold(mut self, threshold: f32) -> Self {
+        self.shrink_threshold = Some(threshold);
+        self
+    }
+
+    /// Sets the policy deciding how far the table grows once it's full, instead of doubling.
+    pub fn growth_policy<P: GrowthPolicy + Send + Sync + 'static>(mut self, policy: P) -> Self {
+        self.growth = Some(Box::new(policy));
+        self
+    }
+
+    /// Replaces the hasher builder.
+    pub fn hasher<H2: BuildHasher + Clone>(self, hasher_builder: H2) -> RHMapBuilder<H2> {
+        RHMapBuilder {
+            capacity: self.capacity,
+            load_factor: self.load_factor,
+            shrink_threshold: self.shrink_threshold,
+            growth: self.growth,
+            hasher_builder,
+        }
+    }
+
+    /// Creates the configured map.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the shrink threshold isn't between 0 and the load factor.
+    pub fn build<K: Hash + Eq, V>(self) -> RHMap<K, V, H> {
+        let mut map = RHMap::with_hasher(self.hasher_builder);
+        map.set_load_factor(self.load_factor);
+        map.set_shrink_threshold(self.shrink_threshold);
+        if let Some(policy) = self.growth {
+            map.set_growth_policy(policy);
+        }
+        map.reserve(self.capacity);
+        map
+    }
+}
+
+impl<H: BuildHasher + Clone + fmt::Debug> fmt::Debug for RHMapBuilder<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RHMapBuilder")
+            .field("capacity", &self.capacity)
+            .field("load_factor", &self.load_factor)
+            .field("shrink_threshold", &self.shrink_threshold)
+            .field("custom_growth", &self.growth.is_some())
+            .field("hasher_builder", &self.hasher_builder)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::RandomState;
+
+    #[test]
+    fn it_builds_maps_that_respect_the_load_factor() {
+        let mut map = RHMapBuilder::new()
+            .capacity(16)
+            .load_factor(0.5)
+            .hasher(RandomState::new())
+            .build();
+        assert_eq!(map.load_factor(), 0.5);
+
+        // Half of 32 slots may be occupied, the 17th insert grows the table.
+        assert_eq!(map.bucket_count(), 32);
+        for x in 0..16 {
+            map.insert(x, x);
+        }
+        assert_eq!(map.bucket_count(), 32);
+        map.insert(16, 16);
+        assert_eq!(map.bucket_count(), 64);
+        assert_eq!(map.capacity(), 32);
+        assert!((0..17).all(|x| map.get(&x) == Some(&x)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn it_rejects_a_full_load_factor() {
+        RHMapBuilder::new().load_factor(1.0);
+    }
+}
@@ -0,0 +1,204 @@
+/// Control byte of a vacant slot. Occupied slots store a 7-bit fragment of their hash instead, so the high bit alone
+/// tells the two apart.
+pub(crate) const EMPTY: u8 = 0x80;
+
+/// Control byte of an occupied slot whose entry still has to be moved while the table grows in place. Never left
+/// behind once the resize is done.
+pub(crate) const PENDING: u8 = 0xfe;
+
+/// Number of control bytes scanned by a single group comparison.
+pub(crate) const GROUP_WIDTH: usize = 16;
+
+/// The name of the instruction set groups are compared with in this build.
+pub(crate) const GROUP_IMPL: &str = imp::NAME;
+
+/// The 7-bit hash fragment kept in the control byte of an occupied slot. Slots are picked with the low bits of the
+/// hash, so the fragment is taken from the top bits to stay independent of the slot.
+pub(crate) fn h2(hash: usize) -> u8 {
+    (hash >> (usize::BITS - 7)) as u8
+}
+
+/// Whether `prefetch` actually issues prefetch instructions in this build.
+pub(crate) const PREFETCH: bool = cfg!(all(feature = "prefetch", target_arch = "x86_64"));
+
+/// Hints the CPU to start loading the cache line at `ptr`, so a probe that follows finds it in cache. Prefetching
+/// never faults, whatever the pointer. A no-op unless the `prefetch` feature is on and the target has a stable
+/// prefetch instruction.
+#[inline(always)]
+pub(crate) fn prefetch<T>(ptr: *const T) {
+    #[cfg(all(feature = "prefetch", target_arch = "x86_64"))]
+    // SAFETY: SSE is part of the x86_64 baseline and prefetches don't dereference the pointer.
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr as *const i8);
+    }
+
+    #[cfg(not(all(feature = "prefetch", target_arch = "x86_64")))]
+    let _ = ptr;
+}
+
+/// The positions within a group that matched a comparison, yielded in ascending order.
+#[derive(Clone, Copy)]
+pub(crate) struct BitMask(u64);
+
+impl BitMask {
+    /// The first matching position, if any.
+    pub(crate) fn lowest(self) -> Option<usize> {
+        match self.0 {
+            0 => None,
+            bits => Some(bits.trailing_zeros() as usize / imp::STRIDE),
+        }
+    }
+}
+
+impl Iterator for BitMask {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let lowest = self.lowest()?;
+        // Clears the lowest set bit.
+        self.0 &= self.0 - 1;
+        Some(lowest)
+    }
+}
+
+/// `GROUP_WIDTH` consecutive control bytes, compared all at once with SSE2 on x86_64 and NEON on aarch64, or one at
+/// a time everywhere else.
+pub(crate) struct Group(imp::Raw);
+
+impl Group {
+    /// Loads the control bytes starting at `pos`, wrapping around the end of a table whose length is `mask + 1`.
+    pub(crate) fn load(ctrl: &[u8], pos: usize, mask: usize) -> Self {
+        if pos + GROUP_WIDTH <= ctrl.len() {
+            return Group(imp::load(&ctrl[pos..pos + GROUP_WIDTH]));
+        }
+
+        let mut bytes = [EMPTY; GROUP_WIDTH];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = ctrl[(pos + i) & mask];
+        }
+        Group(imp::load(&bytes))
+    }
+
+    /// The positions holding exactly `byte`.
+    pub(crate) fn match_byte(&self, byte: u8) -> BitMask {
+        BitMask(imp::match_byte(&self.0, byte))
+    }
+
+    /// The positions of vacant slots.
+    pub(crate) fn match_empty(&self) -> BitMask {
+        self.match_byte(EMPTY)
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+mod imp {
+    use super::GROUP_WIDTH;
+    use core::arch::x86_64::{
+        __m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8,
+    };
+
+    pub(super) type Raw = __m128i;
+
+    pub(super) const NAME: &str = "sse2";
+
+    /// `_mm_movemask_epi8` produces one bit per byte.
+    pub(super) const STRIDE: usize = 1;
+
+    pub(super) fn load(bytes: &[u8]) -> Raw {
+        debug_assert_eq!(bytes.len(), GROUP_WIDTH);
+        // SAFETY: the slice holds 16 bytes and the load is unaligned. SSE2 is enabled for this target.
+        unsafe { _mm_loadu_si128(bytes.as_ptr() as *const __m128i) }
+    }
+
+    pub(super) fn match_byte(group: &Raw, byte: u8) -> u64 {
+        // SAFETY: SSE2 is enabled for this target.
+        unsafe {
+            _mm_movemask_epi8(_mm_cmpeq_epi8(*group, _mm_set1_epi8(byte as i8))) as u16 as u64
+        }
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod imp {
+    use super::GROUP_WIDTH;
+    use core::arch::aarch64::{
+        uint8x16_t, vceqq_u8, vdupq_n_u8, vget_lane_u64, vld1q_u8, vreinterpret_u64_u8,
+        vreinterpretq_u16_u8, vshrn_n_u16,
+    };
+
+    pub(super) type Raw = uint8x16_t;
+
+    pub(super) const NAME: &str = "neon";
+
+    /// NEON has no movemask, so the comparison is narrowed into 4 bits per byte instead.
+    pub(super) const STRIDE: usize = 4;
+
+    pub(super) fn load(bytes: &[u8]) -> Raw {
+        debug_assert_eq!(bytes.len(), GROUP_WIDTH);
+        // SAFETY: the slice holds 16 bytes. NEON is enabled for this target.
+        unsafe { vld1q_u8(bytes.as_ptr()) }
+    }
+
+    pub(super) fn match_byte(group: &Raw, byte: u8) -> u64 {
+        // SAFETY: NEON is enabled for this target.
+        unsafe {
+            let eq = vceqq_u8(*group, vdupq_n_u8(byte));
+            // Every matching byte is 0xff, shifting each 16-bit lane right by 4 and narrowing keeps a nibble per byte.
+            let nibbles = vshrn_n_u16(vreinterpretq_u16_u8(eq), 4);
+            // Keep a single bit per nibble so clearing the lowest set bit drops a whole position.
+            vget_lane_u64(vreinterpret_u64_u8(nibbles), 0) & 0x1111_1111_1111_1111
+        }
+    }
+}
+
+#[cfg(not(any(
+    all(target_arch = "x86_64", target_feature = "sse2"),
+    all(target_arch = "aarch64", target_feature = "neon")
+)))]
+mod imp {
+    use super::GROUP_WIDTH;
+
+    pub(super) type Raw = [u8; GROUP_WIDTH];
+
+    pub(super) const NAME: &str = "scalar";
+
+    pub(super) const STRIDE: usize = 1;
+
+    pub(super) fn load(bytes: &[u8]) -> Raw {
+        let mut raw = [0; GROUP_WIDTH];
+        raw.copy_from_slice(bytes);
+        raw
+    }
+
+    pub(super) fn match_byte(group: &Raw, byte: u8) -> u64 {
+        group
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == byte)
+            .fold(0, |bits, (i, _)| bits | 1 << i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_matches_control_bytes_in_groups() {
+        let mut ctrl = vec![EMPTY; 8];
+        ctrl[1] = 0x11;
+        ctrl[6] = 0x11;
+        ctrl[7] = 0x22;
+
+        // Starting at slot 6 wraps around an 8 slot table, visiting it twice.
+        let group = Group::load(&ctrl, 6, 7);
+        assert_eq!(
+            group.match_byte(0x11).collect::<Vec<_>>(),
+            vec![0, 3, 8, 11]
+        );
+        assert_eq!(group.match_byte(0x22).lowest(), Some(1));
+        assert_eq!(group.match_empty().lowest(), Some(2));
+        assert_eq!(group.match_byte(0x33).lowest(), None);
+    }
+}
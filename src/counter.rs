@@ -0,0 +1,186 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use alloc::vec::Vec;
+use core::{
+    cmp::Reverse,
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+};
+
+/// A `Counter` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxCounter<K> = Counter<K, FxBuildHasher>;
+
+/// A multiset counting how many times each key was seen. Only keys with a count above zero are stored, so `len` is
+/// the number of distinct keys seen and a key counted back down to zero disappears.
+pub struct Counter<K: Hash + Eq, H: BuildHasher + Clone> {
+    map: RHMap<K, usize, H>,
+}
+
+impl<K: Hash + Eq> Counter<K, FxBuildHasher> {
+    /// Creates an empty `Counter` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, H: BuildHasher + Clone + Default> Default for Counter<K, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq, H: BuildHasher + Clone> Counter<K, H> {
+    /// Creates an empty `Counter` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            map: RHMap::with_hasher(hasher_builder),
+        }
+    }
+
+    /// Adds `n` to the count of `key`. Adding 0 leaves the counter untouched.
+    pub fn add(&mut self, key: K, n: usize) {
+        if n > 0 {
+            *self.map.get_or_insert_with(key, || 0) += n;
+        }
+    }
+
+    /// Adds one to the count of `key`.
+    pub fn increment(&mut self, key: K) {
+        self.add(key, 1);
+    }
+
+    /// Takes up to `n` off the count of `key`, removing the key once it reaches zero. Returns what was actually taken
+    /// off.
+    pub fn remove_n<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q, n: usize) -> usize {
+        let count = match self.map.get_mut(key) {
+            Some(count) => count,
+            None => return 0,
+        };
+        if *count > n {
+            *count -= n;
+            return n;
+        }
+
+        let taken = *count;
+        self.map.remove(key).ok();
+        taken
+    }
+
+    /// Gets the count of `key`, 0 if it was never seen.
+    pub fn count<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> usize {
+        self.map.get(key).copied().unwrap_or(0)
+    }
+
+    /// Gets the `k` keys with the highest counts, highest first. Keys with equal counts come in arbitrary order.
+    pub fn most_common(&self, k: usize) -> Vec<(&K, usize)> {
+        let mut counts: Vec<(&K, usize)> = self.iter().collect();
+        if k < counts.len() {
+            // Only the top `k` need sorting.
+            counts.select_nth_unstable_by_key(k, |&(_, count)| Reverse(count));
+            counts.truncate(k);
+        }
+        counts.sort_unstable_by_key(|&(_, count)| Reverse(count));
+        counts
+    }
+
+    /// Adds every count of `other` to this counter.
+    pub fn merge(&mut self, other: Self) {
+        for (key, count) in other.map {
+            self.add(key, count);
+        }
+    }
+
+    /// Takes every count of `other` off this counter, dropping keys that reach zero. Counts never go negative.
+    pub fn subtract(&mut self, other: &Self) {
+        for (key, count) in other.iter() {
+            self.remove_n(key, count);
+        }
+    }
+
+    /// Gets the sum of all counts.
+    pub fn total(&self) -> usize {
+        self.map.values().sum()
+    }
+
+    /// Clears all counts but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Gets the number of distinct keys with a count above zero.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if nothing has been counted.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Iterates over every key and its count in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, usize)> + '_ {
+        self.map.iter().map(|(key, &count)| (key, count))
+    }
+}
+
+/// Formats the counter like the std maps do (`{key: count, ...}`).
+impl<K: Hash + Eq + Debug, H: BuildHasher + Clone> Debug for Counter<K, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Counts every key once per occurrence.
+impl<K: Hash + Eq, H: BuildHasher + Clone> Extend<K> for Counter<K, H> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, keys: I) {
+        for key in keys {
+            self.increment(key);
+        }
+    }
+}
+
+impl<K: Hash + Eq, H: BuildHasher + Clone + Default> FromIterator<K> for Counter<K, H> {
+    fn from_iter<I: IntoIterator<Item = K>>(keys: I) -> Self {
+        let mut counter = Self::default();
+        counter.extend(keys);
+        counter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_counts_the_most_common_keys() {
+        let words: FxCounter<&str> = "the cat and the dog and the bird".split(' ').collect();
+
+        assert_eq!(words.count("the"), 3);
+        assert_eq!(words.count("fish"), 0);
+        assert_eq!(words.most_common(2), vec![(&"the", 3), (&"and", 2)]);
+        assert_eq!(words.most_common(10).len(), 5);
+        assert_eq!(words.total(), 8);
+    }
+
+    #[test]
+    fn it_merges_and_subtracts_counters() {
+        let mut a = FxCounter::new();
+        a.add('x', 5);
+        a.add('y', 1);
+        let mut b = FxCounter::new();
+        b.add('x', 2);
+        b.add('z', 4);
+
+        a.merge(b);
+        assert_eq!((a.count(&'x'), a.count(&'z')), (7, 4));
+
+        let c: FxCounter<char> = vec!['x', 'y', 'y', 'z'].into_iter().collect();
+        a.subtract(&c);
+        assert_eq!((a.count(&'x'), a.count(&'y'), a.count(&'z')), (6, 0, 3));
+        assert_eq!(a.len(), 2);
+        assert_eq!(a.remove_n(&'z', 10), 3);
+        assert_eq!(a.len(), 1);
+    }
+}
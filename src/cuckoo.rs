@@ -0,0 +1,407 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::map_entry::Entry;
+use super::traits::{MapRead, MapWrite};
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    mem,
+};
+
+/// Slots per bucket. Four slots per bucket let cuckoo tables run at loads over 90% before inserts start failing.
+const BUCKET_SIZE: usize = 4;
+const INITIAL_BUCKETS: usize = 2;
+/// How many entries an insert may displace before giving up on the table and stashing the one left over.
+const MAX_KICKS: usize = 256;
+/// How many entries the stash holds before the table has to grow.
+const STASH_SIZE: usize = 4;
+/// The table grows once this fraction of its slots are occupied.
+const MAX_LOAD: f64 = 0.9;
+
+/// A `CuckooMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxCuckooMap<K, V> = CuckooMap<K, V, FxBuildHasher>;
+
+/// A cuckoo hash map: every key can only live in one of two buckets (of four slots each), picked by two hash
+/// functions, or in a small stash. A lookup therefore inspects at most `2 * 4 + 4` entries however full the table is,
+/// at the cost of inserts that may have to displace entries into their other bucket to make room.
+///
+/// Prefer it over `RHMap` when the worst case lookup matters more than insert throughput. Both hash keys with the
+/// map's `BuildHasher` once and store the hash in the same kind of entry; the two buckets come from the two halves of
+/// that hash.
+pub struct CuckooMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    slots: Vec<Option<Entry<K, V>>>,
+    /// Entries that found no room in either bucket.
+    stash: Vec<Entry<K, V>>,
+    /// `number of buckets - 1`. The bucket count is always a power of two.
+    mask: usize,
+    num_items: usize,
+    hasher_builder: H,
+}
+
+impl<K: Hash + Eq, V> CuckooMap<K, V, FxBuildHasher> {
+    /// Creates an empty `CuckooMap` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> Default for CuckooMap<K, V, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> CuckooMap<K, V, H> {
+    /// Creates an empty `CuckooMap` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            slots: Vec::new(),
+            stash: Vec::new(),
+            mask: 0,
+            num_items: 0,
+            hasher_builder,
+        }
+    }
+
+    /// Inserts a key value pair, returning the previous value of the key, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hasher_builder.hash_one(&key) as usize;
+        if let Some(entry) = self.find_mut(hash, &key) {
+            return Some(mem::replace(&mut entry.value, value));
+        }
+
+        if (self.num_items + 1) as f64 > self.slots.len() as f64 * MAX_LOAD {
+            self.grow();
+        }
+        self.num_items += 1;
+        self.place(Entry::new(key, value, hash));
+        None
+    }
+
+    /// Gets the value associated with the key.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    /// Gets the stored key and the value associated with the key.
+    pub fn get_key_value<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<(&K, &V)> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        let entry = self.probe(hash, key).0?;
+        Some((&entry.key, &entry.value))
+    }
+
+    /// Gets a mutable reference to the value associated with the key.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&mut V> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        self.find_mut(hash, key).map(|entry| &mut entry.value)
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.get_key_value(key).is_some()
+    }
+
+    /// Removes the entry with the given key and returns its value, if there was one.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        let is_match = |entry: &Entry<K, V>| entry.hash == hash && key.equivalent(&entry.key);
+        if let Some(i) = self.stash.iter().position(is_match) {
+            self.num_items -= 1;
+            return Some(self.stash.swap_remove(i).value);
+        }
+        if self.slots.is_empty() {
+            return None;
+        }
+
+        let (first, second) = self.buckets_of(hash);
+        let i = [first, second].iter().find_map(|&bucket| {
+            (bucket * BUCKET_SIZE..(bucket + 1) * BUCKET_SIZE)
+                .find(|&i| matches!(&self.slots[i], Some(entry) if is_match(entry)))
+        })?;
+        let removed = self.slots[i].take()?;
+        self.num_items -= 1;
+
+        // The freed slot may be one a stashed entry can move back into.
+        if let Some(j) = self.stash.iter().position(|entry| {
+            let (first, second) = self.buckets_of(entry.hash);
+            i / BUCKET_SIZE == first || i / BUCKET_SIZE == second
+        }) {
+            self.slots[i] = Some(self.stash.swap_remove(j));
+        }
+        Some(removed.value)
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+        self.stash.clear();
+        self.num_items = 0;
+    }
+
+    /// Gets the length / number of entries of the map.
+    pub fn len(&self) -> usize {
+        self.num_items
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+
+    /// Gets the number of entries the map can hold before it has to grow.
+    pub fn capacity(&self) -> usize {
+        (self.slots.len() as f64 * MAX_LOAD) as usize
+    }
+
+    /// Gets the number of entries that found no room in either of their buckets.
+    pub fn stash_len(&self) -> usize {
+        self.stash.len()
+    }
+
+    /// Gets a reference to the hasher builder.
+    pub fn hasher(&self) -> &H {
+        &self.hasher_builder
+    }
+
+    /// Iterates over every key value pair in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.slots
+            .iter()
+            .flatten()
+            .chain(&self.stash)
+            .map(|entry| (&entry.key, &entry.value))
+    }
+
+    /// The two buckets an entry with the given hash may live in: one from the low half of the hash, one from the
+    /// high half.
+    fn buckets_of(&self, hash: usize) -> (usize, usize) {
+        let high = hash.rotate_right(usize::BITS / 2);
+        (hash & self.mask, high & self.mask)
+    }
+
+    fn bucket(&self, bucket: usize) -> &[Option<Entry<K, V>>] {
+        &self.slots[bucket * BUCKET_SIZE..(bucket + 1) * BUCKET_SIZE]
+    }
+
+    /// Looks the key up in both of its buckets and then in the stash. Also returns the number of slots inspected.
+    fn probe<Q: ?Sized + Equivalent<K>>(
+        &self,
+        hash: usize,
+        key: &Q,
+    ) -> (Option<&Entry<K, V>>, usize) {
+        if self.slots.is_empty() {
+            return (None, 0);
+        }
+
+        let (first, second) = self.buckets_of(hash);
+        let buckets = [first, second];
+        let buckets = &buckets[..if first == second { 1 } else { 2 }];
+        let slots = buckets
+            .iter()
+            .flat_map(|&bucket| self.bucket(bucket))
+            .map(Option::as_ref)
+            .chain(self.stash.iter().map(Some));
+        let mut probes = 0;
+        for slot in slots {
+            probes += 1;
+            if matches!(slot, Some(entry) if entry.hash == hash && key.equivalent(&entry.key)) {
+                return (slot, probes);
+            }
+        }
+        (None, probes)
+    }
+
+    fn find_mut<Q: ?Sized + Equivalent<K>>(
+        &mut self,
+        hash: usize,
+        key: &Q,
+    ) -> Option<&mut Entry<K, V>> {
+        let is_match = |entry: &&mut Entry<K, V>| entry.hash == hash && key.equivalent(&entry.key);
+        if self.slots.is_empty() {
+            return None;
+        }
+
+        let (first, second) = self.buckets_of(hash);
+        let (first, second) = (first * BUCKET_SIZE, second * BUCKET_SIZE);
+        if first == second {
+            return self.slots[first..first + BUCKET_SIZE]
+                .iter_mut()
+                .flatten()
+                .chain(&mut self.stash)
+                .find(is_match);
+        }
+
+        // Splits the slots so both buckets can be borrowed at once.
+        let (low, high) = (first.min(second), first.max(second));
+        let (head, tail) = self.slots.split_at_mut(high);
+        head[low..low + BUCKET_SIZE]
+            .iter_mut()
+            .chain(&mut tail[..BUCKET_SIZE])
+            .flatten()
+            .chain(&mut self.stash)
+            .find(is_match)
+    }
+
+    /// Puts a new entry into one of its buckets, displacing entries into their other bucket as needed. Whatever is
+    /// left over after `MAX_KICKS` displacements goes to the stash, or the table grows when the stash is full.
+    fn place(&mut self, mut entry: Entry<K, V>) {
+        let (mut bucket, second) = self.buckets_of(entry.hash);
+        if let Some(i) = self.free_slot(bucket).or_else(|| self.free_slot(second)) {
+            self.slots[i] = Some(entry);
+            return;
+        }
+
+        for kick in 0..MAX_KICKS {
+            // Rotates through the slots of a bucket so a displacement chain doesn't bounce between two entries.
+            let i = bucket * BUCKET_SIZE + (kick + entry.hash) % BUCKET_SIZE;
+            let victim = self.slots[i]
+                .replace(entry)
+                .expect("full buckets have no vacant slots");
+            let (first, second) = self.buckets_of(victim.hash);
+            bucket = if first == bucket { second } else { first };
+            if let Some(j) = self.free_slot(bucket) {
+                self.slots[j] = Some(victim);
+                return;
+            }
+            entry = victim;
+        }
+
+        if self.stash.len() < STASH_SIZE {
+            self.stash.push(entry);
+        } else {
+            self.grow();
+            self.place(entry);
+        }
+    }
+
+    fn free_slot(&self, bucket: usize) -> Option<usize> {
+        let start = bucket * BUCKET_SIZE;
+        (start..start + BUCKET_SIZE).find(|&i| self.slots[i].is_none())
+    }
+
+    /// Doubles the number of buckets and puts every entry, stashed ones included, back in.
+    fn grow(&mut self) {
+        let buckets = if self.slots.is_empty() {
+            INITIAL_BUCKETS
+        } else {
+            (self.mask + 1) * 2
+        };
+        let old_slots = mem::replace(
+            &mut self.slots,
+            (0..buckets * BUCKET_SIZE).map(|_| None).collect(),
+        );
+        let old_stash = mem::take(&mut self.stash);
+        self.mask = buckets - 1;
+
+        for entry in old_slots.into_iter().flatten().chain(old_stash) {
+            self.place(entry);
+        }
+    }
+}
+
+/// Formats the map like the std maps do (`{key: value, ...}`).
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone> Debug for CuckooMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> Extend<(K, V)> for CuckooMap<K, V, H> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        for (key, value) in pairs {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> FromIterator<(K, V)>
+    for CuckooMap<K, V, H>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let mut map = Self::default();
+        map.extend(pairs);
+        map
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> MapRead<K, V> for CuckooMap<K, V, H> {
+    fn get(&self, key: &K) -> Option<&V> {
+        CuckooMap::get(self, key)
+    }
+
+    fn len(&self) -> usize {
+        self.num_items
+    }
+
+    fn probe_count(&self, key: &K) -> usize {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        self.probe(hash, key).1
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> MapWrite<K, V> for CuckooMap<K, V, H> {
+    fn insert(&mut self, key: K, value: V) {
+        CuckooMap::insert(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        CuckooMap::remove(self, key).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_inserts_gets_and_removes() {
+        let mut map = FxCuckooMap::new();
+        assert_eq!(map.insert("a", 1), None);
+        assert_eq!(map.insert("a", 2), Some(1));
+        map.insert("b", 3);
+
+        assert_eq!(map.get("a"), Some(&2));
+        *map.get_mut("b").unwrap() += 1;
+        assert_eq!(map.remove("b"), Some(4));
+        assert_eq!(map.remove("b"), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn it_keeps_every_key_findable_at_high_load() {
+        let mut map: FxCuckooMap<String, usize> =
+            (0..10_000).map(|x| (format!("key{}", x), x)).collect();
+
+        assert_eq!(map.len(), 10_000);
+        assert!(map.len() as f64 > map.capacity() as f64 * 0.4);
+        assert!((0..10_000).all(|x| map.get(format!("key{}", x).as_str()) == Some(&x)));
+        for x in (0..10_000).step_by(2) {
+            assert_eq!(map.remove(format!("key{}", x).as_str()), Some(x));
+        }
+        assert_eq!(map.iter().count(), 5_000);
+        assert!((1..10_000)
+            .step_by(2)
+            .all(|x| map.contains_key(format!("key{}", x).as_str())));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_reports_probes_to_bench_contexts() {
+        use crate::bench::BenchContext;
+
+        let mut bench = BenchContext::new(FxCuckooMap::new());
+        for x in 0..1_000u32 {
+            bench.insert(x, x);
+        }
+        assert_eq!(bench.get(&500), Some(&500));
+        assert!(!bench.contains_key(&5_000));
+        assert!(bench.remove(&500));
+
+        // Two buckets and the stash are all a lookup ever inspects.
+        let (map, report) = bench.finish();
+        assert!((0..2_000).all(|x| MapRead::probe_count(&map, &x) <= 2 * BUCKET_SIZE + STASH_SIZE));
+        assert!(report.mean_probes() >= 1.0);
+    }
+}
@@ -0,0 +1,673 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::{CasError, RHMap};
+use super::map_entry::Entry as Slot;
+use std::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    mem,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    thread,
+};
+
+/// A `DashMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxDashMap<K, V> = DashMap<K, V, FxBuildHasher>;
+
+type Shard<K, V, H> = RwLock<RHMap<K, V, H>>;
+type ReadGuard<'a, K, V, H> = RwLockReadGuard<'a, RHMap<K, V, H>>;
+type WriteGuard<'a, K, V, H> = RwLockWriteGuard<'a, RHMap<K, V, H>>;
+/// A read guard shared by an iterator and the entries it yielded from one shard.
+type SharedGuard<'a, K, V, H> = Rc<ReadGuard<'a, K, V, H>>;
+
+/// A concurrent map that can be shared between threads (e.g. in an `Arc`) and updated through `&self`. Keys are
+/// spread over a power of two number of `RHMap` shards, each behind its own `RwLock`, so threads only contend when
+/// they touch the same shard.
+///
+/// The guards returned by `get`, `get_mut`, `entry` and `iter` keep their shard locked. Holding one while updating the
+/// map from the same thread can deadlock if the update lands in that shard.
+pub struct DashMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    shards: Box<[Shard<K, V, H>]>,
+    /// `shard_count - 1`, masking the hash bits that pick a shard.
+    mask: usize,
+    hasher_builder: H,
+}
+
+/// Where the bits picking a shard start in the hash.
+const SHARD_HASH_SHIFT: u32 = 32;
+
+/// A shard count of four per core, so that even with every core busy two threads rarely meet in a shard.
+fn default_shard_count() -> usize {
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    (cores * 4).next_power_of_two()
+}
+
+impl<K: Hash + Eq, V> DashMap<K, V, FxBuildHasher> {
+    /// Creates an empty `DashMap` with the default Fx Hasher and four shards per core.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+
+    /// Creates an empty `DashMap` with the default Fx Hasher and the given number of shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard_count` isn't a power of two.
+    pub fn with_shard_count(shard_count: usize) -> Self {
+        Self::with_shard_count_and_hasher(shard_count, FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> Default for DashMap<K, V, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> DashMap<K, V, H> {
+    /// Creates an empty `DashMap` with four shards per core and a custom hasher builder which overrides the default
+    /// fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self::with_shard_count_and_hasher(default_shard_count(), hasher_builder)
+    }
+
+    /// Creates an empty `DashMap` with the given number of shards and a custom hasher builder.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shard_count` isn't a power of two.
+    pub fn with_shard_count_and_hasher(shard_count: usize, hasher_builder: H) -> Self {
+        assert!(
+            shard_count.is_power_of_two(),
+            "DashMap shard count must be a power of two"
+        );
+        let shards = (0..shard_count)
+            .map(|_| RwLock::new(RHMap::with_hasher(hasher_builder.clone())))
+            .collect();
+
+        Self {
+            shards,
+            mask: shard_count - 1,
+            hasher_builder,
+        }
+    }
+
+    /// Inserts a key value pair, returning the previous value of the key, if any.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let (hash, shard) = self.shard_of(&key);
+        let mut shard = write(shard);
+        match shard.probe_hashed(hash, &key).0 {
+            Some(i) => Some(mem::replace(&mut entry_at_mut(&mut shard, i).value, value)),
+            None => {
+                shard.insert_vacant(hash, key, value);
+                None
+            }
+        }
+    }
+
+    /// Gets the value associated with the key. Its shard stays read locked until the returned guard is dropped.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<Ref<'_, K, V, H>> {
+        let (hash, shard) = self.shard_of(key);
+        let guard = read(shard);
+        let index = guard.probe_hashed(hash, key).0?;
+        Some(Ref { guard, index })
+    }
+
+    /// Gets the value associated with the key for writing. Its shard stays write locked until the returned guard is
+    /// dropped.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(
+        &self,
+        key: &Q,
+    ) -> Option<RefMut<'_, K, V, H>> {
+        let (hash, shard) = self.shard_of(key);
+        let guard = write(shard);
+        let index = guard.probe_hashed(hash, key).0?;
+        Some(RefMut { guard, index })
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        let (hash, shard) = self.shard_of(key);
+        read(shard).probe_hashed(hash, key).0.is_some()
+    }
+
+    /// Removes the entry with the given key and returns it, if there was one.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<(K, V)> {
+        let (hash, shard) = self.shard_of(key);
+        write(shard).remove_entry_hashed(hash, key)
+    }
+
+    /// Replaces the value stored under `key` with `new`, but only if it currently equals `expected`. The comparison and
+    /// the update happen under the shard's write lock, so no other thread can change the value in between.
+    pub fn compare_and_update<Q: ?Sized + Hash + Equivalent<K>>(
+        &self,
+        key: &Q,
+        expected: &V,
+        new: V,
+    ) -> Result<(), CasError>
+    where
+        V: PartialEq,
+    {
+        let (hash, shard) = self.shard_of(key);
+        write(shard).compare_and_update_hashed(hash, key, expected, new)
+    }
+
+    /// Gets the entry of the given key for in-place manipulation. Its shard stays write locked until the entry, or the
+    /// guard it turns into, is dropped.
+    pub fn entry(&self, key: K) -> Entry<'_, K, V, H> {
+        let (hash, shard) = self.shard_of(&key);
+        let guard = write(shard);
+        match guard.probe_hashed(hash, &key).0 {
+            Some(index) => Entry::Occupied(OccupiedEntry { guard, index }),
+            None => Entry::Vacant(VacantEntry { guard, key, hash }),
+        }
+    }
+
+    /// Keeps only the entries for which `f` returns `true`, write locking one shard at a time.
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&self, mut f: F) {
+        for shard in self.shards.iter() {
+            write(shard).retain(&mut f);
+        }
+    }
+
+    /// Clears all entries, write locking one shard at a time.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            write(shard).clear();
+        }
+    }
+
+    /// Gets the number of entries, read locking one shard at a time. Updates racing with the count may or may not be
+    /// included.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| read(shard).len()).sum()
+    }
+
+    /// Returns `true` if no shard holds any entries.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| read(shard).is_empty())
+    }
+
+    /// Gets the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Gets a reference to the hasher builder.
+    pub fn hasher(&self) -> &H {
+        &self.hasher_builder
+    }
+
+    /// Iterates over every entry, read locking one shard at a time: a shard is locked when the iterator reaches it and
+    /// unlocked once the iterator and every guard it yielded from that shard have moved on.
+    pub fn iter(&self) -> Iter<'_, K, V, H> {
+        Iter {
+            shards: self.shards.iter(),
+            current: None,
+        }
+    }
+
+    /// Hashes the key and picks its shard. The hash is handed back so the shard's table can probe with it instead of
+    /// hashing the key again (the shards share the map's hasher).
+    fn shard_of<Q: ?Sized + Hash>(&self, key: &Q) -> (usize, &Shard<K, V, H>) {
+        let hash = self.hasher_builder.hash_one(key);
+        // The tables index by the low bits of the hash and keep the top bits in their control bytes, so the shard is
+        // picked by bits in between that neither uses (short of tables with billions of slots).
+        let i = (hash >> SHARD_HASH_SHIFT) as usize & self.mask;
+        (hash as usize, &self.shards[i])
+    }
+}
+
+fn read<K: Hash + Eq, V, H: BuildHasher + Clone>(shard: &Shard<K, V, H>) -> ReadGuard<'_, K, V, H> {
+    shard
+        .read()
+        .expect("a thread panicked while holding a DashMap shard")
+}
+
+fn write<K: Hash + Eq, V, H: BuildHasher + Clone>(
+    shard: &Shard<K, V, H>,
+) -> WriteGuard<'_, K, V, H> {
+    shard
+        .write()
+        .expect("a thread panicked while holding a DashMap shard")
+}
+
+fn entry_at<K: Hash + Eq, V, H: BuildHasher + Clone>(
+    map: &RHMap<K, V, H>,
+    index: usize,
+) -> &Slot<K, V> {
+    map.entry_at(index)
+        .expect("slots don't move while their shard is locked")
+}
+
+fn entry_at_mut<K: Hash + Eq, V, H: BuildHasher + Clone>(
+    map: &mut RHMap<K, V, H>,
+    index: usize,
+) -> &mut Slot<K, V> {
+    map.entry_at_mut(index)
+        .expect("slots don't move while their shard is locked")
+}
+
+/// Read access to an entry of a `DashMap`, keeping its shard read locked.
+pub struct Ref<'a, K: Hash + Eq, V, H: BuildHasher + Clone> {
+    guard: ReadGuard<'a, K, V, H>,
+    index: usize,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> Ref<'a, K, V, H> {
+    /// Gets the key of the entry.
+    pub fn key(&self) -> &K {
+        &entry_at(&self.guard, self.index).key
+    }
+
+    /// Gets the value of the entry.
+    pub fn value(&self) -> &V {
+        &entry_at(&self.guard, self.index).value
+    }
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> Deref for Ref<'a, K, V, H> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.value()
+    }
+}
+
+/// Write access to an entry of a `DashMap`, keeping its shard write locked.
+pub struct RefMut<'a, K: Hash + Eq, V, H: BuildHasher + Clone> {
+    guard: WriteGuard<'a, K, V, H>,
+    index: usize,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> RefMut<'a, K, V, H> {
+    /// Gets the key of the entry.
+    pub fn key(&self) -> &K {
+        &entry_at(&self.guard, self.index).key
+    }
+
+    /// Gets the value of the entry.
+    pub fn value(&self) -> &V {
+        &entry_at(&self.guard, self.index).value
+    }
+
+    /// Gets a mutable reference to the value of the entry.
+    pub fn value_mut(&mut self) -> &mut V {
+        &mut entry_at_mut(&mut self.guard, self.index).value
+    }
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> Deref for RefMut<'a, K, V, H> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.value()
+    }
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> DerefMut for RefMut<'a, K, V, H> {
+    fn deref_mut(&mut self) -> &mut V {
+        self.value_mut()
+    }
+}
+
+/// The entry of a key in a `DashMap`, created by `DashMap::entry`. Keeps the key's shard write locked.
+pub enum Entry<'a, K: Hash + Eq, V, H: BuildHasher + Clone> {
+    Occupied(OccupiedEntry<'a, K, V, H>),
+    Vacant(VacantEntry<'a, K, V, H>),
+}
+
+/// An entry of a `DashMap` whose key is present.
+pub struct OccupiedEntry<'a, K: Hash + Eq, V, H: BuildHasher + Clone> {
+    guard: WriteGuard<'a, K, V, H>,
+    index: usize,
+}
+
+/// An entry of a `DashMap` whose key is missing.
+pub struct VacantEntry<'a, K: Hash + Eq, V, H: BuildHasher + Clone> {
+    guard: WriteGuard<'a, K, V, H>,
+    key: K,
+    hash: usize,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> Entry<'a, K, V, H> {
+    /// Gets the key of the entry.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => &entry.key,
+        }
+    }
+
+    /// Inserts `default` if the key is missing, and returns write access to the value either way.
+    pub fn or_insert(self, default: V) -> RefMut<'a, K, V, H> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Inserts the value `default` returns if the key is missing, and returns write access to the value either way.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> RefMut<'a, K, V, H> {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Inserts the default value if the key is missing, and returns write access to the value either way.
+    pub fn or_default(self) -> RefMut<'a, K, V, H>
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Runs `f` on the value if the key is present.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> OccupiedEntry<'a, K, V, H> {
+    /// Gets the key of the entry.
+    pub fn key(&self) -> &K {
+        &entry_at(&self.guard, self.index).key
+    }
+
+    /// Gets the value of the entry.
+    pub fn get(&self) -> &V {
+        &entry_at(&self.guard, self.index).value
+    }
+
+    /// Gets a mutable reference to the value of the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut entry_at_mut(&mut self.guard, self.index).value
+    }
+
+    /// Replaces the value of the entry, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the entry from the map and returns it.
+    pub fn remove_entry(mut self) -> (K, V) {
+        let entry = self.guard.remove_at(self.index);
+        (entry.key, entry.value)
+    }
+
+    /// Turns the entry into write access to its value, keeping the shard locked.
+    pub fn into_ref(self) -> RefMut<'a, K, V, H> {
+        RefMut {
+            guard: self.guard,
+            index: self.index,
+        }
+    }
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> VacantEntry<'a, K, V, H> {
+    /// Gets the key that would be inserted.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Inserts the key with the given value and returns write access to it, keeping the shard locked.
+    pub fn insert(mut self, value: V) -> RefMut<'a, K, V, H> {
+        let index = self.guard.insert_vacant(self.hash, self.key, value);
+        RefMut {
+            guard: self.guard,
+            index,
+        }
+    }
+}
+
+/// Read access to an entry yielded by `DashMap::iter`. Shares the read lock of its shard with the iterator and the
+/// other entries yielded from that shard.
+pub struct IterRef<'a, K: Hash + Eq, V, H: BuildHasher + Clone> {
+    guard: SharedGuard<'a, K, V, H>,
+    index: usize,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> IterRef<'a, K, V, H> {
+    /// Gets the key of the entry.
+    pub fn key(&self) -> &K {
+        &entry_at(&self.guard, self.index).key
+    }
+
+    /// Gets the value of the entry.
+    pub fn value(&self) -> &V {
+        &entry_at(&self.guard, self.index).value
+    }
+
+    /// Gets the key and the value of the entry.
+    pub fn pair(&self) -> (&K, &V) {
+        let entry = entry_at(&self.guard, self.index);
+        (&entry.key, &entry.value)
+    }
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> Deref for IterRef<'a, K, V, H> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.value()
+    }
+}
+
+/// Iterator over the entries of a `DashMap`, created by `DashMap::iter`.
+pub struct Iter<'a, K: Hash + Eq, V, H: BuildHasher + Clone> {
+    shards: std::slice::Iter<'a, Shard<K, V, H>>,
+    /// The shard being walked and the next slot to look at in it.
+    current: Option<(SharedGuard<'a, K, V, H>, usize)>,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> Iterator for Iter<'a, K, V, H> {
+    type Item = IterRef<'a, K, V, H>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((guard, slot)) = &mut self.current {
+                while *slot < guard.bucket_count() {
+                    let index = *slot;
+                    *slot += 1;
+                    if guard.entry_at(index).is_some() {
+                        return Some(IterRef {
+                            guard: guard.clone(),
+                            index,
+                        });
+                    }
+                }
+            }
+
+            // Drops the guard of the finished shard before locking the next one.
+            self.current = None;
+            self.current = Some((Rc::new(read(self.shards.next()?)), 0));
+        }
+    }
+}
+
+/// Formats the map like the std maps do (`{key: value, ...}`), locking one shard at a time.
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone> Debug for DashMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        for entry in self.iter() {
+            let (key, value) = entry.pair();
+            map.entry(key, value);
+        }
+        map.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn it_counts_from_many_threads() {
+        let map = Arc::new(FxDashMap::with_shard_count(8));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for x in 0..1000 {
+                        *map.entry(x % 100).or_insert(0) += 1;
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(map.len(), 100);
+        assert!(map.iter().all(|count| *count == 40));
+        assert_eq!(map.iter().map(|count| *count).sum::<i32>(), 4000);
+    }
+
+    #[test]
+    fn it_gets_inserts_and_removes() {
+        let map = FxDashMap::new();
+        assert_eq!(map.insert("a", 1), None);
+        assert_eq!(map.insert("a", 2), Some(1));
+        map.insert("b", 3);
+
+        assert_eq!(map.get("a").map(|value| *value), Some(2));
+        *map.get_mut("b").unwrap() += 10;
+        assert_eq!(*map.get("b").unwrap(), 13);
+        map.entry("b").and_modify(|value| *value = 0);
+        assert_eq!(*map.get("b").unwrap(), 0);
+        assert_eq!(map.remove("a"), Some(("a", 2)));
+        assert!(!map.contains_key("a"));
+        if let Entry::Occupied(entry) = map.entry("b") {
+            assert_eq!(entry.remove_entry(), ("b", 0));
+        }
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn it_compares_and_updates_under_the_shard_lock() {
+        let map = Arc::new(FxDashMap::with_shard_count(4));
+        map.insert("counter", 0);
+        assert_eq!(
+            map.compare_and_update("counter", &1, 2),
+            Err(CasError::Mismatch)
+        );
+        assert_eq!(
+            map.compare_and_update("missing", &0, 1),
+            Err(CasError::NotFound)
+        );
+
+        // Every thread retries until its swap lands, so no increment is lost.
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for _ in 0..500 {
+                        loop {
+                            let current = *map.get("counter").unwrap();
+                            if map
+                                .compare_and_update("counter", &current, current + 1)
+                                .is_ok()
+                            {
+                                break;
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(*map.get("counter").unwrap(), 2000);
+    }
+
+    #[test]
+    fn it_inserts_and_removes_from_many_threads() {
+        let map = Arc::new(FxDashMap::with_shard_count(4));
+        let handles: Vec<_> = (0..4u32)
+            .map(|t| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    // Every thread owns its own keys, inserts them all and takes the odd ones back out.
+                    for x in (0..1000).map(|x| x * 4 + t) {
+                        assert_eq!(map.insert(x, t), None);
+                    }
+                    for x in (0..1000).map(|x| x * 4 + t).filter(|x| x % 2 == 1) {
+                        assert_eq!(map.remove(&x), Some((x, t)));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(map.len(), 2000);
+        assert!(map.iter().all(|entry| entry.key() % 2 == 0));
+        assert_eq!(map.get(&6).map(|value| *value), Some(2));
+        assert!(!map.contains_key(&7));
+    }
+
+    #[test]
+    fn it_keeps_the_shard_locked_while_an_entry_is_held() {
+        let map = Arc::new(FxDashMap::with_shard_count(2));
+        let entry = map.entry(1);
+        assert_eq!(*entry.key(), 1);
+
+        // A reader of the same key has to wait for the entry to be filled in and dropped.
+        let reader = {
+            let map = map.clone();
+            thread::spawn(move || loop {
+                if let Some(value) = map.get(&1) {
+                    return *value;
+                }
+                thread::yield_now();
+            })
+        };
+        let mut value = entry.or_insert(10);
+        *value += 5;
+        drop(value);
+        assert_eq!(reader.join().unwrap(), 15);
+
+        match map.entry(1) {
+            Entry::Occupied(mut entry) => assert_eq!(entry.insert(20), 15),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(*map.entry(2).or_default(), 0);
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn it_retains_and_iterates_across_shards() {
+        let map = FxDashMap::with_shard_count(8);
+        for x in 0..1000 {
+            map.insert(x, x * 2);
+        }
+        map.retain(|key, value| {
+            *value += 1;
+            key % 3 == 0
+        });
+
+        assert_eq!(map.len(), 334);
+        let mut keys: Vec<_> = map.iter().map(|entry| *entry.key()).collect();
+        keys.sort_unstable();
+        assert!(keys.iter().copied().eq((0..1000).step_by(3)));
+        assert!(map
+            .iter()
+            .all(|entry| *entry.value() == entry.key() * 2 + 1));
+        // Iterating locks one shard at a time, so keys of the other shards stay writable meanwhile.
+        let mut iter = map.iter();
+        let first = iter.next().unwrap();
+        let locked = map.shard_of(first.key()).1;
+        let other = (1000..).find(|x| !std::ptr::eq(map.shard_of(x).1, locked));
+        map.insert(other.unwrap(), 0);
+        drop(first);
+        drop(iter);
+        assert_eq!(map.len(), 335);
+        map.clear();
+        assert!(map.is_empty());
+    }
+}
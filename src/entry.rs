@@ -0,0 +1,304 @@
+use super::hashmap::RHMap;
+use allocator_api2::alloc::{Allocator, Global};
+use core::{
+    borrow::Borrow,
+    error::Error,
+    fmt::{self, Debug, Display},
+    hash::{BuildHasher, Hash},
+    mem,
+};
+
+/// A view into an occupied entry of a `RHMap`.
+pub struct OccupiedEntry<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone = Global>
+{
+    map: &'a mut RHMap<K, V, H, A>,
+    /// Slot index of the entry. Valid for as long as we hold the mutable borrow of the map.
+    index: usize,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone>
+    OccupiedEntry<'a, K, V, H, A>
+{
+    pub(crate) fn new(map: &'a mut RHMap<K, V, H, A>, index: usize) -> Self {
+        Self { map, index }
+    }
+
+    /// Gets a reference to the key in the entry.
+    pub fn key(&self) -> &K {
+        &self.map.entry_at(self.index).unwrap().key
+    }
+
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        &self.map.entry_at(self.index).unwrap().value
+    }
+
+    /// Gets a mutable reference to the value in the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.entry_at_mut(self.index).unwrap().value
+    }
+
+    /// Converts the entry into a mutable reference to its value with the lifetime of the map.
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.entry_at_mut(self.index).unwrap().value
+    }
+
+    /// Sets the value of the entry and returns the old value.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Takes the value out of the map, removing the entry.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Takes the key and value out of the map, removing the entry.
+    pub fn remove_entry(self) -> (K, V) {
+        let entry = self.map.remove_at(self.index);
+        self.map.shrink_if_needed();
+        (entry.key, entry.value)
+    }
+}
+
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone, A: Allocator + Clone> Debug
+    for OccupiedEntry<'_, K, V, H, A>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("key", self.key())
+            .field("value", self.get())
+            .finish()
+    }
+}
+
+/// A view into a single entry of a `RHMap` looked up by an owned key, created by `RHMap::entry`.
+pub enum Entry<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone = Global> {
+    Occupied(OccupiedEntry<'a, K, V, H, A>),
+    Vacant(VacantEntry<'a, K, V, H, A>),
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone> Entry<'a, K, V, H, A> {
+    /// Ensures a value is in the entry by inserting the default if empty, and returns a mutable reference to it.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Ensures a value is in the entry by inserting the result of `default` if empty, and returns a mutable
+    /// reference to it.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Like `or_insert_with`, but `default` gets to see the key it's building a value for.
+    pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting `V::default()` if empty, and returns a mutable reference to it.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+
+        self
+    }
+
+    /// Gets a reference to the key of the entry.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+/// A view into a vacant entry of a `RHMap` looked up by an owned key, which is kept until a value is inserted.
+pub struct VacantEntry<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone = Global> {
+    map: &'a mut RHMap<K, V, H, A>,
+    /// The hash of `key`, reused when inserting so the key doesn't need to be hashed again.
+    hash: usize,
+    key: K,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone>
+    VacantEntry<'a, K, V, H, A>
+{
+    pub(crate) fn new(map: &'a mut RHMap<K, V, H, A>, hash: usize, key: K) -> Self {
+        Self { map, hash, key }
+    }
+
+    /// Gets a reference to the key that would be used when inserting.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes the key back without inserting anything.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts the key with the given value and returns a mutable reference to the value.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let index = self.map.insert_vacant(self.hash, self.key, value);
+        &mut self.map.entry_at_mut(index).unwrap().value
+    }
+}
+
+/// A view into a single entry of a `RHMap` looked up by a borrowed key, created by `RHMap::entry_ref`.
+pub enum EntryRef<
+    'a,
+    'b,
+    K: Hash + Eq,
+    Q: ?Sized,
+    V,
+    H: BuildHasher + Clone,
+    A: Allocator + Clone = Global,
+> {
+    Occupied(OccupiedEntry<'a, K, V, H, A>),
+    Vacant(VacantEntryRef<'a, 'b, K, Q, V, H, A>),
+}
+
+impl<'a, 'b, K, Q, V, H, A> EntryRef<'a, 'b, K, Q, V, H, A>
+where
+    K: Hash + Eq + Borrow<Q> + From<&'b Q>,
+    Q: ?Sized,
+    H: BuildHasher + Clone,
+    A: Allocator + Clone,
+{
+    /// Ensures a value is in the entry by inserting the default if empty, and returns a mutable reference to it.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Ensures a value is in the entry by inserting the result of `default` if empty, and returns a mutable
+    /// reference to it.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting `V::default()` if empty, and returns a mutable reference to it.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let EntryRef::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+
+        self
+    }
+
+    /// Gets a reference to the key of the entry.
+    pub fn key(&self) -> &Q {
+        match self {
+            EntryRef::Occupied(entry) => entry.key().borrow(),
+            EntryRef::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+/// A view into a vacant entry of a `RHMap` looked up by a borrowed key. The owned key is only built once a value is
+/// actually inserted.
+pub struct VacantEntryRef<
+    'a,
+    'b,
+    K: Hash + Eq,
+    Q: ?Sized,
+    V,
+    H: BuildHasher + Clone,
+    A: Allocator + Clone = Global,
+> {
+    map: &'a mut RHMap<K, V, H, A>,
+    /// The hash of `key`, reused when inserting so the owned key doesn't need to be hashed again.
+    hash: usize,
+    key: &'b Q,
+}
+
+impl<'a, 'b, K: Hash + Eq, Q: ?Sized, V, H: BuildHasher + Clone, A: Allocator + Clone>
+    VacantEntryRef<'a, 'b, K, Q, V, H, A>
+{
+    pub(crate) fn new(map: &'a mut RHMap<K, V, H, A>, hash: usize, key: &'b Q) -> Self {
+        Self { map, hash, key }
+    }
+
+    /// Gets a reference to the borrowed key that would be used when inserting.
+    pub fn key(&self) -> &'b Q {
+        self.key
+    }
+
+    /// Builds the owned key, inserts it with the given value and returns a mutable reference to the value.
+    pub fn insert(self, value: V) -> &'a mut V
+    where
+        K: From<&'b Q>,
+    {
+        let index = self.map.insert_vacant(self.hash, K::from(self.key), value);
+        &mut self.map.entry_at_mut(index).unwrap().value
+    }
+}
+
+/// The error returned by `RHMap::try_insert` when the key is already present. Carries the value that was not inserted
+/// together with the occupied entry it collided with.
+pub struct OccupiedError<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone = Global>
+{
+    /// The entry in the map that was already occupied.
+    pub entry: OccupiedEntry<'a, K, V, H, A>,
+    /// The value which was not inserted, because the entry was already occupied.
+    pub value: V,
+}
+
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone, A: Allocator + Clone> Debug
+    for OccupiedError<'_, K, V, H, A>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedError")
+            .field("key", self.entry.key())
+            .field("old_value", self.entry.get())
+            .field("new_value", &self.value)
+            .finish()
+    }
+}
+
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone, A: Allocator + Clone> Display
+    for OccupiedError<'_, K, V, H, A>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to insert {:?}, key {:?} already exists with value {:?}",
+            self.value,
+            self.entry.key(),
+            self.entry.get(),
+        )
+    }
+}
+
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone, A: Allocator + Clone> Error
+    for OccupiedError<'_, K, V, H, A>
+{
+}
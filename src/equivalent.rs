@@ -0,0 +1,64 @@
+use core::borrow::Borrow;
+
+/// Key equality for lookups, so a map can be queried with a lightweight proxy instead of an owned key. Every type
+/// that `K` borrows as is equivalent to it, which covers the usual `&str` for `String` lookups. Composite keys need
+/// their own proxy type, e.g. a `(&str, u32)` newtype that compares field by field against `(String, u32)`.
+///
+/// A proxy must hash exactly like the key it is equivalent to, otherwise the lookup probes the wrong slots.
+pub trait Equivalent<K: ?Sized> {
+    /// Compares `self` to `key` and returns `true` if they are equal.
+    fn equivalent(&self, key: &K) -> bool;
+}
+
+impl<Q: ?Sized + Eq, K: ?Sized + Borrow<Q>> Equivalent<K> for Q {
+    fn equivalent(&self, key: &K) -> bool {
+        self == key.borrow()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hashmap::RHMap;
+    use super::*;
+    use std::hash::{Hash, Hasher};
+
+    /// Borrowed form of a `(String, u32)` key.
+    struct Lookup<'a>(&'a str, u32);
+
+    impl Hash for Lookup<'_> {
+        fn hash<S: Hasher>(&self, state: &mut S) {
+            // Hashes like the tuple does.
+            self.0.hash(state);
+            self.1.hash(state);
+        }
+    }
+
+    impl Equivalent<(String, u32)> for Lookup<'_> {
+        fn equivalent(&self, key: &(String, u32)) -> bool {
+            self.0 == key.0 && self.1 == key.1
+        }
+    }
+
+    #[test]
+    fn it_looks_up_composite_keys_through_proxies() {
+        let mut map = RHMap::new();
+        map.insert(("a".to_string(), 1), "a1");
+        map.insert(("b".to_string(), 2), "b2");
+
+        assert_eq!(map.get(&Lookup("a", 1)), Some(&"a1"));
+        assert_eq!(map.get(&Lookup("a", 2)), None);
+        assert!(map.contains_key(&Lookup("b", 2)));
+        assert_eq!(
+            map.remove_entry(&Lookup("b", 2)),
+            Some((("b".to_string(), 2), "b2"))
+        );
+        assert_eq!(map.get(&("a".to_string(), 1)), Some(&"a1"));
+        assert_eq!(map.len(), 1);
+
+        let handle = map.find_handle(&Lookup("a", 1)).unwrap();
+        assert_eq!(map.get_by_handle(handle), Some(&"a1"));
+        assert!(map.locate(&Lookup("a", 1)).is_some());
+        assert_eq!(map.compare_and_update(&Lookup("a", 1), &"a1", "A1"), Ok(()));
+        assert_eq!(map.get(&Lookup("a", 1)), Some(&"A1"));
+    }
+}
@@ -0,0 +1,81 @@
+use super::control::{GROUP_IMPL, GROUP_WIDTH, PREFETCH};
+use super::hashmap::{DEFAULT_LOAD_FACTOR, MAX_LOAD_FACTOR, MIN_LOAD_FACTOR};
+
+/// The capabilities compiled into this build of the crate, as returned by `features()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Features {
+    /// The instruction set control bytes are probed with: `"sse2"`, `"neon"` or `"scalar"`.
+    pub group_probing: &'static str,
+    /// How many control bytes a single probe step compares.
+    pub group_width: usize,
+    /// Whether long probes and batched lookups issue software prefetches (the `prefetch` feature, on x86_64).
+    pub prefetch: bool,
+    /// Whether the standard library is linked (the `std` feature). Without it the crate builds on `core` and `alloc`.
+    pub std: bool,
+    /// Whether maps can be created in a custom allocator.
+    pub custom_allocator: bool,
+    /// Whether that allocator is the unstable std `Allocator` trait (the `nightly` feature) rather than the
+    /// `allocator-api2` copy of it.
+    pub std_allocator_api: bool,
+    /// The hasher maps use unless another one is given.
+    pub default_hasher: &'static str,
+    /// Whether the default hasher gets a random seed per builder (the `random-seed` feature).
+    pub random_seed: bool,
+    /// Whether `AesBuildHasher` (the `aes-hash` feature) mixes with hardware AES rounds rather than its multiply
+    /// fallback.
+    pub hardware_aes: bool,
+    /// The load factor maps are created with.
+    pub default_load_factor: f32,
+    /// The range `RHMap::set_load_factor` accepts.
+    pub load_factor_range: (f32, f32),
+}
+
+/// Reports which code paths this build uses, so applications can log it at startup and attach it to bug reports.
+///
+/// ```
+/// let features = rhmap::features();
+/// println!("rhmap probes with {}", features.group_probing);
+/// ```
+pub fn features() -> Features {
+    Features {
+        group_probing: GROUP_IMPL,
+        group_width: GROUP_WIDTH,
+        prefetch: PREFETCH,
+        std: cfg!(feature = "std"),
+        custom_allocator: true,
+        std_allocator_api: cfg!(feature = "nightly"),
+        default_hasher: "FxHasher",
+        random_seed: cfg!(feature = "random-seed"),
+        hardware_aes: cfg!(all(
+            feature = "aes-hash",
+            target_arch = "x86_64",
+            target_feature = "aes"
+        )),
+        default_load_factor: DEFAULT_LOAD_FACTOR,
+        load_factor_range: (MIN_LOAD_FACTOR, MAX_LOAD_FACTOR),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reports_the_probing_path() {
+        let features = features();
+        let expected = if cfg!(all(target_arch = "x86_64", target_feature = "sse2")) {
+            "sse2"
+        } else if cfg!(all(target_arch = "aarch64", target_feature = "neon")) {
+            "neon"
+        } else {
+            "scalar"
+        };
+
+        assert_eq!(features.group_probing, expected);
+        assert_eq!(features.default_load_factor, 0.75);
+        assert_eq!(
+            features.prefetch,
+            cfg!(all(feature = "prefetch", target_arch = "x86_64"))
+        );
+    }
+}
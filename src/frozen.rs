@@ -0,0 +1,313 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::{RHMap, DEFAULT_LOAD_FACTOR};
+use alloc::{boxed::Box, vec::Vec};
+use allocator_api2::alloc::Allocator;
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    mem,
+};
+
+/// A `FrozenMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FrozenFxMap<K, V> = FrozenMap<K, V, FxBuildHasher>;
+
+struct Slot<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+/// An immutable map built once from a `RHMap` (see `RHMap::freeze`). Knowing every key up front, the build lays the
+/// table out with the shortest probes linear probing allows, and the table can be filled all the way up since
+/// nothing is ever inserted into it. There is no growth, shrink or PSL bookkeeping left to carry around either.
+///
+/// The entries themselves are stored in an array of exactly `len()` of them. Only the index the lookups probe has
+/// vacant slots, and those take a `usize` each rather than a whole entry.
+///
+/// It never changes, so it can be shared across threads (behind an `Arc`, or as a `static`) without any locking.
+///
+/// The bucket count doesn't have to be a power of two: the home slot of a hash is picked from its high bits by a
+/// multiply instead of a mask, which also keeps the load factor exact.
+pub struct FrozenMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    /// Every entry, in slot order.
+    entries: Box<[Slot<K, V>]>,
+    /// The position in `entries` of the entry in each slot, `VACANT` for vacant slots.
+    slots: Box<[usize]>,
+    /// Entries sit at most this many slots past their home slot.
+    max_psl: usize,
+    hasher_builder: H,
+    num_items: usize,
+}
+
+/// Index of a vacant slot.
+const VACANT: usize = usize::MAX;
+
+/// Maps a hash onto `0..buckets` using its high bits, which are the best mixed ones of a multiplicative hash like Fx.
+fn home_of(hash: u64, buckets: usize) -> usize {
+    ((hash as u128 * buckets as u128) >> 64) as usize
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> FrozenMap<K, V, H> {
+    /// Freezes `map` into an index of `ceil(len / load_factor)` slots. A load factor of 1.0 leaves no slot vacant.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `load_factor` lies within `0.1..=1.0`.
+    pub fn from_map<A: Allocator + Clone>(map: RHMap<K, V, H, A>, load_factor: f32) -> Self {
+        assert!(
+            (0.1..=1.0).contains(&load_factor),
+            "load factor {} is outside of 0.1..=1.0",
+            load_factor
+        );
+        let hasher_builder = map.hasher().clone();
+        let num_items = map.len();
+        let exact_buckets = num_items as f64 / load_factor as f64;
+        let mut buckets = exact_buckets as usize;
+        if (buckets as f64) < exact_buckets || buckets == 0 {
+            buckets += 1;
+        }
+
+        // Counting sort by home slot.
+        let mut entries: Vec<(usize, u64, K, V)> = map
+            .into_iter()
+            .map(|(key, value)| {
+                let hash = hasher_builder.hash_one(&key);
+                (home_of(hash, buckets), hash, key, value)
+            })
+            .collect();
+        let mut arrivals = alloc::vec![0usize; buckets];
+        for &(home, ..) in &entries {
+            arrivals[home] += 1;
+        }
+
+        // Entries are placed in home order, each in the first free slot from its home, which is the layout Robin Hood
+        // insertion converges to and minimises the total probe length. Walking the slots as a queue (entries arrive
+        // at their home slot, one leaves per slot) has to start right after the slot where the queue is longest,
+        // counted from anywhere, so that nothing is still queued when the walk wraps back around to the start.
+        let mut backlog = 0isize;
+        let mut lowest = 0isize;
+        let mut start = 0;
+        for (slot, &count) in arrivals.iter().enumerate() {
+            backlog += count as isize - 1;
+            if backlog < lowest {
+                lowest = backlog;
+                start = slot + 1;
+            }
+        }
+        let start = start % buckets;
+        entries.sort_unstable_by_key(|&(home, ..)| (home + buckets - start) % buckets);
+
+        let mut slots = alloc::vec![VACANT; buckets];
+        let mut placed_entries = Vec::with_capacity(num_items);
+        let mut queue = entries.into_iter();
+        let mut arrived = 0;
+        let mut placed = 0;
+        let mut max_psl = 0;
+        for offset in 0..buckets {
+            let slot = (start + offset) % buckets;
+            arrived += arrivals[slot];
+            if placed < arrived {
+                let (home, hash, key, value) = queue.next().expect("arrived entries are queued");
+                max_psl = max_psl.max((slot + buckets - home) % buckets);
+                slots[slot] = placed;
+                placed_entries.push(Slot { hash, key, value });
+                placed += 1;
+            }
+        }
+        debug_assert_eq!(placed, num_items);
+
+        // Entries were placed walking the slots from `start`, so rotate them into slot order.
+        placed_entries.rotate_right(slots[..start].iter().filter(|&&i| i != VACANT).count());
+        for (position, i) in slots.iter_mut().filter(|i| **i != VACANT).enumerate() {
+            *i = position;
+        }
+
+        Self {
+            entries: placed_entries.into_boxed_slice(),
+            slots: slots.into_boxed_slice(),
+            max_psl,
+            hasher_builder,
+            num_items,
+        }
+    }
+
+    /// Gets the value associated with the key.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    /// Gets the stored key and the value associated with the key.
+    pub fn get_key_value<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<(&K, &V)> {
+        let hash = self.hasher_builder.hash_one(key);
+        let buckets = self.slots.len();
+        let home = home_of(hash, buckets);
+        for psl in 0..=self.max_psl {
+            let mut slot = home + psl;
+            if slot >= buckets {
+                slot -= buckets;
+            }
+            let entry = match self.slots[slot] {
+                VACANT => return None,
+                i => &self.entries[i],
+            };
+            if entry.hash == hash && key.equivalent(&entry.key) {
+                return Some((&entry.key, &entry.value));
+            }
+            // Entries are laid out in home order, so one that is closer to its home than the key would be means the
+            // key isn't in the table.
+            let entry_psl = (slot + buckets - home_of(entry.hash, buckets)) % buckets;
+            if entry_psl < psl {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.get_key_value(key).is_some()
+    }
+
+    /// Gets the length / number of entries of the map.
+    pub fn len(&self) -> usize {
+        self.num_items
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+
+    /// Gets the number of slots in the index lookups probe.
+    pub fn bucket_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Gets the fraction of slots that hold an entry.
+    pub fn load_factor(&self) -> f32 {
+        self.num_items as f32 / self.slots.len() as f32
+    }
+
+    /// Gets the longest probe any lookup of a present key takes, counted in slots past the home slot.
+    pub fn max_probe_length(&self) -> usize {
+        self.max_psl
+    }
+
+    /// Gets the heap bytes taken up by the entries and the index.
+    pub fn memory_usage(&self) -> usize {
+        self.entries.len() * mem::size_of::<Slot<K, V>>()
+            + self.slots.len() * mem::size_of::<usize>()
+    }
+
+    /// Gets a reference to the hasher builder.
+    pub fn hasher(&self) -> &H {
+        &self.hasher_builder
+    }
+
+    /// Iterates over every key value pair in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.entries.iter().map(|entry| (&entry.key, &entry.value))
+    }
+
+    /// Iterates over every key in slot order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterates over every value in slot order.
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Turns the map back into a mutable `RHMap` with the same hasher.
+    pub fn thaw(self) -> RHMap<K, V, H> {
+        let mut map = RHMap::with_capacity_and_hasher(self.num_items, self.hasher_builder);
+        for entry in self.entries.into_vec() {
+            map.insert_unique_unchecked(entry.key, entry.value);
+        }
+        map
+    }
+}
+
+/// Freezes at the default load factor of the mutable map.
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> From<RHMap<K, V, H>> for FrozenMap<K, V, H> {
+    fn from(map: RHMap<K, V, H>) -> Self {
+        Self::from_map(map, DEFAULT_LOAD_FACTOR)
+    }
+}
+
+/// Formats the map like the std maps do (`{key: value, ...}`).
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone> Debug for FrozenMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_finds_every_key_at_full_load() {
+        let mut map = RHMap::new();
+        for x in 0..1000 {
+            map.insert(format!("key{}", x), x);
+        }
+        let frozen = map.freeze_with_load_factor(1.0);
+
+        assert_eq!(frozen.len(), 1000);
+        assert_eq!(frozen.load_factor(), 1.0);
+        for x in 0..1000 {
+            assert_eq!(frozen.get(format!("key{}", x).as_str()), Some(&x));
+        }
+        assert!(!frozen.contains_key("key1000"));
+        assert_eq!(frozen.iter().count(), 1000);
+    }
+
+    #[test]
+    fn it_shares_across_threads() {
+        let mut map = RHMap::new();
+        for x in 0..500u64 {
+            map.insert(x, x * x);
+        }
+        let frozen = std::sync::Arc::new(map.freeze());
+        assert!(frozen.max_probe_length() <= 8);
+
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let frozen = frozen.clone();
+                std::thread::spawn(move || {
+                    (t..500)
+                        .step_by(4)
+                        .all(|x| frozen.get(&x) == Some(&(x * x)))
+                })
+            })
+            .collect();
+        assert!(handles.into_iter().all(|handle| handle.join().unwrap()));
+
+        let thawed = std::sync::Arc::try_unwrap(frozen).ok().unwrap().thaw();
+        assert_eq!(thawed.len(), 500);
+        assert_eq!(thawed.get(&7), Some(&49));
+    }
+
+    #[test]
+    fn it_stores_exactly_len_entries() {
+        let mut map = RHMap::new();
+        for x in 0..300u64 {
+            map.insert(x, [x; 4]);
+        }
+        let frozen = map.freeze();
+
+        assert_eq!(frozen.entries.len(), frozen.len());
+        assert!(frozen.bucket_count() >= frozen.len());
+        assert_eq!(
+            frozen.memory_usage(),
+            300 * mem::size_of::<Slot<u64, [u64; 4]>>()
+                + frozen.bucket_count() * mem::size_of::<usize>()
+        );
+        assert!((0..300).all(|x| frozen.get(&x) == Some(&[x; 4])));
+        assert_eq!(frozen.thaw().len(), 300);
+    }
+}
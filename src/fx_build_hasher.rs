@@ -1,12 +1,64 @@
-use rustc_hash::FxHasher;
-use std::hash::BuildHasher;
+use core::{
+    hash::{BuildHasher, BuildHasherDefault, Hash, Hasher},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-#[derive(Clone)]
-pub struct FxBuildHasher;
+/// The hasher behind `FxBuildHasher`, for collections outside this crate that want the same hashing. Integers are
+/// mixed in with a single rotate, xor and multiply each: `write_u8` through `write_usize` are implemented directly
+/// rather than going through the byte slice `write`, which is what makes Fx fast for integer and small tuple keys.
+///
+/// The state is a `usize` and the multiplier is picked by pointer width, so on 32-bit targets (`wasm32`, most
+/// embedded cores) keys are mixed a native word at a time with a 32-bit constant instead of emulating 64-bit math.
+pub use rustc_hash::FxHasher;
+
+/// A `BuildHasher` for `FxHasher` that plugs into any collection taking a `BuildHasher + Default`, e.g.
+/// `std::collections::HashSet<u32, FxBuildHasherDefault>`.
+pub type FxBuildHasherDefault = BuildHasherDefault<FxHasher>;
+
+/// Number of live `DeterministicHashing` guards in the process.
+static DETERMINISTIC_GUARDS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug)]
+pub struct FxBuildHasher {
+    /// Mixed into every hasher before the key, `0` for plain Fx.
+    seed: u64,
+}
 
 impl FxBuildHasher {
+    /// Creates the default builder. Unseeded, unless the `random-seed` feature is on, in which case every builder
+    /// gets its own random seed (while no `DeterministicHashing` guard is installed).
     pub fn new() -> Self {
-        Self {}
+        #[cfg(feature = "random-seed")]
+        if !DeterministicHashing::is_active() {
+            return Self::with_seed(random_seed());
+        }
+
+        Self::deterministic()
+    }
+
+    /// Creates a builder that is guaranteed to hash every key the same way across runs and processes, no matter how
+    /// the default builder gets seeded. Use this wherever reproducible placement and iteration order matter.
+    pub fn deterministic() -> Self {
+        Self { seed: 0 }
+    }
+
+    /// Creates a builder that mixes `seed` into every hash, so keys only collide the same way under the same seed. A
+    /// secret seed makes it much harder for untrusted input to flood a map with colliding keys, though Fx is no
+    /// keyed cryptographic hash: use SipHash where that matters.
+    pub fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Hashes a single value the way maps using this builder do, without importing `BuildHasher` or building a hasher
+    /// by hand.
+    pub fn hash_one<T: Hash>(&self, value: T) -> u64 {
+        BuildHasher::hash_one(self, value)
+    }
+}
+
+impl Default for FxBuildHasher {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -14,6 +66,135 @@ impl BuildHasher for FxBuildHasher {
     type Hasher = FxHasher;
 
     fn build_hasher(&self) -> Self::Hasher {
-        FxHasher::default()
+        let mut hasher = FxHasher::default();
+        if self.seed != 0 {
+            hasher.write_u64(self.seed);
+        }
+        hasher
+    }
+}
+
+/// A fresh seed from the OS entropy std's `RandomState` is keyed with.
+#[cfg(feature = "random-seed")]
+fn random_seed() -> u64 {
+    use std::collections::hash_map::RandomState;
+
+    RandomState::new().build_hasher().finish()
+}
+
+/// A process wide switch that test harnesses can install to make every `FxBuildHasher::new()` behave like
+/// `FxBuildHasher::deterministic()`, so maps built deep inside the code under test place and iterate entries
+/// reproducibly from run to run. The switch stays on for as long as any guard is alive.
+#[derive(Debug)]
+pub struct DeterministicHashing {
+    _private: (),
+}
+
+impl DeterministicHashing {
+    /// Turns deterministic hashing on until the returned guard is dropped.
+    #[must_use = "deterministic hashing is turned off again as soon as the guard is dropped"]
+    pub fn install() -> Self {
+        DETERMINISTIC_GUARDS.fetch_add(1, Ordering::SeqCst);
+        Self { _private: () }
+    }
+
+    /// Returns `true` while at least one guard is installed.
+    pub fn is_active() -> bool {
+        DETERMINISTIC_GUARDS.load(Ordering::SeqCst) > 0
+    }
+}
+
+impl Drop for DeterministicHashing {
+    fn drop(&mut self) {
+        DETERMINISTIC_GUARDS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_hashes_deterministically() {
+        let a = FxBuildHasher::deterministic();
+        let b = FxBuildHasher::deterministic();
+
+        assert_eq!(a.hash_one("rhmap"), b.hash_one("rhmap"));
+    }
+
+    #[test]
+    fn it_mixes_the_seed_into_hashes() {
+        let seeded = FxBuildHasher::with_seed(0x5eed);
+
+        assert_eq!(
+            seeded.hash_one(42),
+            FxBuildHasher::with_seed(0x5eed).hash_one(42)
+        );
+        assert_ne!(
+            seeded.hash_one(42),
+            FxBuildHasher::with_seed(0x5eee).hash_one(42)
+        );
+        assert_ne!(
+            seeded.hash_one(42),
+            FxBuildHasher::deterministic().hash_one(42)
+        );
+        assert_eq!(
+            FxBuildHasher::with_seed(0).hash_one(42),
+            FxBuildHasher::deterministic().hash_one(42)
+        );
+    }
+
+    #[test]
+    fn it_hashes_integers_a_word_at_a_time() {
+        let fx = FxBuildHasher::deterministic();
+        let mut hasher = fx.build_hasher();
+        hasher.write_u16(7);
+
+        // Every width of integer is a single word mixed into the empty state.
+        assert_eq!(hasher.finish(), fx.hash_one(7u8));
+        assert_eq!(fx.hash_one(7u32), fx.hash_one(7usize));
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(fx.hash_one(7u64), fx.hash_one(7usize));
+        assert_ne!(fx.hash_one(7u32), fx.hash_one(8u32));
+    }
+
+    #[test]
+    fn it_mixes_with_the_constant_for_the_word_size() {
+        #[cfg(target_pointer_width = "64")]
+        const K: u64 = 0x517cc1b727220a95;
+        #[cfg(target_pointer_width = "32")]
+        const K: u64 = 0x9e3779b9;
+
+        // A single word mixed into the empty state comes out multiplied by the constant.
+        assert_eq!(FxBuildHasher::deterministic().hash_one(1usize), K);
+    }
+
+    #[test]
+    fn it_hashes_std_collections_like_the_map() {
+        let mut set: std::collections::HashSet<u32, FxBuildHasherDefault> = Default::default();
+        set.insert(7);
+
+        assert!(set.contains(&7));
+        assert_eq!(
+            set.hasher().hash_one(7),
+            FxBuildHasher::deterministic().hash_one(7)
+        );
+    }
+
+    #[test]
+    fn it_toggles_deterministic_hashing_with_guards() {
+        let outer = DeterministicHashing::install();
+        let inner = DeterministicHashing::install();
+        assert!(DeterministicHashing::is_active());
+
+        drop(inner);
+        assert!(DeterministicHashing::is_active());
+        assert_eq!(
+            FxBuildHasher::new().hash_one(42),
+            FxBuildHasher::deterministic().hash_one(42)
+        );
+
+        drop(outer);
+        assert!(!DeterministicHashing::is_active());
     }
 }
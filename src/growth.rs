@@ -0,0 +1,189 @@
+use alloc::boxed::Box;
+
+/// Decides how large a table grows once it runs out of room.
+///
+/// Policies pick the number of entries the grown table must hold. Bucket counts stay powers of two so probing can mask
+/// instead of divide, so the map goes to the smallest power of two that holds that many entries at its load factor.
+/// Every growth at least doubles the buckets that way, but the entry capacity a policy asks for is always there
+/// afterwards, however far past double it is.
+pub trait GrowthPolicy {
+    /// The number of entries a full table holding `current` entries should grow to hold. Returning `current` or less
+    /// means the table may not grow any further.
+    fn next_capacity(&self, current: usize) -> usize;
+
+    /// The most slots the table may ever have, a power of two. Unbounded by default.
+    fn max_buckets(&self) -> usize {
+        usize::MAX
+    }
+}
+
+impl<P: GrowthPolicy + ?Sized> GrowthPolicy for Box<P> {
+    fn next_capacity(&self, current: usize) -> usize {
+        (**self).next_capacity(current)
+    }
+
+    fn max_buckets(&self) -> usize {
+        (**self).max_buckets()
+    }
+}
+
+/// Doubles the table every time. This is what maps use by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Doubling;
+
+impl GrowthPolicy for Doubling {
+    fn next_capacity(&self, current: usize) -> usize {
+        current.saturating_mul(2)
+    }
+}
+
+/// Grows the table to hold half as many entries again.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreeHalves;
+
+impl GrowthPolicy for ThreeHalves {
+    fn next_capacity(&self, current: usize) -> usize {
+        current.saturating_add(current / 2)
+    }
+}
+
+/// Grows the table to hold a fixed number of entries more, e.g. enough for the next batch a map is known to receive.
+/// An increment of 0 keeps the table from growing at all.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedIncrement(pub usize);
+
+impl GrowthPolicy for FixedIncrement {
+    fn next_capacity(&self, current: usize) -> usize {
+        current.saturating_add(self.0)
+    }
+}
+
+/// Grows like `policy` but never past `max` slots, rounded down to a power of two. Inserting a new key into a table
+/// that has reached its cap and has a single vacancy left panics, since Robin Hood probing needs a vacant slot to stop
+/// at. Updates of keys already in the table still go through.
+#[derive(Clone, Copy, Debug)]
+pub struct Capped<P: GrowthPolicy> {
+    pub policy: P,
+    pub max: usize,
+}
+
+impl<P: GrowthPolicy> GrowthPolicy for Capped<P> {
+    fn next_capacity(&self, current: usize) -> usize {
+        self.policy.next_capacity(current)
+    }
+
+    fn max_buckets(&self) -> usize {
+        let max = match self.max {
+            0 => 0,
+            max => 1 << (usize::BITS - 1 - max.leading_zeros()),
+        };
+        self.policy.max_buckets().min(max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::RHMapBuilder;
+    use crate::prelude::*;
+
+    #[test]
+    fn it_caps_the_table_size() {
+        let policy = Capped {
+            policy: Doubling,
+            max: 20,
+        };
+        assert_eq!(policy.next_capacity(4), 8);
+        assert_eq!(policy.max_buckets(), 16);
+
+        let mut map = RHMapBuilder::new().growth_policy(policy).build();
+        for x in 0..15 {
+            map.insert(x, x);
+        }
+        assert_eq!(map.bucket_count(), 16);
+        assert!((0..15).all(|x| map.get(&x) == Some(&x)));
+    }
+
+    #[test]
+    fn it_grows_to_the_entries_a_policy_asks_for() {
+        assert_eq!(ThreeHalves.next_capacity(12), 18);
+        assert_eq!(FixedIncrement(100).next_capacity(12), 112);
+
+        // 12 entries fit in 16 slots at the default load factor, and 18 need the next power of two up.
+        let mut map = RHMapBuilder::new().growth_policy(ThreeHalves).build();
+        for x in 0..13 {
+            map.insert(x, x);
+        }
+        assert_eq!(map.bucket_count(), 32);
+
+        // A step larger than doubling is taken in one go: 3 entries and 1000 more need 2048 slots.
+        let mut map = RHMapBuilder::new()
+            .growth_policy(FixedIncrement(1000))
+            .build();
+        let mut buckets = Vec::new();
+        for x in 0..1200 {
+            map.insert(x, x);
+            if buckets.last() != Some(&map.bucket_count()) {
+                buckets.push(map.bucket_count());
+            }
+        }
+        assert_eq!(buckets, vec![4, 2048]);
+        assert!(map.capacity() >= 1003);
+        assert!((0..1200).all(|x| map.get(&x) == Some(&x)));
+    }
+
+    #[test]
+    #[should_panic(expected = "growth policy")]
+    fn it_panics_once_a_capped_table_is_full() {
+        let mut map = RHMapBuilder::new()
+            .growth_policy(Capped {
+                policy: Doubling,
+                max: 8,
+            })
+            .build();
+        for x in 0..9 {
+            map.insert(x, x);
+        }
+    }
+
+    #[test]
+    fn it_appends_and_updates_within_a_capped_table() {
+        let mut map = RHMapBuilder::new()
+            .growth_policy(Capped {
+                policy: Doubling,
+                max: 16,
+            })
+            .build();
+        for x in 0..10 {
+            map.insert(x, x);
+        }
+        let mut other = FxHashMap::new();
+        other.insert_batch((5..15).map(|x| (x, x * 10)));
+        map.append(&mut other);
+        assert_eq!(map.len(), 15);
+        assert_eq!(map.bucket_count(), 16);
+        assert_eq!(map.get(&7), Some(&70));
+
+        // The table is as full as it can get, but updating a key it holds doesn't need another slot.
+        map.insert(3, 30);
+        assert_eq!(map.get(&3), Some(&30));
+        assert_eq!(map.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "growth policy")]
+    fn it_panics_when_appending_past_the_cap() {
+        let mut map = RHMapBuilder::new()
+            .growth_policy(Capped {
+                policy: Doubling,
+                max: 16,
+            })
+            .build();
+        for x in 0..10 {
+            map.insert(x, x);
+        }
+        let mut other = FxHashMap::new();
+        other.insert_batch((10..20).map(|x| (x, x)));
+        map.append(&mut other);
+    }
+}
@@ -0,0 +1,103 @@
+//! Statistical checks on how well `FxBuildHasher` spreads common key shapes over a table. Slow, so they only run with
+//! the `hash-quality` feature: `cargo test --release --features hash-quality hash_quality`.
+
+use super::control::h2;
+use super::fx_build_hasher::FxBuildHasher;
+use core::hash::Hash;
+
+/// Number of keys hashed per key shape.
+const KEYS: usize = 1 << 20;
+/// log2 of the number of buckets: keys are spread at a load of 1 key per 2 buckets, like a map at half load.
+const BUCKET_BITS: u32 = 21;
+
+/// A small xorshift generator, so the random key shapes are reproducible without pulling in a dependency.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// Chi-squared statistic of `hashes` over the buckets picked by `bucket`, divided by the degrees of freedom. Close to
+/// 1 for hashes that spread like random ones, far above it for clustered ones.
+fn chi_squared<F: Fn(usize) -> usize>(hashes: &[usize], buckets: usize, bucket: F) -> f64 {
+    let mut counts = vec![0u64; buckets];
+    for &hash in hashes {
+        counts[bucket(hash)] += 1;
+    }
+
+    let expected = hashes.len() as f64 / buckets as f64;
+    let statistic: f64 = counts
+        .iter()
+        .map(|&count| (count as f64 - expected).powi(2) / expected)
+        .sum();
+    statistic / (buckets - 1) as f64
+}
+
+/// Checks that the slot bits (the low bits) and the control byte (the top 7 bits) of each hash are both spread evenly.
+fn assert_spreads<T: Hash>(shape: &str, keys: impl Iterator<Item = T>) {
+    let builder = FxBuildHasher::deterministic();
+    let hashes: Vec<usize> = keys.map(|key| builder.hash_one(key) as usize).collect();
+    let mask = (1 << BUCKET_BITS) - 1;
+
+    let slots = chi_squared(&hashes, mask + 1, |hash| hash & mask);
+    assert!(slots < 1.1, "{} keys cluster in slots: {}", shape, slots);
+    let fragments = chi_squared(&hashes, 128, |hash| h2(hash) as usize);
+    assert!(
+        fragments < 2.0,
+        "{} keys cluster in control bytes: {}",
+        shape,
+        fragments
+    );
+}
+
+#[test]
+fn it_spreads_sequential_integers() {
+    assert_spreads("sequential u64", 0..KEYS as u64);
+    assert_spreads("sequential u32", 0..KEYS as u32);
+}
+
+#[test]
+#[ignore = "Fx only mixes the low bits of a string with the low bits of its bytes, so short strings cluster in slots"]
+fn it_spreads_short_strings() {
+    assert_spreads("short string", (0..KEYS).map(|i| format!("key{}", i)));
+}
+
+#[test]
+fn it_spreads_uuids() {
+    let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+    assert_spreads(
+        "uuid",
+        (0..KEYS).map(|_| ((rng.next() as u128) << 64) | rng.next() as u128),
+    );
+}
+
+#[test]
+fn it_reaches_the_control_byte_from_every_input_bit() {
+    // Fx only carries a flipped bit upwards through the multiply, so single bits don't flip each control byte bit
+    // half the time like a full avalanche would. What matters for group probing is that keys differing in any one bit
+    // almost never share a fragment.
+    let builder = FxBuildHasher::deterministic();
+    let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+    let samples = 4096;
+    for bit in 0..64 {
+        let changed = (0..samples)
+            .filter(|_| {
+                let key = rng.next();
+                h2(builder.hash_one(key) as usize) != h2(builder.hash_one(key ^ 1 << bit) as usize)
+            })
+            .count();
+
+        let rate = changed as f64 / samples as f64;
+        assert!(
+            rate > 0.99,
+            "input bit {} only changes the control byte at a rate of {}",
+            bit,
+            rate
+        );
+    }
+}
@@ -1,48 +1,203 @@
+use super::control::{h2, prefetch, Group, EMPTY, GROUP_WIDTH, PENDING};
+use super::entry::{self, EntryRef, OccupiedEntry, OccupiedError, VacantEntry, VacantEntryRef};
+use super::equivalent::Equivalent;
+use super::frozen::FrozenMap;
 use super::fx_build_hasher::FxBuildHasher;
-use super::map_entry::{Entry, MapEntry};
-use std::{
-    cmp::max,
-    hash::{BuildHasher, Hash, Hasher},
-    ptr, vec,
+use super::growth::{Doubling, GrowthPolicy};
+use super::map_entry::Entry;
+use super::raw_entry::{RawEntryBuilder, RawEntryBuilderMut};
+use super::traits::{MapRead, MapWrite};
+use alloc::{sync::Arc, vec, vec::Vec};
+use allocator_api2::{
+    alloc::{Allocator, Global},
+    vec::Vec as BucketVec,
+};
+use core::{
+    borrow::Borrow,
+    cmp::{max, min},
+    error::Error,
+    fmt::{self, Debug, Display},
+    hash::{BuildHasher, Hash},
+    iter::{FusedIterator, Zip},
+    mem::{self, MaybeUninit},
+    slice,
 };
 
 const INITIAL_SIZE: usize = 4;
 
+/// A slot of the table, initialized when its control byte isn't `EMPTY`.
+#[cfg(feature = "rayon")]
+pub(crate) type Slot<K, V> = MaybeUninit<Entry<K, V>>;
+
+/// Probes in tables whose longest probe exceeds this many slots prefetch the next group of slots while scanning the
+/// current one (with the `prefetch` feature).
+const PREFETCH_PSL: usize = 8;
+
+/// How many lookups `get_batch` prefetches ahead of probing. Enough to overlap the memory latency of a large table
+/// without the first prefetched lines being evicted before they're used.
+const BATCH_CHUNK: usize = 16;
+
+/// The load factor maps are created with unless configured otherwise.
+pub const DEFAULT_LOAD_FACTOR: f32 = 0.75;
+/// The smallest load factor a map can be configured with.
+pub const MIN_LOAD_FACTOR: f32 = 0.1;
+/// The largest load factor a map can be configured with. Robin Hood probing keeps probe lengths short at high loads,
+/// but a completely full table can't probe at all.
+pub const MAX_LOAD_FACTOR: f32 = 0.95;
+
+/// Estimates the heap bytes taken up by the slots and control bytes of a map sized (through `reserve`) to hold `n`
+/// entries, accounting for the slot layout and the load factor.
+pub fn estimated_bytes_for_capacity<K: Hash + Eq, V>(n: usize) -> usize {
+    if n == 0 {
+        return 0;
+    }
+
+    buckets_for(n) * slot_bytes::<K, V>()
+}
+
+/// The number of entries a map can be sized for (through `reserve`) while its slots stay within `byte_budget` heap
+/// bytes. Use this to fit a cache into a memory budget.
+pub fn capacity_for_bytes<K: Hash + Eq, V>(byte_budget: usize) -> usize {
+    let max_buckets = byte_budget / slot_bytes::<K, V>();
+    if max_buckets < INITIAL_SIZE {
+        return 0;
+    }
+
+    // Bucket counts only ever double starting from `INITIAL_SIZE`, so find the largest one within budget.
+    let mut buckets = INITIAL_SIZE;
+    while buckets * 2 <= max_buckets {
+        buckets *= 2;
+    }
+
+    max_load(buckets, DEFAULT_LOAD_FACTOR)
+}
+
+/// Heap bytes per bucket: the slot itself plus its control byte.
+fn slot_bytes<K: Hash + Eq, V>() -> usize {
+    mem::size_of::<MaybeUninit<Entry<K, V>>>() + 1
+}
+
+/// The probe sequence length of an entry with the given hash sitting in `slot`: how many slots past its home slot it
+/// is, wrapping around the end of a table whose length is `mask + 1`.
+fn psl_of(slot: usize, hash: usize, mask: usize) -> usize {
+    slot.wrapping_sub(hash) & mask
+}
+
+/// The shard an entry with the given hash goes to when a map is split `shards` ways: where the hash falls among
+/// `shards` equal ranges of hash values, so the shards partition the hashes by their top bits.
+fn shard_of(hash: usize, shards: usize) -> usize {
+    ((hash as u128 * shards as u128) >> usize::BITS) as usize
+}
+
+/// The number of entries `buckets` slots may hold at the given load factor.
+fn max_load(buckets: usize, load_factor: f32) -> usize {
+    (buckets as f64 * load_factor as f64) as usize
+}
+
+/// Panics unless the load factor lies within `MIN_LOAD_FACTOR..=MAX_LOAD_FACTOR`.
+pub(crate) fn check_load_factor(load_factor: f32) {
+    assert!(
+        (MIN_LOAD_FACTOR..=MAX_LOAD_FACTOR).contains(&load_factor),
+        "load factor {} is outside of {}..={}",
+        load_factor,
+        MIN_LOAD_FACTOR,
+        MAX_LOAD_FACTOR
+    );
+}
+
+/// The number of buckets needed to hold `n` entries at a load factor of 0.75, doubling from `INITIAL_SIZE` like the
+/// map does when it grows.
+fn buckets_for(n: usize) -> usize {
+    buckets_for_load(n, DEFAULT_LOAD_FACTOR)
+}
+
+/// The smallest power of two number of buckets, at least `INITIAL_SIZE`, that holds `n` entries at `load_factor`.
+fn buckets_for_load(n: usize, load_factor: f32) -> usize {
+    let mut buckets = INITIAL_SIZE;
+    while n > max_load(buckets, load_factor) {
+        buckets *= 2;
+    }
+
+    buckets
+}
+
+/// A `RHMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxHashMap<K, V> = RHMap<K, V, FxBuildHasher>;
+
 /// Robinhood HashMap backed by the fx hashing algorithm (by default).
-#[derive(Debug)]
-pub struct RHMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
-    inner: Vec<MapEntry<K, V>>,
+///
+/// The slots and control bytes are allocated with `A`, the global allocator unless the map is created with one of the
+/// `_in` constructors. `Allocator` is the one from `allocator-api2`, which is the unstable std trait when the `nightly`
+/// feature is on.
+pub struct RHMap<K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone = Global> {
+    /// The slots. Only those whose control byte isn't `EMPTY` are initialized.
+    inner: BucketVec<MaybeUninit<Entry<K, V>>, A>,
+    /// One control byte per slot of `inner`: `EMPTY`, or the 7-bit hash fragment of the entry in it. Doubles as the
+    /// occupancy map. Probing scans this dense strip and only touches an entry once its fragment matches.
+    ctrl: BucketVec<u8, A>,
+    /// `inner.len() - 1`. The bucket count is always a power of two so `hash & mask` picks a slot.
+    mask: usize,
     hasher_builder: H,
     num_items: usize,
+    /// The fraction of slots that may be occupied before the table grows.
+    load_factor: f32,
+    /// Once fewer than this fraction of slots are occupied after a removal, the table shrinks. Off when `None`.
+    shrink_threshold: Option<f32>,
+    /// How far the table grows, doubling when `None`.
+    growth: Option<Arc<dyn GrowthPolicy + Send + Sync>>,
+    /// The largest PSL of any entry, kept exact as entries come and go with the help of `psl_counts`.
     max_psl: usize,
+    /// `psl_counts[p]` is the number of entries with a PSL of `p`.
+    psl_counts: Vec<usize>,
+    /// Bumped every time the table is reallocated, which moves every entry.
+    epoch: usize,
+    /// Bumped whenever any entry may have moved: on every insert of a new key, every removal and every resize.
+    generation: usize,
 }
 
 impl<K: Hash + Eq, V> RHMap<K, V, FxBuildHasher> {
     /// Creates a `RHMap` with the default Fx Hasher and an initial capacity of 0.
     pub fn new() -> Self {
-        let hasher_builder = FxBuildHasher::new();
-
-        Self {
-            inner: Vec::new(),
-            hasher_builder,
-            num_items: 0,
-            max_psl: 0,
-        }
+        Self::with_hasher(FxBuildHasher::new())
     }
 
-    /// Constructs a `RHMap` with an initial capacity. This method of constructing is recommended if you have a good idea of how large
-    /// your hashmap will grow as this reduces the number of resizes.
+    /// Constructs a `RHMap` that can hold at least `initial_capacity` entries without resizing. This method of constructing
+    /// is recommended if you have a good idea of how large your hashmap will grow as this reduces the number of resizes.
     pub fn with_capacity(initial_capacity: usize) -> Self {
-        let hasher_builder = FxBuildHasher::new();
-        let mut inner: Vec<MapEntry<K, V>> = Vec::with_capacity(initial_capacity);
-        inner.extend((0..initial_capacity).map(|_| MapEntry::default()));
+        Self::with_capacity_and_hasher(initial_capacity, FxBuildHasher::new())
+    }
 
-        Self {
-            inner,
-            hasher_builder,
-            num_items: 0,
-            max_psl: 0,
+    /// Creates a `RHMap` that grows once more than `load_factor` of its slots are occupied, instead of the default
+    /// 0.75. Higher load factors trade longer probes for less memory. Use `RHMapBuilder` to combine this with a
+    /// capacity or a custom hasher.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `load_factor` is outside of `MIN_LOAD_FACTOR..=MAX_LOAD_FACTOR`.
+    pub fn with_load_factor(load_factor: f32) -> Self {
+        let mut map = Self::new();
+        map.set_load_factor(load_factor);
+        map
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> Default for RHMap<K, V, H> {
+    /// Creates an empty `RHMap` with the hasher builder's default, e.g. the Fx Hasher for a `FxHashMap`.
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq, V, const N: usize> From<[(K, V); N]> for RHMap<K, V, FxBuildHasher> {
+    /// Builds a `RHMap` from an array of key value pairs, e.g. `RHMap::from([(1, "a"), (2, "b")])`.
+    /// Later pairs overwrite earlier ones with the same key.
+    fn from(pairs: [(K, V); N]) -> Self {
+        let mut map = Self::with_capacity(N);
+        for (key, value) in IntoIterator::into_iter(pairs) {
+            map.insert(key, value);
         }
+
+        map
     }
 }
 
@@ -50,133 +205,352 @@ impl<K: Hash + Eq, V, H: BuildHasher + Clone> RHMap<K, V, H> {
     /// Creates a `RHMap` with a custom hasher builder which overrides the default fx hasher. Use this if you want to create a
     /// robinhood hashmap but with a custom hasher perhaps to provide greater cryptographic security.
     pub fn with_hasher(hasher_builder: H) -> Self {
+        Self::with_hasher_in(hasher_builder, Global)
+    }
+
+    /// Creates a `RHMap` with both an initial capacity (see `with_capacity`) and a custom hasher.
+    pub fn with_capacity_and_hasher(initial_capacity: usize, hasher_builder: H) -> Self {
+        Self::with_capacity_and_hasher_in(initial_capacity, hasher_builder, Global)
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone> RHMap<K, V, H, A> {
+    /// Creates a `RHMap` with a custom hasher whose slots are allocated with `alloc`, e.g. an arena or a bump
+    /// allocator.
+    pub fn with_hasher_in(hasher_builder: H, alloc: A) -> Self {
         Self {
-            inner: Vec::new(),
+            inner: BucketVec::new_in(alloc.clone()),
+            ctrl: BucketVec::new_in(alloc),
+            mask: 0,
             hasher_builder,
             num_items: 0,
+            load_factor: DEFAULT_LOAD_FACTOR,
+            shrink_threshold: None,
+            growth: None,
             max_psl: 0,
+            psl_counts: Vec::new(),
+            epoch: 0,
+            generation: 0,
         }
     }
 
-    /// Creates a `RHMap` with both an initial capacity and a custom hasher.
-    pub fn with_capacity_and_hasher(initial_capacity: usize, hasher_builder: H) -> Self {
-        let mut map = RHMap::with_hasher(hasher_builder);
-        let mut inner: Vec<MapEntry<K, V>> = Vec::with_capacity(initial_capacity);
-        inner.extend((0..initial_capacity).map(|_| MapEntry::default()));
-        map.inner = inner;
+    /// Creates a `RHMap` with an initial capacity (see `with_capacity`), a custom hasher, and slots allocated with
+    /// `alloc`.
+    pub fn with_capacity_and_hasher_in(
+        initial_capacity: usize,
+        hasher_builder: H,
+        alloc: A,
+    ) -> Self {
+        match initial_capacity {
+            0 => RHMap::with_hasher_in(hasher_builder, alloc),
+            n => RHMap::with_buckets_in(buckets_for(n), hasher_builder, alloc),
+        }
+    }
+
+    /// Creates a `RHMap` with exactly `buckets` slots, rounded up to the next power of two.
+    pub(crate) fn with_buckets_in(buckets: usize, hasher_builder: H, alloc: A) -> Self {
+        let mut map = RHMap::with_hasher_in(hasher_builder, alloc);
+        if buckets == 0 {
+            return map;
+        }
+
+        // Bucket counts are always powers of two so slots can be computed with a mask instead of a division.
+        let buckets = buckets.next_power_of_two();
+        map.inner.resize_with(buckets, MaybeUninit::uninit);
+        map.ctrl.resize(buckets, EMPTY);
+        map.mask = buckets - 1;
 
         map
     }
 
+    /// Gets the allocator the slots live in.
+    pub fn allocator(&self) -> &A {
+        self.inner.allocator()
+    }
+
     /// Inserts a value with its associated key into the hashmap.
     pub fn insert(&mut self, key: K, value: V) {
-        // Load Factor of 0.75
-        if self.inner.is_empty() || self.num_items > 3 * self.inner.len() / 4 {
-            self.resize();
+        let hash = self.make_hash(&key);
+        self.grow_for_insert(hash, &key);
+        // Handles insertion logic
+        self.insert_entry(Entry::new(key, value, hash), false);
+    }
+
+    /// Inserts a key value pair only if the key isn't already present and returns a mutable reference to the inserted
+    /// value. Unlike `insert` this never overwrites: if the key is taken, the attempted value is handed back in an
+    /// `OccupiedError` alongside the existing entry.
+    pub fn try_insert(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<&mut V, OccupiedError<'_, K, V, H, A>> {
+        let hash = self.make_hash(&key);
+        if let Some(index) = self.probe_hashed(hash, &key).0 {
+            return Err(OccupiedError {
+                entry: OccupiedEntry::new(self, index),
+                value,
+            });
         }
 
-        let hash = self.hash_key(&key);
-        // Handles insertion logic
-        self.insert_entry(Entry::new(key, value, hash, 0));
+        let index = self.insert_vacant(hash, key, value);
+        Ok(&mut self.entry_at_mut(index).unwrap().value)
     }
 
     /// Deletes the entry with the given key. Returns an `Err` if no such entry with the provided key exists.
-    pub fn remove(&mut self, key: &K) -> Result<(), &'static str> {
-        // We're going to go with an interesting approach called backward shift deletion here
-        let res = self.get_entry(&key);
-        if let Some(entry) = res {
-            let len = self.inner.len();
-            let slot = entry.hash % len;
-            // Index position of the entry to be deleted
-            let i = slot + entry.psl;
-            // To keep track of where the bucket ends so that we can shift all entries to the right of the entry
-            // to be deleted to the left.
-            let mut j = i + 1;
-
-            // This is possible if the entry to be deleted is actually the last element
-            // of the inner vector. In this case, we actually don't have any elements (to the left) to shift
-            // so all we do is directly overwrite the value at index i to be a `MapEntry::VacantEntry`
-            if j >= self.inner.len() {
-                self.inner[i] = MapEntry::VacantEntry;
-                self.num_items -= 1;
-                return Ok(());
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(
+        &mut self,
+        key: &Q,
+    ) -> Result<(), &'static str> {
+        if let Some(i) = self.find_index(key) {
+            self.remove_at(i);
+            self.shrink_if_needed();
+            Ok(())
+        } else {
+            Err("Entry not found")
+        }
+    }
+
+    /// Removes the entry at slot index `i` and returns it.
+    pub(crate) fn remove_at(&mut self, i: usize) -> Entry<K, V> {
+        // We're going to go with an interesting approach called backward shift deletion here.
+        // Take the entry out, leaving a hole, and then keep pulling the next entry of the bucket into the hole until
+        // we reach the end of the bucket. Buckets can wrap around the end of the table, so the slot after the last one
+        // is slot 0.
+        let removed = self
+            .take_slot(i)
+            .expect("remove_at called on a vacant slot");
+        let mut hole = i;
+
+        loop {
+            let next = (hole + 1) & self.mask;
+            // The bucket ends at a vacant entry or at an entry that already sits in its home slot (psl == 0), since
+            // shifting that one would move it in front of its home.
+            let psl = match self.entry_at(next) {
+                Some(entry) => psl_of(next, entry.hash, self.mask),
+                None => break,
+            };
+            if psl == 0 {
+                break;
             }
 
-            loop {
-                let cur = self.inner.get(j).unwrap();
+            // The shifted entry is now one step closer to its home.
+            self.inner.swap(hole, next);
+            self.ctrl.swap(hole, next);
+            self.note_placed(psl - 1);
+            self.note_taken(psl);
+            hole = next;
+        }
 
-                // We overflow the bucket if we find an entry with psl == 0.
-                // We can also stop if we see a vacant entry because there can't be any valid
-                // occupied entries after a vacant entry (unless we overflow to the next bucket)
-                if let MapEntry::Occupied(entry) = cur {
-                    if entry.psl == 0 {
-                        break;
-                    }
-                } else {
-                    break;
-                }
+        self.num_items -= 1;
+        self.generation += 1;
+        self.note_taken(psl_of(i, removed.hash, self.mask));
+        self.debug_check_invariants();
+        removed
+    }
 
-                j += 1;
-            }
+    /// Inserts a key that the caller has already hashed and found missing from the map, and returns the slot index it
+    /// ended up in.
+    pub(crate) fn insert_vacant(&mut self, hash: usize, key: K, value: V) -> usize {
+        self.grow_if_needed();
+        self.insert_entry(Entry::new(key, value, hash), true)
+    }
 
-            // unsafe because UB if we go out of bounds, any of the pointers are invalid or we mess up the vec pointer while modifying
-            // we should guarantee that indices i and j are within bounds
-            unsafe {
-                // Replace the entry to be deleted by shifting j - i - 1 elements to the left, overwriting
-                // the entry to be deleted in the process
-                let entry_ptr = self.inner.as_mut_ptr().add(i);
-                ptr::copy(entry_ptr.offset(1), entry_ptr, j - i - 1);
-                // We have to ensure that we add back in a `VacantEntry` after shifting all the elements of the bucket
-                // thereby taking the place of the deleted entry in order to not mess up the vec's structure.
-                ptr::write(entry_ptr.add(j - i - 1), MapEntry::VacantEntry);
-            }
+    /// Inserts a key that is known not to be in the map yet. This skips the key comparisons `insert` does while
+    /// probing, which measurably speeds up bulk loads of distinct keys.
+    ///
+    /// Inserting a key that is already present is a logic error: the map ends up holding the key twice. Debug builds
+    /// catch this with an assertion.
+    pub fn insert_unique_unchecked(&mut self, key: K, value: V) {
+        debug_assert!(
+            !self.contains_key(&key),
+            "insert_unique_unchecked called with a key that is already in the map"
+        );
 
-            self.num_items -= 1;
-            return Ok(());
-        } else {
-            return Err("Entry not found");
+        let hash = self.make_hash(&key);
+        self.insert_vacant(hash, key, value);
+    }
+
+    /// Inserts many entries at once. Keys are hashed up front and the entries are placed in slot order, so a bulk load
+    /// walks the table front to back instead of jumping to a random slot for every entry.
+    pub fn insert_batch<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) {
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|(key, value)| (self.make_hash(&key), key, value))
+            .collect();
+        self.place_batch(entries);
+    }
+
+    /// Like `insert_batch`, for entries whose hashes are already known, e.g. because they were stored next to the
+    /// entries on disk. Each hash must be the one this map's hasher produces for its key (see `BuildHasher::hash_one`),
+    /// otherwise the entry can't be found afterwards. Debug builds check this.
+    pub fn extend_with_hashes<I: IntoIterator<Item = (u64, K, V)>>(&mut self, entries: I) {
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|(hash, key, value)| {
+                debug_assert_eq!(
+                    hash as usize,
+                    self.make_hash(&key),
+                    "extend_with_hashes was given a hash that doesn't match the key"
+                );
+                (hash as usize, key, value)
+            })
+            .collect();
+        self.place_batch(entries);
+    }
+
+    /// Sizes the table for every entry, then inserts them sorted by home slot. Later duplicates win, like a series of
+    /// `insert`s would.
+    fn place_batch(&mut self, mut entries: Vec<(usize, K, V)>) {
+        self.reserve(entries.len());
+        if self.inner.is_empty() {
+            return;
+        }
+
+        let mask = self.mask;
+        // A stable sort keeps duplicate keys in their original order.
+        entries.sort_by_key(|&(hash, _, _)| hash & mask);
+        for (hash, key, value) in entries {
+            self.grow_for_insert(hash, &key);
+            self.insert_entry(Entry::new(key, value, hash), false);
         }
     }
 
-    fn insert_entry(&mut self, mut entry: Entry<K, V>) {
-        let slot = entry.hash % self.inner.len();
-        let mut i = slot;
+    fn grow_if_needed(&mut self) {
+        if self.needs_growth() && !self.grow() && !self.has_spare_slot() {
+            self.panic_full();
+        }
+    }
 
-        loop {
-            let cur = self.inner.get_mut(i);
-            // We've probably reached the end of the backing vector after probing and not finding an empty spot. We'll just append the new entry at this point.
-            // I'm not sure if this can ever happen but I'll just put it in here as a failsafe
-            if let None = cur {
-                self.inner.push(MapEntry::Occupied(entry));
-                break;
-            }
+    /// Like `grow_if_needed`, for inserting `key` under `hash`, which may just update an entry already in the map.
+    /// Updates don't take up a slot, so a table its growth policy keeps from growing still takes them once it's full.
+    fn grow_for_insert(&mut self, hash: usize, key: &K) {
+        if self.needs_growth()
+            && !self.grow()
+            && !self.has_spare_slot()
+            && self.probe_hashed(hash, key).0.is_none()
+        {
+            self.panic_full();
+        }
+    }
+
+    /// Whether one more entry fits without growing. Probing only terminates as long as one slot stays vacant.
+    fn has_spare_slot(&self) -> bool {
+        self.num_items + 1 < self.inner.len()
+    }
+
+    #[cold]
+    fn panic_full(&self) -> ! {
+        panic!(
+            "RHMap is full and its growth policy doesn't allow it to grow past {} slots",
+            self.inner.len()
+        );
+    }
+
+    /// Whether the next insert would resize the table.
+    pub(crate) fn needs_growth(&self) -> bool {
+        self.num_items >= max_load(self.inner.len(), self.load_factor)
+    }
+
+    /// An empty map with the same hasher and settings, sized like this one would be after its next resize. Returns
+    /// `None` if the growth policy won't let the table grow.
+    pub(crate) fn empty_grown(&self) -> Option<Self> {
+        let target_size = match self.inner.len() {
+            0 => INITIAL_SIZE,
+            n => self.next_capacity(n),
+        };
+        if target_size <= self.inner.len() {
+            return None;
+        }
+
+        let mut map = Self::with_buckets_in(
+            target_size,
+            self.hasher_builder.clone(),
+            self.allocator().clone(),
+        );
+        self.copy_settings_to(&mut map);
+        Some(map)
+    }
 
-            let cur = cur.unwrap();
-            if let MapEntry::Occupied(occupied_entry) = cur {
-                if occupied_entry.key == entry.key {
-                    // Update value
-                    let _ = std::mem::replace(occupied_entry, entry);
+    /// Places the entry with the robinhood rules and returns the slot index it ended up in. If the caller knows the
+    /// key is `unique`, the key comparisons used to detect updates are skipped.
+    fn insert_entry(&mut self, mut entry: Entry<K, V>, unique: bool) -> usize {
+        let mask = self.mask;
+        let mut i = entry.hash & mask;
+        // Where the entry we were given ends up. Entries it evicts along the way keep moving but this one stays put.
+        let mut placed = None;
+        // The PSL of the entry we're carrying if it were placed in slot `i`.
+        let mut psl = 0;
+
+        loop {
+            // Probing wraps around to the start of the table. The load factor guarantees there's always a vacancy
+            // somewhere so this terminates.
+            if self.ctrl[i] != EMPTY {
+                // SAFETY: the control byte says the slot is initialized.
+                let occupied_entry = unsafe { self.inner[i].assume_init_mut() };
+                if !unique && occupied_entry.hash == entry.hash && occupied_entry.key == entry.key {
+                    // Update value. Both entries share a home slot so they also share a PSL.
+                    let _ = core::mem::replace(occupied_entry, entry);
                     // Return to prevent updating num items.
-                    return;
+                    return placed.unwrap_or(i);
                 }
 
-                if entry.psl > occupied_entry.psl {
-                    std::mem::swap(&mut entry, occupied_entry);
+                let occupied_psl = psl_of(i, occupied_entry.hash, mask);
+                if psl > occupied_psl {
+                    mem::swap(&mut entry, occupied_entry);
+                    let placed_hash = occupied_entry.hash;
+                    // `entry` now holds the evicted entry, which is off the table until we find it a new slot.
+                    self.ctrl[i] = h2(placed_hash);
+                    self.note_placed(psl);
+                    self.note_taken(occupied_psl);
+                    placed.get_or_insert(i);
+                    psl = occupied_psl;
                     continue;
                 }
 
-                i += 1;
+                i = (i + 1) & mask;
             } else {
                 // Insert entry into the vacancy.
-                let _ = std::mem::replace(cur, MapEntry::Occupied(entry));
+                self.put_slot(i, entry);
+                self.note_placed(psl);
                 break;
             }
 
-            entry.psl += 1;
-            self.max_psl = max(self.max_psl, entry.psl);
+            psl += 1;
         }
 
         self.num_items += 1;
+        self.generation += 1;
+        self.debug_check_invariants();
+        placed.unwrap_or(i)
+    }
+
+    /// Records that an entry with the given PSL now sits in the table.
+    fn note_placed(&mut self, psl: usize) {
+        if self.psl_counts.len() <= psl {
+            self.psl_counts.resize(psl + 1, 0);
+        }
+
+        self.psl_counts[psl] += 1;
+        self.max_psl = max(self.max_psl, psl);
+    }
+
+    /// Records that an entry with the given PSL left the table (or its slot), lowering `max_psl` if it was the last
+    /// entry that far from home.
+    fn note_taken(&mut self, psl: usize) {
+        self.psl_counts[psl] -= 1;
+        while self.max_psl > 0 && self.psl_counts[self.max_psl] == 0 {
+            self.max_psl -= 1;
+        }
+    }
+
+    /// Resets the bookkeeping once every entry has been taken out of the table.
+    fn forget_entries(&mut self) {
+        self.num_items = 0;
+        self.generation += 1;
+        self.max_psl = 0;
+        self.psl_counts.clear();
+        self.ctrl.clear();
+        self.ctrl.resize(self.inner.len(), EMPTY);
     }
 
     /// Gets the appropriate value given a valid key. Returns `None` if the key value mapping does not exist.
@@ -189,222 +563,2180 @@ impl<K: Hash + Eq, V, H: BuildHasher + Clone> RHMap<K, V, H> {
     /// over the maximum search time in case of the standard FCFS collision strategy.
     ///
     /// tl;dr - In general, even in the worst case, we can effectively consider lookup to be O(1) time.
-    pub fn get(&self, key: &K) -> Option<&V> {
-        if let Some(entry) = self.get_entry(key) {
-            return Some(&entry.value);
-        } else {
-            return None;
-        }
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        self.get_entry(key).map(|entry| &entry.value)
     }
 
-    /// There are some additional (minor) optimizations in place here. Namely:
-    /// We return nothing if we encounter an entry with a psl less than the number of steps we've walked.
-    /// We return nothing if the number of steps we've walked exceeds the maximum psl value ever recorded.
-    fn get_entry(&self, key: &K) -> Option<&Entry<K, V>> {
-        let hash = self.hash_key(key);
-        let slot = hash % self.inner.len();
-        let mut d = slot;
-
-        while d < self.inner.len() {
-            let cur = self.inner.get(d).unwrap();
-            if let MapEntry::Occupied(entry) = cur {
-                if entry.key == *key {
-                    return Some(entry);
-                }
+    /// Gets a mutable reference to the value associated with the key.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&mut V> {
+        let i = self.find_index(key)?;
+        self.entry_at_mut(i).map(|entry| &mut entry.value)
+    }
 
-                // If we walked d steps and we encounter an entry that is some distance less than d from its home, we can stop.
-                // OR: Our probing has reached to a point where it is impossible to find an entry this far out from home so we
-                // can confidently stop in this case as well.
-                if entry.psl < d || d > self.max_psl {
-                    return None;
+    /// Looks up many keys at once and returns their values in the same order. Keys are hashed and the cache lines of
+    /// their home slots prefetched a chunk at a time before any of them is probed, so the memory latency of one lookup
+    /// overlaps with the others. Worth it for tables too large for the CPU caches. Prefetching needs the `prefetch`
+    /// feature, without it this is a plain loop over `get`.
+    pub fn get_batch<Q: ?Sized + Hash + Equivalent<K>>(&self, keys: &[&Q]) -> Vec<Option<&V>> {
+        let mut values = Vec::with_capacity(keys.len());
+        let mut hashes = [0; BATCH_CHUNK];
+        for chunk in keys.chunks(BATCH_CHUNK) {
+            for (hash, key) in hashes.iter_mut().zip(chunk) {
+                *hash = self.make_hash(key);
+                if !self.inner.is_empty() {
+                    let slot = *hash & self.mask;
+                    prefetch(&self.ctrl[slot]);
+                    prefetch(&self.inner[slot]);
                 }
-            } else {
-                return None;
             }
 
-            d += 1;
+            for (&hash, key) in hashes.iter().zip(chunk) {
+                let value = self
+                    .probe_hashed(hash, *key)
+                    .0
+                    .and_then(|i| self.entry_at(i));
+                values.push(value.map(|entry| &entry.value));
+            }
         }
 
-        return None;
+        values
     }
 
-    /// Clears all entries but preserves the allocated memory for use later.
-    pub fn clear(&mut self) {
-        let old_capacity = self.inner.len();
-        self.inner.clear();
-
-        let mut i = 0;
-        while i < old_capacity {
-            self.inner.push(MapEntry::VacantEntry);
-            i += 1;
-        }
+    /// Gets the value of the entry with the given hash whose key satisfies `eq`, for callers that already know the hash
+    /// of the key they're after (e.g. from a shard router) and don't want it hashed again. `hash` must be what this
+    /// map's hasher produces for the key (see `hasher`), otherwise the entry isn't found.
+    pub fn get_with_hash<F: FnMut(&K) -> bool>(&self, hash: u64, eq: F) -> Option<&V> {
+        let i = self.probe_with(hash as usize, eq).0?;
+        self.entry_at(i).map(|entry| &entry.value)
+    }
 
-        self.num_items = 0;
+    /// Inserts a key value pair under a hash the caller already computed, overwriting any previous value, without
+    /// hashing the key. `hash` must be what this map's hasher produces for `key`, otherwise the entry can't be found
+    /// afterwards. Debug builds check this.
+    pub fn insert_with_hash(&mut self, hash: u64, key: K, value: V) {
+        debug_assert_eq!(
+            hash as usize,
+            self.make_hash(&key),
+            "insert_with_hash was given a hash that doesn't match the key"
+        );
+        self.grow_for_insert(hash as usize, &key);
+        self.insert_entry(Entry::new(key, value, hash as usize), false);
     }
 
-    /// Checks to see if the provided key is associated with any value.
-    pub fn contains_key(&self, key: &K) -> bool {
-        let entry = self.get_entry(key);
-        if let Some(_) = entry {
-            return true;
-        } else {
-            return false;
-        }
+    /// Gets the stored key along with its value.
+    pub fn get_key_value<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<(&K, &V)> {
+        self.get_entry(key).map(|entry| (&entry.key, &entry.value))
     }
 
-    /// Gets the length / number of entries of the hashmap.
-    pub fn len(&self) -> usize {
-        self.num_items
+    /// Removes the entry with the given key and returns the stored key and value, if there was one.
+    pub fn remove_entry<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<(K, V)> {
+        self.remove_entry_hashed(self.make_hash(key), key)
     }
 
-    /// Gets the capacity of the hashmap.
-    pub fn capacity(&self) -> usize {
-        self.inner.len()
+    /// Same as `remove_entry` but for callers that have already hashed the key.
+    pub(crate) fn remove_entry_hashed<Q: ?Sized + Equivalent<K>>(
+        &mut self,
+        hash: usize,
+        key: &Q,
+    ) -> Option<(K, V)> {
+        let i = self.probe_hashed(hash, key).0?;
+        let entry = self.remove_at(i);
+        self.shrink_if_needed();
+        Some((entry.key, entry.value))
     }
 
-    /// Allocates a new map of a different size and then moves the contents of the previous map into it.
-    fn resize(&mut self) {
-        let target_size: usize = match self.inner.len() {
-            0 => INITIAL_SIZE,
-            n => 2 * n,
-        };
+    /// Keeps only the entries for which `f` returns `true`.
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        self.extract_if(|k, v| !f(k, v)).for_each(drop);
+        self.shrink_if_needed();
+    }
 
-        let mut new_map = Self::with_capacity_and_hasher(target_size, self.hasher_builder.clone());
-        // Filters out all vacant entries since we don't care about those.
-        let entries = self.inner.drain(0..).filter_map(|entry| {
-            if let MapEntry::Occupied(inner_entry) = entry {
-                return Some(inner_entry);
-            } else {
-                return None;
-            }
-        });
+    /// There are some additional (minor) optimizations in place here. Namely:
+    /// We only compare keys for slots whose control byte holds a matching hash fragment.
+    /// We return nothing if we encounter an entry with a psl less than the number of steps we've walked.
+    /// We return nothing if the number of steps we've walked exceeds the largest psl currently in the table.
+    fn get_entry<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&Entry<K, V>> {
+        self.find_index(key).and_then(|i| self.entry_at(i))
+    }
 
-        for entry in entries {
-            // Transfer ownership
-            new_map.insert_entry(entry);
-        }
+    /// Probes for the given key and returns the index of the slot holding it.
+    pub(crate) fn find_index<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<usize> {
+        self.probe(key).0
+    }
 
-        // Replace with the new resized hashmap.
-        let _ = std::mem::replace(self, new_map);
+    /// Probes for the given key and returns the index of the slot holding it along with the number of slots that
+    /// were inspected along the way.
+    pub(crate) fn probe<Q: ?Sized + Hash + Equivalent<K>>(
+        &self,
+        key: &Q,
+    ) -> (Option<usize>, usize) {
+        self.probe_hashed(self.make_hash(key), key)
     }
 
-    /// Builds a new hasher, hashes the provided key and returns the hash.
-    fn hash_key(&self, key: &K) -> usize {
-        let mut hasher = self.hasher_builder.build_hasher();
-        key.hash(&mut hasher);
-        hasher.finish() as usize
+    /// Same as `probe` but for callers that have already hashed the key.
+    pub(crate) fn probe_hashed<Q: ?Sized + Equivalent<K>>(
+        &self,
+        hash: usize,
+        key: &Q,
+    ) -> (Option<usize>, usize) {
+        self.probe_with(hash, |k| key.equivalent(k))
     }
-}
 
-impl<K: Eq + Hash, V, H: BuildHasher + Clone> IntoIterator for RHMap<K, V, H> {
-    type Item = (K, V);
-    type IntoIter = vec::IntoIter<Self::Item>;
+    /// Same as `probe_hashed` but keys are matched with `eq`, which is only called for entries whose full hash is
+    /// `hash`.
+    pub(crate) fn probe_with<F: FnMut(&K) -> bool>(
+        &self,
+        hash: usize,
+        mut eq: F,
+    ) -> (Option<usize>, usize) {
+        if self.inner.is_empty() {
+            return (None, 0);
+        }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.inner
-            .into_iter()
-            .filter_map(|e| {
-                if let MapEntry::Occupied(entry) = e {
-                    return Some((entry.key, entry.value));
-                } else {
-                    return None;
+        let slot = hash & self.mask;
+        let fragment = h2(hash);
+        // How far we've walked from the home slot. Probing wraps around to the start of the table.
+        let mut dist = 0;
+
+        // Our probing can stop once it reaches a point where it is impossible to find an entry this far out from home.
+        // Control bytes are scanned a group at a time.
+        while dist <= self.max_psl {
+            let pos = (slot + dist) & self.mask;
+            let group = Group::load(&self.ctrl, pos, self.mask);
+            // Nothing past a vacancy or past the largest psl belongs to our probe sequence.
+            let in_range = self.max_psl - dist + 1;
+            if self.max_psl > PREFETCH_PSL && in_range > GROUP_WIDTH {
+                // The probe may run on into the next group, start loading it while this one is scanned.
+                let next = (pos + GROUP_WIDTH) & self.mask;
+                prefetch(&self.ctrl[next]);
+                prefetch(&self.inner[next]);
+            }
+            let empty = group.match_empty().lowest().filter(|&i| i < in_range);
+            let limit = empty.unwrap_or_else(|| min(in_range, GROUP_WIDTH));
+
+            // Only entries whose hash fragment matches are worth touching, and only those whose full hash matches too
+            // are worth a possibly expensive key comparison.
+            for i in group.match_byte(fragment).take_while(|&i| i < limit) {
+                let d = (pos + i) & self.mask;
+                if let Some(entry) = self.entry_at(d) {
+                    if entry.hash == hash && eq(&entry.key) {
+                        return (Some(d), dist + i + 1);
+                    }
+
+                    // If we walked dist + i steps and we encounter an entry that is some distance less than that
+                    // from its home, we can stop: our key would have evicted it.
+                    if psl_of(d, entry.hash, self.mask) < dist + i {
+                        return (None, dist + i + 1);
+                    }
                 }
-            })
-            .collect::<Vec<(K, V)>>()
-            .into_iter()
+            }
+
+            if let Some(i) = empty {
+                return (None, dist + i + 1);
+            }
+
+            dist += limit;
+        }
+
+        (None, dist)
     }
-}
 
-impl<'a, K: Eq + Hash, V, H: BuildHasher + Clone> IntoIterator for &'a RHMap<K, V, H> {
-    type Item = (&'a K, &'a V);
-    type IntoIter = vec::IntoIter<Self::Item>;
+    /// The largest PSL of any entry in the map, i.e. the furthest any entry sits from its home slot. Lookups never
+    /// probe past this distance, so it bounds the cost of every lookup, hit or miss.
+    pub fn max_probe_length(&self) -> usize {
+        self.max_psl
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.inner
+    /// Summarizes how far entries sit from their home slots. Computed from the PSL histogram the map keeps anyway, so
+    /// it costs `O(max_probe_length)` rather than a scan of the table.
+    pub fn probe_stats(&self) -> ProbeStats {
+        let histogram = self.psl_histogram();
+        let n = self.num_items as f64;
+        if self.num_items == 0 {
+            return ProbeStats::default();
+        }
+
+        let mean = histogram
             .iter()
-            .filter_map(|e| {
-                if let MapEntry::Occupied(entry) = e {
-                    return Some((&entry.key, &entry.value));
-                } else {
-                    return None;
-                }
+            .enumerate()
+            .map(|(psl, &count)| (psl * count) as f64)
+            .sum::<f64>()
+            / n;
+        let variance = histogram
+            .iter()
+            .enumerate()
+            .map(|(psl, &count)| {
+                let deviation = psl as f64 - mean;
+                count as f64 * deviation * deviation
             })
-            .collect::<Vec<(&K, &V)>>()
-            .into_iter()
+            .sum::<f64>()
+            / n;
+
+        ProbeStats {
+            mean,
+            variance,
+            max: self.max_psl,
+        }
     }
-}
 
-impl<'a, K: Eq + Hash, V, H: BuildHasher + Clone> IntoIterator for &'a mut RHMap<K, V, H> {
-    type Item = (&'a K, &'a mut V);
-    type IntoIter = vec::IntoIter<Self::Item>;
+    /// The number of entries at each PSL: `psl_histogram()[d]` entries sit `d` slots past their home slot. Empty for an
+    /// empty map, and never longer than `max_probe_length() + 1`.
+    pub fn psl_histogram(&self) -> &[usize] {
+        if self.num_items == 0 {
+            return &[];
+        }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.inner
-            .iter_mut()
-            .filter_map(|e| {
-                if let MapEntry::Occupied(entry) = e {
-                    return Some((&entry.key, &mut entry.value));
-                } else {
-                    return None;
-                }
-            })
-            .collect::<Vec<(&K, &mut V)>>()
-            .into_iter()
+        &self.psl_counts[..=self.max_psl]
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::super::fx_build_hasher::FxBuildHasher;
-    use super::*;
+    /// Verifies the table's internal consistency and reports the first violation found: every entry sits in a probe
+    /// sequence without gaps and in Robin Hood order, its control byte and PSL match its stored hash, and the item
+    /// count and PSL histogram agree with the slots. Walks the whole table, so it's meant for tests and debugging.
+    /// The `check-invariants` feature runs it after every change in debug builds.
+    pub fn check_invariants(&self) -> Result<(), &'static str> {
+        let len = self.inner.len();
+        if self.ctrl.len() != len {
+            return Err("Control bytes and slots differ in length");
+        }
+        if len == 0 {
+            return match self.num_items {
+                0 => Ok(()),
+                _ => Err("Empty table holds items"),
+            };
+        }
+        if !len.is_power_of_two() || self.mask != len - 1 {
+            return Err("Bucket count is not a power of two matching the mask");
+        }
 
-    #[test]
-    fn it_constructs_with_an_initial_capacity() {
-        let initial_capacity = 5;
-        let hashmap: RHMap<&str, i32, FxBuildHasher> = RHMap::with_capacity(initial_capacity);
+        let mut psl_counts = vec![0; self.max_psl + 1];
+        let mut items = 0;
+        for i in 0..len {
+            let entry = match self.entry_at(i) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            items += 1;
+
+            if self.ctrl[i] != h2(entry.hash) {
+                return Err("Control byte doesn't match the stored hash");
+            }
+            let psl = psl_of(i, entry.hash, self.mask);
+            if psl > self.max_psl {
+                return Err("Entry sits further from home than the max probe length");
+            }
+            psl_counts[psl] += 1;
+
+            // An entry away from home must follow one that is at most one step less far from its own home, otherwise
+            // lookups would stop at a gap or a richer entry before reaching it.
+            if psl > 0 {
+                let prev = (i + len - 1) & self.mask;
+                match self.entry_at(prev) {
+                    Some(prev_entry) if psl_of(prev, prev_entry.hash, self.mask) + 1 >= psl => {}
+                    Some(_) => return Err("Entries are out of Robin Hood order"),
+                    None => return Err("Entry is unreachable past a vacant slot"),
+                }
+            }
+        }
 
-        assert_eq!(hashmap.capacity(), initial_capacity);
+        if items != self.num_items {
+            return Err("Item count doesn't match the occupied slots");
+        }
+        if items > 0 && psl_counts.last() == Some(&0) {
+            return Err("No entry sits as far from home as the max probe length");
+        }
+        if items > 0 && psl_counts != self.psl_histogram() {
+            return Err("PSL histogram doesn't match the occupied slots");
+        }
+        Ok(())
     }
 
-    #[test]
-    fn it_inserts_values_without_initial_capacity() {
-        let mut hashmap = RHMap::new();
-
-        for x in 0..100 {
-            hashmap.insert(x, x + 1);
+    /// Runs `check_invariants` and panics on a violation, in debug builds with the `check-invariants` feature.
+    #[inline(always)]
+    fn debug_check_invariants(&self) {
+        #[cfg(all(debug_assertions, feature = "check-invariants"))]
+        if let Err(violation) = self.check_invariants() {
+            panic!("RHMap invariant violated: {}", violation);
         }
+    }
 
-        assert_eq!(hashmap.len(), 100);
+    /// Returns the entry stored at the given slot index, if that slot is occupied.
+    pub(crate) fn entry_at(&self, index: usize) -> Option<&Entry<K, V>> {
+        match self.ctrl.get(index) {
+            // SAFETY: the control byte says the slot is initialized.
+            Some(&ctrl) if ctrl != EMPTY => Some(unsafe { self.inner[index].assume_init_ref() }),
+            _ => None,
+        }
+    }
 
-        for x in 100..0 {
-            let val = hashmap.get(&x).unwrap();
-            assert_eq!(*val, x + 1);
+    /// Returns a mutable reference to the entry stored at the given slot index, if that slot is occupied.
+    pub(crate) fn entry_at_mut(&mut self, index: usize) -> Option<&mut Entry<K, V>> {
+        match self.ctrl.get(index) {
+            // SAFETY: the control byte says the slot is initialized.
+            Some(&ctrl) if ctrl != EMPTY => Some(unsafe { self.inner[index].assume_init_mut() }),
+            _ => None,
         }
     }
 
-    #[test]
-    fn it_inserts_values_with_initial_capacity() {
-        let mut book_reviews = RHMap::with_capacity(10);
-        let key = "The Adventures of Sherlock Holmes".to_string();
-        let value = "Eye lyked it alot.".to_string();
-        book_reviews.insert(key, value);
+    /// Moves the entry out of the slot at `index`, if it is occupied, and marks the slot vacant. Doesn't touch any of
+    /// the bookkeeping.
+    fn take_slot(&mut self, index: usize) -> Option<Entry<K, V>> {
+        if self.ctrl[index] == EMPTY {
+            return None;
+        }
 
-        assert_eq!(book_reviews.capacity(), 10);
-        assert_eq!(
-            *book_reviews
+        // Mark the slot vacant first so the entry can never be read or dropped twice.
+        self.ctrl[index] = EMPTY;
+        // SAFETY: the control byte said the slot was initialized, and it now says it isn't.
+        Some(unsafe { self.inner[index].assume_init_read() })
+    }
+
+    /// Moves the entry into the vacant slot at `index`. Doesn't touch any of the bookkeeping.
+    fn put_slot(&mut self, index: usize, entry: Entry<K, V>) {
+        debug_assert_eq!(
+            self.ctrl[index], EMPTY,
+            "put_slot called on an occupied slot"
+        );
+        self.ctrl[index] = h2(entry.hash);
+        self.inner[index].write(entry);
+    }
+
+    /// Puts an entry straight into a vacant slot of a table being restored from a snapshot, which recorded where every
+    /// entry goes, so nothing is probed or moved. The caller checks the finished table with `check_invariants`.
+    #[cfg(feature = "std")]
+    pub(crate) fn restore_at(&mut self, index: usize, entry: Entry<K, V>) {
+        let psl = psl_of(index, entry.hash, self.mask);
+        self.put_slot(index, entry);
+        self.note_placed(psl);
+        self.num_items += 1;
+    }
+
+    /// Moves every entry out of the table in slot order, leaving all slots vacant. The bookkeeping is left for the
+    /// caller to reset (or drop).
+    fn take_entries(&mut self) -> impl Iterator<Item = Entry<K, V>> + '_ {
+        (0..self.inner.len()).filter_map(move |i| self.take_slot(i))
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.take_entries().for_each(drop);
+        self.forget_entries();
+    }
+
+    /// Gets the entry for the given key for in-place updates, like `HashMap::entry` in std. The key is hashed once,
+    /// and kept for the insert if the entry turns out to be vacant.
+    pub fn entry(&mut self, key: K) -> entry::Entry<'_, K, V, H, A> {
+        let hash = self.make_hash(&key);
+        match self.probe_hashed(hash, &key).0 {
+            Some(index) => entry::Entry::Occupied(OccupiedEntry::new(self, index)),
+            None => entry::Entry::Vacant(VacantEntry::new(self, hash, key)),
+        }
+    }
+
+    /// Gets the entry for the given key, which may be any borrowed form of the map's key type. The owned key is only
+    /// built (via `K::from`) if the entry turns out to be vacant and a value gets inserted, so upserting with e.g. a
+    /// `&str` into a map keyed by `String` doesn't allocate on every lookup.
+    pub fn entry_ref<'a, 'b, Q: ?Sized + Hash + Eq>(
+        &'a mut self,
+        key: &'b Q,
+    ) -> EntryRef<'a, 'b, K, Q, V, H, A>
+    where
+        K: Borrow<Q>,
+    {
+        let hash = self.make_hash(key);
+        match self.probe_hashed(hash, key).0 {
+            Some(index) => EntryRef::Occupied(OccupiedEntry::new(self, index)),
+            None => EntryRef::Vacant(VacantEntryRef::new(self, hash, key)),
+        }
+    }
+
+    /// Starts a lookup by hash and a match closure, for layers built on top of the map (interners, caches keyed by
+    /// borrowed data) that manage hashes themselves.
+    pub fn raw_entry(&self) -> RawEntryBuilder<'_, K, V, H, A> {
+        RawEntryBuilder::new(self)
+    }
+
+    /// Starts a lookup by hash and a match closure that can insert on a miss. The owned key is only built once a
+    /// vacant entry is actually filled, so looking up a `&str` in a map keyed by `String` never allocates on a hit.
+    pub fn raw_entry_mut(&mut self) -> RawEntryBuilderMut<'_, K, V, H, A> {
+        RawEntryBuilderMut::new(self)
+    }
+
+    /// Returns a mutable reference to the value for `key`, inserting the result of `default` first if the key is
+    /// missing. The key is only hashed once and `default` only runs on a miss, which makes this the cheapest way to
+    /// memoize without going through `entry_ref`.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        let hash = self.make_hash(&key);
+        let index = match self.probe_hashed(hash, &key).0 {
+            Some(index) => index,
+            None => self.insert_vacant(hash, key, default()),
+        };
+
+        &mut self.entry_at_mut(index).unwrap().value
+    }
+
+    /// Replaces the value stored under `key` with `new`, but only if it currently equals `expected`. This lets
+    /// optimistic concurrency patterns (read, compute, swap if unchanged) be expressed in a single call.
+    pub fn compare_and_update<Q: ?Sized + Hash + Equivalent<K>>(
+        &mut self,
+        key: &Q,
+        expected: &V,
+        new: V,
+    ) -> Result<(), CasError>
+    where
+        V: PartialEq,
+    {
+        self.compare_and_update_hashed(self.make_hash(key), key, expected, new)
+    }
+
+    /// Same as `compare_and_update` but for callers that have already hashed the key.
+    pub(crate) fn compare_and_update_hashed<Q: ?Sized + Equivalent<K>>(
+        &mut self,
+        hash: usize,
+        key: &Q,
+        expected: &V,
+        new: V,
+    ) -> Result<(), CasError>
+    where
+        V: PartialEq,
+    {
+        let index = self.probe_hashed(hash, key).0.ok_or(CasError::NotFound)?;
+        let entry = self.entry_at_mut(index).unwrap();
+        if entry.value != *expected {
+            return Err(CasError::Mismatch);
+        }
+
+        entry.value = new;
+        Ok(())
+    }
+
+    /// Reports where the entry for `key` currently lives. Together with `resize_epoch` this lets callers that cache
+    /// slot positions cheaply tell whether a cached location has been invalidated by a resize. Note that inserts and
+    /// removes can still shift entries by a few slots within the same epoch.
+    pub fn locate<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<EntryLocation> {
+        let slot = self.find_index(key)?;
+        Some(EntryLocation {
+            slot,
+            psl: psl_of(slot, self.entry_at(slot)?.hash, self.mask),
+            epoch: self.epoch,
+        })
+    }
+
+    /// Looks up `key` once and returns a handle to its slot. As long as no entry moves in the meantime (no new keys
+    /// are inserted, nothing is removed and the table isn't resized), `get_by_handle` and `get_mut_by_handle` reach the
+    /// entry again without hashing or probing. Overwriting or mutating values in place keeps handles valid.
+    ///
+    /// Handles are only meaningful for the map that created them.
+    pub fn find_handle<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<EntryHandle> {
+        let slot = self.find_index(key)?;
+        Some(EntryHandle {
+            slot,
+            generation: self.generation,
+        })
+    }
+
+    /// Gets the value the handle points at. Returns `None` if entries may have moved since the handle was created,
+    /// in which case a fresh handle needs to be looked up with `find_handle`.
+    pub fn get_by_handle(&self, handle: EntryHandle) -> Option<&V> {
+        if handle.generation != self.generation {
+            return None;
+        }
+
+        self.entry_at(handle.slot).map(|entry| &entry.value)
+    }
+
+    /// Gets a mutable reference to the value the handle points at. Returns `None` if the handle is stale.
+    pub fn get_mut_by_handle(&mut self, handle: EntryHandle) -> Option<&mut V> {
+        if handle.generation != self.generation {
+            return None;
+        }
+
+        self.entry_at_mut(handle.slot).map(|entry| &mut entry.value)
+    }
+
+    /// The number of times the table has been reallocated. Every entry moves when this changes.
+    pub fn resize_epoch(&self) -> usize {
+        self.epoch
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.get_entry(key).is_some()
+    }
+
+    /// Gets the length / number of entries of the hashmap.
+    pub fn len(&self) -> usize {
+        self.num_items
+    }
+
+    /// Returns `true` if the hashmap contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+
+    /// Gets the number of entries the map can hold before it has to grow: the bucket count scaled by the load factor.
+    pub fn capacity(&self) -> usize {
+        max_load(self.inner.len(), self.load_factor)
+    }
+
+    /// Gets the number of slots in the table, occupied or not. Always a power of two.
+    pub fn bucket_count(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Gets the heap bytes currently held by the table: its slots, control bytes and probe length bookkeeping. Doesn't
+    /// include memory owned by the keys and values themselves, see `deep_memory_usage` for that.
+    pub fn memory_usage(&self) -> usize {
+        self.inner.capacity() * mem::size_of::<MaybeUninit<Entry<K, V>>>()
+            + self.ctrl.capacity()
+            + self.psl_counts.capacity() * mem::size_of::<usize>()
+    }
+
+    /// Like `memory_usage`, plus the heap bytes `entry_bytes` reports for each entry, e.g. the length of a `String`
+    /// key.
+    pub fn deep_memory_usage<F: FnMut(&K, &V) -> usize>(&self, mut entry_bytes: F) -> usize {
+        self.memory_usage() + self.iter().map(|(k, v)| entry_bytes(k, v)).sum::<usize>()
+    }
+
+    /// Gets the hasher builder keys are hashed with.
+    pub fn hasher(&self) -> &H {
+        &self.hasher_builder
+    }
+
+    /// Gets the fraction of slots that may be occupied before the map grows.
+    pub fn load_factor(&self) -> f32 {
+        self.load_factor
+    }
+
+    /// Replaces the policy deciding how far the table grows once it's full. Maps double by default.
+    pub fn set_growth_policy<P: GrowthPolicy + Send + Sync + 'static>(&mut self, policy: P) {
+        self.growth = Some(Arc::new(policy));
+    }
+
+    /// The number of slots the growth policy allows a table of `current` slots to grow to, a power of two: the
+    /// smallest one holding the entries the policy asks for, within its bucket limit.
+    fn next_capacity(&self, current: usize) -> usize {
+        // A tiny table at a low load factor may not hold a single entry, which no policy could grow from.
+        let entries = max(max_load(current, self.load_factor), 1);
+        let (target, max_buckets) = match &self.growth {
+            Some(policy) => (policy.next_capacity(entries), policy.max_buckets()),
+            None => (Doubling.next_capacity(entries), usize::MAX),
+        };
+        if target <= entries {
+            return current;
+        }
+
+        let mut buckets = current;
+        while max_load(buckets, self.load_factor) < target {
+            match buckets.checked_mul(2) {
+                Some(doubled) => buckets = doubled,
+                None => break,
+            }
+        }
+        max(buckets.min(max_buckets), current)
+    }
+
+    /// Carries the load factor and growth policy over to a map derived from this one.
+    fn copy_settings_to<H2: BuildHasher + Clone>(&self, map: &mut RHMap<K, V, H2, A>) {
+        map.load_factor = self.load_factor;
+        map.shrink_threshold = self.shrink_threshold;
+        map.growth = self.growth.clone();
+    }
+
+    /// Changes the load factor. Takes effect on the next insert or `reserve`. Turns automatic shrinking off if its
+    /// threshold isn't below the new load factor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `load_factor` is outside of `MIN_LOAD_FACTOR..=MAX_LOAD_FACTOR`.
+    pub fn set_load_factor(&mut self, load_factor: f32) {
+        check_load_factor(load_factor);
+        if self
+            .shrink_threshold
+            .is_some_and(|threshold| threshold >= load_factor)
+        {
+            self.shrink_threshold = None;
+        }
+        self.load_factor = load_factor;
+    }
+
+    /// Gets the occupancy below which removals shrink the table, if automatic shrinking is on.
+    pub fn shrink_threshold(&self) -> Option<f32> {
+        self.shrink_threshold
+    }
+
+    /// Turns on automatic shrinking: whenever a removal leaves fewer than `threshold` of the slots occupied, the table
+    /// is reallocated to a size where it's about half as full as the load factor allows. `None` turns it back off,
+    /// which is the default. Long-lived maps that empty out then give memory back without `shrink_to_fit` calls.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` isn't between 0 and the load factor.
+    pub fn set_shrink_threshold(&mut self, threshold: Option<f32>) {
+        if let Some(threshold) = threshold {
+            assert!(
+                threshold > 0.0 && threshold < self.load_factor,
+                "shrink threshold {} must be between 0 and the load factor {}",
+                threshold,
+                self.load_factor
+            );
+        }
+        self.shrink_threshold = threshold;
+    }
+
+    /// Shrinks the table to the smallest capacity that holds the current entries within the load factor.
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to_hold(self.num_items);
+    }
+
+    /// Shrinks the table if automatic shrinking is on and occupancy dropped below the threshold. Leaves room for as
+    /// many entries again so a few inserts right after don't grow it straight back.
+    pub(crate) fn shrink_if_needed(&mut self) {
+        if let Some(threshold) = self.shrink_threshold {
+            if (self.num_items as f64) < self.inner.len() as f64 * threshold as f64 {
+                self.shrink_to_hold(2 * self.num_items);
+            }
+        }
+    }
+
+    /// Reallocates the table to the smallest power of two holding `n` entries, if that's smaller than it is now.
+    fn shrink_to_hold(&mut self, n: usize) {
+        let target_size = buckets_for_load(n, self.load_factor);
+        if target_size < self.inner.len() {
+            self.resize_to(target_size);
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more entries, so that many inserts won't trigger a resize.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.num_items + additional;
+        if needed <= max_load(self.inner.len(), self.load_factor) {
+            return;
+        }
+
+        let mut target_size = max(self.inner.len(), INITIAL_SIZE);
+        // Keep growing (like `resize` does) until the load factor is respected or the growth policy refuses.
+        while needed > max_load(target_size, self.load_factor) {
+            let next = self.next_capacity(target_size);
+            if next <= target_size {
+                break;
+            }
+            target_size = next;
+        }
+        if target_size <= self.inner.len() {
+            return;
+        }
+
+        self.resize_to(target_size);
+    }
+
+    /// Reserves capacity for exactly `additional` more entries: the table goes straight to the smallest bucket count
+    /// that holds them at the map's load factor, with at most one rehash. Unlike `reserve`, which stops wherever the
+    /// growth policy stops, the capacity is guaranteed afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the growth policy doesn't let the table grow that far.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let needed = self
+            .num_items
+            .checked_add(additional)
+            .expect("RHMap capacity overflow");
+        let target_size = buckets_for_load(needed, self.load_factor);
+        if target_size <= self.inner.len() {
+            return;
+        }
+
+        // The policy only decides how large the table may get here, not the steps it takes getting there.
+        let mut allowed = max(self.inner.len(), INITIAL_SIZE);
+        while allowed < target_size {
+            let next = self.next_capacity(allowed);
+            assert!(
+                next > allowed,
+                "RHMap growth policy doesn't allow {} slots",
+                target_size
+            );
+            allowed = next;
+        }
+
+        self.resize_to(target_size);
+    }
+
+    /// Moves all entries out of `other` into `self`, leaving `other` empty but keeping its allocation. Capacity for
+    /// every entry is reserved up front and the hashes already stored in `other` are reused rather than hashing each
+    /// key again. Values from `other` win for keys present in both maps.
+    pub fn append(&mut self, other: &mut Self) {
+        self.reserve(other.len());
+
+        let mut reuse_hashes = None;
+        for mut entry in other.take_entries() {
+            entry.hash = self.adopt_hash(&entry, &mut reuse_hashes);
+            // `reserve` stops short wherever the growth policy does, so every entry still needs the usual check.
+            self.grow_for_insert(entry.hash, &entry.key);
+            self.insert_entry(entry, false);
+        }
+
+        other.forget_entries();
+    }
+
+    /// Exchanges what `self` and `other` store under each of the given keys: values under keys present in both maps
+    /// are swapped, and entries under keys present in only one map move over to the other one. Both maps must share
+    /// a hasher (e.g. one map cloned from the other's builder), which lets each key be hashed just once. Debug builds
+    /// check this. Handy for double-buffered state handoff.
+    pub fn swap_entries<I: IntoIterator<Item = K>>(&mut self, other: &mut Self, keys: I) {
+        for key in keys {
+            let hash = self.make_hash(&key);
+            debug_assert_eq!(
+                hash,
+                other.make_hash(&key),
+                "swap_entries needs both maps to hash keys the same way"
+            );
+            match (
+                self.probe_hashed(hash, &key).0,
+                other.probe_hashed(hash, &key).0,
+            ) {
+                (Some(i), Some(j)) => {
+                    if let (Some(ours), Some(theirs)) =
+                        (self.entry_at_mut(i), other.entry_at_mut(j))
+                    {
+                        mem::swap(&mut ours.value, &mut theirs.value);
+                    }
+                }
+                (Some(i), None) => {
+                    let entry = self.remove_at(i);
+                    other.insert_vacant(hash, entry.key, entry.value);
+                }
+                (None, Some(j)) => {
+                    let entry = other.remove_at(j);
+                    self.insert_vacant(hash, entry.key, entry.value);
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    /// Merges `other` into `self`. Keys only present in one map are moved over as is, while for keys present in both
+    /// `f(key, self_value, other_value)` decides the value that is kept, e.g. summing counts when combining word
+    /// counts.
+    pub fn merge<F: FnMut(&K, V, V) -> V>(&mut self, mut other: Self, mut f: F) {
+        self.reserve(other.len());
+
+        let mut reuse_hashes = None;
+        for entry in other.take_entries() {
+            let hash = self.adopt_hash(&entry, &mut reuse_hashes);
+            match self.probe_hashed(hash, &entry.key).0 {
+                Some(index) => {
+                    // Take the existing entry out so its value can be handed to `f` by value.
+                    let existing = self.remove_at(index);
+                    let value = f(&existing.key, existing.value, entry.value);
+                    self.insert_vacant(existing.hash, existing.key, value);
+                }
+                None => {
+                    self.insert_vacant(hash, entry.key, entry.value);
+                }
+            }
+        }
+    }
+
+    /// Returns the hash of an entry coming from another map. Stored hashes are only reusable if both maps hash keys
+    /// identically, which is checked once (on the first entry) and remembered in `reuse_hashes`.
+    fn adopt_hash(&self, entry: &Entry<K, V>, reuse_hashes: &mut Option<bool>) -> usize {
+        match reuse_hashes {
+            Some(true) => entry.hash,
+            Some(false) => self.make_hash(&entry.key),
+            None => {
+                let hash = self.make_hash(&entry.key);
+                *reuse_hashes = Some(hash == entry.hash);
+                hash
+            }
+        }
+    }
+
+    /// Grows the table to the size the growth policy picks and moves the entries over. Returns `false` if the policy
+    /// won't let the table grow, in which case inserts go on past the load factor as long as a slot stays vacant.
+    fn grow(&mut self) -> bool {
+        let target_size: usize = match self.inner.len() {
+            0 => INITIAL_SIZE,
+            n => self.next_capacity(n),
+        };
+
+        if target_size <= self.inner.len() {
+            return false;
+        }
+        self.resize_to(target_size);
+        true
+    }
+
+    fn resize_to(&mut self, target_size: usize) {
+        if target_size > self.inner.len() {
+            self.grow_in_place(target_size);
+            return;
+        }
+
+        let mut new_map = Self::with_buckets_in(
+            target_size,
+            self.hasher_builder.clone(),
+            self.allocator().clone(),
+        );
+        self.copy_settings_to(&mut new_map);
+        // Vacant slots are skipped since we don't care about those.
+        for entry in self.take_entries() {
+            // Transfer ownership
+            new_map.insert_entry(entry, true);
+        }
+
+        // Replace with the new resized hashmap.
+        new_map.epoch = self.epoch + 1;
+        new_map.generation = self.generation + 1;
+        let _ = core::mem::replace(self, new_map);
+        self.debug_check_invariants();
+    }
+
+    /// Grows the allocation to `target_size` slots and redistributes the entries within it, so two full tables are
+    /// never alive at the same time. Every entry is flagged `PENDING` first, then the flagged ones are taken out one
+    /// at a time and placed under the new mask.
+    fn grow_in_place(&mut self, target_size: usize) {
+        debug_assert!(target_size > self.inner.len() && target_size.is_power_of_two());
+        let old_len = self.inner.len();
+        for byte in &mut self.ctrl {
+            if *byte != EMPTY {
+                *byte = PENDING;
+            }
+        }
+
+        self.inner.resize_with(target_size, MaybeUninit::uninit);
+        self.ctrl.resize(target_size, EMPTY);
+        self.mask = target_size - 1;
+        self.max_psl = 0;
+        self.psl_counts.clear();
+
+        for i in 0..old_len {
+            if self.ctrl[i] == PENDING {
+                self.ctrl[i] = EMPTY;
+                // SAFETY: pending slots are initialized, and the control byte now says this one isn't.
+                let entry = unsafe { self.inner[i].assume_init_read() };
+                self.place_pending(entry);
+            }
+        }
+
+        self.epoch += 1;
+        self.generation += 1;
+        self.debug_check_invariants();
+    }
+
+    /// Places an entry taken out by `grow_in_place` with the robinhood rules, treating `PENDING` slots as vacant: the
+    /// entry takes over the slot and the pending entry that was in it gets placed next, probing from its own home.
+    /// Keys are never compared since every entry is already known to be unique.
+    fn place_pending(&mut self, mut entry: Entry<K, V>) {
+        let mask = self.mask;
+        let mut i = entry.hash & mask;
+        let mut psl = 0;
+
+        loop {
+            match self.ctrl[i] {
+                EMPTY => {
+                    self.put_slot(i, entry);
+                    self.note_placed(psl);
+                    return;
+                }
+                PENDING => {
+                    // SAFETY: pending slots are initialized.
+                    let pending = unsafe { self.inner[i].assume_init_mut() };
+                    mem::swap(&mut entry, pending);
+                    self.ctrl[i] = h2(pending.hash);
+                    self.note_placed(psl);
+
+                    i = entry.hash & mask;
+                    psl = 0;
+                    continue;
+                }
+                _ => {
+                    // SAFETY: the control byte says the slot is initialized.
+                    let occupied_entry = unsafe { self.inner[i].assume_init_mut() };
+                    let occupied_psl = psl_of(i, occupied_entry.hash, mask);
+                    if psl > occupied_psl {
+                        mem::swap(&mut entry, occupied_entry);
+                        self.ctrl[i] = h2(occupied_entry.hash);
+                        self.note_placed(psl);
+                        self.note_taken(occupied_psl);
+                        psl = occupied_psl;
+                        continue;
+                    }
+                }
+            }
+
+            i = (i + 1) & mask;
+            psl += 1;
+        }
+    }
+
+    /// Rebuilds the table under a different hasher in a single pass, moving every entry into the returned map and
+    /// leaving `self` empty. Use this to periodically rotate hasher seeds or to migrate to another hasher without a
+    /// manual drain and collect.
+    pub fn rehash_with<H2: BuildHasher + Clone>(&mut self, new_builder: H2) -> RHMap<K, V, H2, A> {
+        let mut map =
+            RHMap::with_buckets_in(self.inner.len(), new_builder, self.allocator().clone());
+        self.copy_settings_to(&mut map);
+
+        for mut entry in self.take_entries() {
+            entry.hash = map.make_hash(&entry.key);
+            map.insert_entry(entry, true);
+        }
+
+        self.inner.clear();
+        self.forget_entries();
+        self.epoch += 1;
+
+        map
+    }
+
+    /// Turns the map into an immutable `FrozenMap` laid out for the shortest probes. The entries are compacted to
+    /// exactly `len()` of them, and only the index they're found through keeps the default load factor.
+    pub fn freeze(self) -> FrozenMap<K, V, H> {
+        FrozenMap::from_map(self, DEFAULT_LOAD_FACTOR)
+    }
+
+    /// Turns the map into an immutable `FrozenMap` laid out for the shortest probes at the given load factor, up to
+    /// 1.0 for a table without a single vacant slot.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `load_factor` lies within `0.1..=1.0`.
+    pub fn freeze_with_load_factor(self, load_factor: f32) -> FrozenMap<K, V, H> {
+        FrozenMap::from_map(self, load_factor)
+    }
+
+    /// Returns an iterator that lazily removes and yields every entry for which `pred` returns `true`. Entries are
+    /// removed with backward shift deletion as they are yielded, so if the iterator is dropped early the remaining
+    /// entries are simply kept.
+    pub fn extract_if<F: FnMut(&K, &mut V) -> bool>(
+        &mut self,
+        pred: F,
+    ) -> ExtractIf<'_, K, V, H, F, A> {
+        ExtractIf {
+            start: self.scan_start(),
+            map: self,
+            index: 0,
+            pred,
+        }
+    }
+
+    /// Empties the map and returns its entries in arbitrary order. The allocated memory is kept for use later, like
+    /// `clear`.
+    pub fn drain(&mut self) -> vec::IntoIter<(K, V)> {
+        let mut entries: Vec<(K, V)> = Vec::with_capacity(self.num_items);
+        entries.extend(self.take_entries().map(|entry| (entry.key, entry.value)));
+
+        self.forget_entries();
+        entries.into_iter()
+    }
+
+    /// Empties the map and returns its entries in ascending key order, for consumers that need sorted output. The
+    /// allocated memory is kept for use later, like `clear`.
+    pub fn drain_sorted(&mut self) -> vec::IntoIter<(K, V)>
+    where
+        K: Ord,
+    {
+        let mut entries: Vec<(K, V)> = Vec::with_capacity(self.num_items);
+        entries.extend(self.take_entries().map(|entry| (entry.key, entry.value)));
+
+        self.forget_entries();
+
+        // Keys are unique so an unstable sort can't reorder anything that compares equal.
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        entries.into_iter()
+    }
+
+    /// Splits the map in two: the first map holds the entries for which `pred` returns `true`, the second the rest.
+    /// Both maps share this map's hasher, so stored hashes are reused rather than recomputed.
+    pub fn partition<F: FnMut(&K, &V) -> bool>(mut self, pred: F) -> (Self, Self) {
+        let matching = self.split_by(pred);
+        (matching, self)
+    }
+
+    /// Moves the entries for which `pred` returns `true` out into a new map (sharing this map's hasher) and keeps
+    /// the rest in place.
+    pub fn split_by<F: FnMut(&K, &V) -> bool>(&mut self, mut pred: F) -> Self {
+        let mut matching =
+            Self::with_hasher_in(self.hasher_builder.clone(), self.allocator().clone());
+        self.copy_settings_to(&mut matching);
+        let start = self.scan_start();
+        let mut i = 0;
+
+        while i < self.inner.len() {
+            let slot = (start + i) & self.mask;
+            let hit = match self.entry_at(slot) {
+                Some(entry) => pred(&entry.key, &entry.value),
+                None => false,
+            };
+
+            if hit {
+                // Like `extract_if`, backward shift deletion fills the slot with an entry we still need to look at.
+                let entry = self.remove_at(slot);
+                matching.grow_if_needed();
+                matching.insert_entry(entry, true);
+            } else {
+                i += 1;
+            }
+        }
+
+        matching
+    }
+
+    /// Splits the map into `n` maps by hash prefix, e.g. to process them on `n` threads without rayon. The shards share
+    /// this map's hasher and settings, so they can be put back together with `append`, which reuses the stored hashes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    pub fn split_into(mut self, n: usize) -> Vec<Self> {
+        assert!(n > 0, "a map can't be split into 0 shards");
+        let mut shards: Vec<Self> = (0..n)
+            .map(|_| {
+                let mut shard =
+                    Self::with_hasher_in(self.hasher_builder.clone(), self.allocator().clone());
+                self.copy_settings_to(&mut shard);
+                shard.reserve(self.num_items / n);
+                shard
+            })
+            .collect();
+
+        for entry in self.take_entries() {
+            let shard = &mut shards[shard_of(entry.hash, n)];
+            shard.grow_if_needed();
+            shard.insert_entry(entry, true);
+        }
+        self.forget_entries();
+        shards
+    }
+
+    /// Borrowing counterpart of `split_into`: iterates over the entries of each of the `n` hash prefix shards, which
+    /// can be handed to scoped threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    pub fn iter_shards(&self, n: usize) -> Vec<vec::IntoIter<(&K, &V)>> {
+        assert!(n > 0, "a map can't be split into 0 shards");
+        let mut shards: Vec<Vec<(&K, &V)>> = (0..n).map(|_| Vec::new()).collect();
+        for entry in self.entries() {
+            shards[shard_of(entry.hash, n)].push((&entry.key, &entry.value));
+        }
+        shards.into_iter().map(Vec::into_iter).collect()
+    }
+
+    /// Calls `f` on every entry in arbitrary order. This is a plain loop over the slots with no iterator state to carry
+    /// around, which makes it the fastest way to scan the whole map and a natural traversal primitive for FFI.
+    pub fn for_each<F: FnMut(&K, &V)>(&self, mut f: F) {
+        for entry in self.entries() {
+            f(&entry.key, &entry.value);
+        }
+    }
+
+    /// Like `for_each` but with mutable access to the values.
+    pub fn for_each_mut<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
+        for entry in self.entries_mut() {
+            f(&entry.key, &mut entry.value);
+        }
+    }
+
+    /// Iterates over every key value pair in arbitrary order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            slots: self.ctrl.iter().zip(self.inner.iter()),
+            remaining: self.num_items,
+        }
+    }
+
+    /// Iterates over every key value pair in arbitrary order, with mutable references to the values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            slots: self.ctrl.iter().zip(self.inner.iter_mut()),
+            remaining: self.num_items,
+        }
+    }
+
+    /// Iterates over every key in arbitrary order.
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    /// Iterates over every value in arbitrary order.
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    /// Iterates over mutable references to every value in arbitrary order.
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut {
+            inner: self.iter_mut(),
+        }
+    }
+
+    /// Consumes the map and returns an iterator over its keys in arbitrary order.
+    pub fn into_keys(self) -> IntoKeys<K, V> {
+        IntoKeys {
+            inner: self.into_iter(),
+        }
+    }
+
+    /// Consumes the map and returns an iterator over its values in arbitrary order.
+    pub fn into_values(self) -> IntoValues<K, V> {
+        IntoValues {
+            inner: self.into_iter(),
+        }
+    }
+
+    /// Consumes the map and collects its keys into a `Vec` sized to fit exactly, in arbitrary order.
+    pub fn into_keys_vec(mut self) -> Vec<K> {
+        let mut keys = Vec::with_capacity(self.num_items);
+        keys.extend(self.take_entries().map(|entry| entry.key));
+
+        keys
+    }
+
+    /// Consumes the map and collects its values into a `Vec` sized to fit exactly, in arbitrary order.
+    pub fn into_values_vec(mut self) -> Vec<V> {
+        let mut values = Vec::with_capacity(self.num_items);
+        values.extend(self.take_entries().map(|entry| entry.value));
+
+        values
+    }
+
+    /// Iterates over the occupied slots in slot order.
+    fn entries(&self) -> impl Iterator<Item = &Entry<K, V>> + '_ {
+        self.ctrl
+            .iter()
+            .zip(&self.inner)
+            .filter(|(&ctrl, _)| ctrl != EMPTY)
+            // SAFETY: the control byte says the slot is initialized.
+            .map(|(_, slot)| unsafe { slot.assume_init_ref() })
+    }
+
+    /// Iterates mutably over the occupied slots in slot order.
+    fn entries_mut(&mut self) -> impl Iterator<Item = &mut Entry<K, V>> + '_ {
+        self.ctrl
+            .iter()
+            .zip(&mut self.inner)
+            .filter(|(&ctrl, _)| ctrl != EMPTY)
+            // SAFETY: the control byte says the slot is initialized.
+            .map(|(_, slot)| unsafe { slot.assume_init_mut() })
+    }
+
+    /// The control bytes along with the slots they describe, for the parallel iterators to split between threads.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn raw_slots(&self) -> (&[u8], &[Slot<K, V>]) {
+        (&self.ctrl, &self.inner)
+    }
+
+    /// Like `raw_slots` but with mutable access to the slots.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn raw_slots_mut(&mut self) -> (&[u8], &mut [Slot<K, V>]) {
+        (&self.ctrl, &mut self.inner)
+    }
+
+    /// Where scans that remove as they go should start: the first vacant slot. Backward shift deletion never moves
+    /// an entry across a vacancy, so starting there means removals only ever pull entries we haven't looked at yet
+    /// into the current slot, even for buckets that wrap around the end of the table.
+    fn scan_start(&self) -> usize {
+        self.ctrl
+            .iter()
+            .position(|&ctrl| ctrl == EMPTY)
+            .unwrap_or(0)
+    }
+
+    /// Hashes a key the way this map does, e.g. to route it to a shard, add it to a bloom filter or log it. The result
+    /// is what `get_with_hash` and `insert_with_hash` expect.
+    pub fn hash_key<Q: ?Sized + Hash>(&self, key: &Q) -> u64 {
+        self.hasher_builder.hash_one(key)
+    }
+
+    /// Builds a new hasher, hashes the provided key and returns the hash.
+    pub(crate) fn make_hash<Q: ?Sized + Hash>(&self, key: &Q) -> usize {
+        self.hash_key(key) as usize
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone> MapRead<K, V>
+    for RHMap<K, V, H, A>
+{
+    fn get(&self, key: &K) -> Option<&V> {
+        RHMap::get(self, key)
+    }
+
+    fn len(&self) -> usize {
+        RHMap::len(self)
+    }
+
+    fn probe_count(&self, key: &K) -> usize {
+        self.probe(key).1
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone> MapWrite<K, V>
+    for RHMap<K, V, H, A>
+{
+    fn insert(&mut self, key: K, value: V) {
+        RHMap::insert(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        RHMap::remove(self, key).is_ok()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone> Drop for RHMap<K, V, H, A> {
+    fn drop(&mut self) {
+        // `MaybeUninit` never drops its contents, so the occupied slots have to be dropped by hand.
+        if mem::needs_drop::<Entry<K, V>>() {
+            self.take_entries().for_each(drop);
+        }
+    }
+}
+
+/// Clones the table at its current size and settings, reusing the stored hashes instead of hashing every key again.
+impl<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone, A: Allocator + Clone> Clone
+    for RHMap<K, V, H, A>
+{
+    fn clone(&self) -> Self {
+        let mut map = Self::with_buckets_in(
+            self.inner.len(),
+            self.hasher_builder.clone(),
+            self.allocator().clone(),
+        );
+        self.copy_settings_to(&mut map);
+        for i in 0..self.inner.len() {
+            if let Some(entry) = self.entry_at(i) {
+                let entry = Entry::new(entry.key.clone(), entry.value.clone(), entry.hash);
+                map.insert_entry(entry, true);
+            }
+        }
+        map
+    }
+}
+
+/// Maps are equal when they hold the same keys with equal values, whatever order their tables keep them in.
+impl<K: Hash + Eq, V: PartialEq, H: BuildHasher + Clone, A: Allocator + Clone> PartialEq
+    for RHMap<K, V, H, A>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K: Hash + Eq, V: Eq, H: BuildHasher + Clone, A: Allocator + Clone> Eq for RHMap<K, V, H, A> {}
+
+/// Formats the map like the std maps do (`{key: value, ...}`), only printing occupied entries.
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone, A: Allocator + Clone> Debug
+    for RHMap<K, V, H, A>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self).finish()
+    }
+}
+
+impl<K: Eq + Hash, V, H: BuildHasher + Clone, A: Allocator + Clone> IntoIterator
+    for RHMap<K, V, H, A>
+{
+    type Item = (K, V);
+    type IntoIter = vec::IntoIter<Self::Item>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.take_entries()
+            .map(|entry| (entry.key, entry.value))
+            .collect::<Vec<(K, V)>>()
+            .into_iter()
+    }
+}
+
+impl<'a, K: Eq + Hash, V, H: BuildHasher + Clone, A: Allocator + Clone> IntoIterator
+    for &'a RHMap<K, V, H, A>
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Eq + Hash, V, H: BuildHasher + Clone, A: Allocator + Clone> IntoIterator
+    for &'a mut RHMap<K, V, H, A>
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// An iterator over the entries of a `RHMap`, created by `RHMap::iter`. Knows its exact length from the map's entry
+/// count, and stops scanning slots as soon as every entry has been yielded.
+pub struct Iter<'a, K: Hash + Eq, V> {
+    slots: Zip<slice::Iter<'a, u8>, slice::Iter<'a, MaybeUninit<Entry<K, V>>>>,
+    /// Number of entries not yielded yet.
+    remaining: usize,
+}
+
+impl<'a, K: Hash + Eq, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (_, slot) = self.slots.find(|(&ctrl, _)| ctrl != EMPTY)?;
+        self.remaining -= 1;
+        // SAFETY: the control byte says the slot is initialized.
+        let entry = unsafe { slot.assume_init_ref() };
+        Some((&entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: Hash + Eq, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (_, slot) = self.slots.rfind(|(&ctrl, _)| ctrl != EMPTY)?;
+        self.remaining -= 1;
+        // SAFETY: the control byte says the slot is initialized.
+        let entry = unsafe { slot.assume_init_ref() };
+        Some((&entry.key, &entry.value))
+    }
+}
+
+impl<K: Hash + Eq, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K: Hash + Eq, V> FusedIterator for Iter<'_, K, V> {}
+
+impl<K: Hash + Eq, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+/// An iterator over the entries of a `RHMap` with mutable references to the values, created by `RHMap::iter_mut`.
+pub struct IterMut<'a, K: Hash + Eq, V> {
+    slots: Zip<slice::Iter<'a, u8>, slice::IterMut<'a, MaybeUninit<Entry<K, V>>>>,
+    /// Number of entries not yielded yet.
+    remaining: usize,
+}
+
+impl<'a, K: Hash + Eq, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (_, slot) = self.slots.find(|(&ctrl, _)| ctrl != EMPTY)?;
+        self.remaining -= 1;
+        // SAFETY: the control byte says the slot is initialized.
+        let entry = unsafe { slot.assume_init_mut() };
+        Some((&entry.key, &mut entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: Hash + Eq, V> DoubleEndedIterator for IterMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (_, slot) = self.slots.rfind(|(&ctrl, _)| ctrl != EMPTY)?;
+        self.remaining -= 1;
+        // SAFETY: the control byte says the slot is initialized.
+        let entry = unsafe { slot.assume_init_mut() };
+        Some((&entry.key, &mut entry.value))
+    }
+}
+
+impl<K: Hash + Eq, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<K: Hash + Eq, V> FusedIterator for IterMut<'_, K, V> {}
+
+/// An iterator over the keys of a `RHMap`, created by `RHMap::keys`.
+pub struct Keys<'a, K: Hash + Eq, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K: Hash + Eq, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: Hash + Eq, V> DoubleEndedIterator for Keys<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<K: Hash + Eq, V> ExactSizeIterator for Keys<'_, K, V> {}
+
+impl<K: Hash + Eq, V> FusedIterator for Keys<'_, K, V> {}
+
+impl<K: Hash + Eq, V> Clone for Keys<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// An iterator over the values of a `RHMap`, created by `RHMap::values`.
+pub struct Values<'a, K: Hash + Eq, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K: Hash + Eq, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: Hash + Eq, V> DoubleEndedIterator for Values<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<K: Hash + Eq, V> ExactSizeIterator for Values<'_, K, V> {}
+
+impl<K: Hash + Eq, V> FusedIterator for Values<'_, K, V> {}
+
+impl<K: Hash + Eq, V> Clone for Values<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// An iterator over mutable references to the values of a `RHMap`, created by `RHMap::values_mut`.
+pub struct ValuesMut<'a, K: Hash + Eq, V> {
+    inner: IterMut<'a, K, V>,
+}
+
+impl<'a, K: Hash + Eq, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K: Hash + Eq, V> DoubleEndedIterator for ValuesMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<K: Hash + Eq, V> ExactSizeIterator for ValuesMut<'_, K, V> {}
+
+impl<K: Hash + Eq, V> FusedIterator for ValuesMut<'_, K, V> {}
+
+/// An owning iterator over the keys of a `RHMap`, created by `RHMap::into_keys`.
+pub struct IntoKeys<K, V> {
+    inner: vec::IntoIter<(K, V)>,
+}
+
+impl<K, V> Iterator for IntoKeys<K, V> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoKeys<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| k)
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoKeys<K, V> {}
+
+impl<K, V> FusedIterator for IntoKeys<K, V> {}
+
+/// An owning iterator over the values of a `RHMap`, created by `RHMap::into_values`.
+pub struct IntoValues<K, V> {
+    inner: vec::IntoIter<(K, V)>,
+}
+
+impl<K, V> Iterator for IntoValues<K, V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoValues<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoValues<K, V> {}
+
+impl<K, V> FusedIterator for IntoValues<K, V> {}
+
+/// A lazy draining iterator over the entries matching a predicate, created by `RHMap::extract_if`.
+pub struct ExtractIf<
+    'a,
+    K: Hash + Eq,
+    V,
+    H: BuildHasher + Clone,
+    F: FnMut(&K, &mut V) -> bool,
+    A: Allocator + Clone = Global,
+> {
+    map: &'a mut RHMap<K, V, H, A>,
+    /// The slot the scan started at.
+    start: usize,
+    /// How many slots past `start` the next slot to look at is.
+    index: usize,
+    pred: F,
+}
+
+impl<
+        K: Hash + Eq,
+        V,
+        H: BuildHasher + Clone,
+        F: FnMut(&K, &mut V) -> bool,
+        A: Allocator + Clone,
+    > Iterator for ExtractIf<'_, K, V, H, F, A>
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.map.inner.len() {
+            let slot = (self.start + self.index) & self.map.mask;
+            if let Some(entry) = self.map.entry_at_mut(slot) {
+                if (self.pred)(&entry.key, &mut entry.value) {
+                    // Backward shift deletion moves the rest of the bucket one slot to the left, so the slot now
+                    // holds an entry we haven't looked at yet and we mustn't advance.
+                    let entry = self.map.remove_at(slot);
+                    return Some((entry.key, entry.value));
+                }
+            }
+
+            self.index += 1;
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.map.len()))
+    }
+}
+
+/// Once the scan has passed every slot it stays there.
+impl<
+        K: Hash + Eq,
+        V,
+        H: BuildHasher + Clone,
+        F: FnMut(&K, &mut V) -> bool,
+        A: Allocator + Clone,
+    > FusedIterator for ExtractIf<'_, K, V, H, F, A>
+{
+}
+
+/// Where an entry was found, as reported by `RHMap::locate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryLocation {
+    /// Index of the slot holding the entry.
+    pub slot: usize,
+    /// How far the entry sits from its home slot.
+    pub psl: usize,
+    /// The `resize_epoch` at the time of the lookup.
+    pub epoch: usize,
+}
+
+/// Probe length statistics of a map, as reported by `RHMap::probe_stats`. All zero for an empty map.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProbeStats {
+    /// The average distance of an entry from its home slot.
+    pub mean: f64,
+    /// The variance of those distances. Robin Hood insertion exists to keep this low.
+    pub variance: f64,
+    /// The largest distance, same as `RHMap::max_probe_length`.
+    pub max: usize,
+}
+
+/// A cached slot position for a key, created by `RHMap::find_handle`. Goes stale as soon as any entry in the map
+/// may have moved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryHandle {
+    slot: usize,
+    /// The map's generation when the handle was created.
+    generation: usize,
+}
+
+/// The error returned by `compare_and_update` when the value wasn't replaced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CasError {
+    /// No entry exists for the key.
+    NotFound,
+    /// The current value didn't equal the expected one.
+    Mismatch,
+}
+
+impl Display for CasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CasError::NotFound => write!(f, "Entry not found"),
+            CasError::Mismatch => write!(f, "Current value does not match the expected value"),
+        }
+    }
+}
+
+impl Error for CasError {}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fx_build_hasher::FxBuildHasher;
+    use super::super::growth::Capped;
+    use super::*;
+    use std::{collections::hash_map::DefaultHasher, hash::BuildHasherDefault};
+
+    #[test]
+    fn it_constructs_with_an_initial_capacity() {
+        let initial_capacity = 5;
+        let hashmap: RHMap<&str, i32, FxBuildHasher> = RHMap::with_capacity(initial_capacity);
+
+        assert_eq!(hashmap.bucket_count(), 8);
+        assert_eq!(hashmap.capacity(), 6);
+    }
+
+    #[test]
+    fn it_inserts_values_without_initial_capacity() {
+        let mut hashmap = RHMap::new();
+
+        for x in 0..100 {
+            hashmap.insert(x, x + 1);
+        }
+
+        assert_eq!(hashmap.len(), 100);
+
+        for x in (0..100).rev() {
+            let val = hashmap.get(&x).unwrap();
+            assert_eq!(*val, x + 1);
+        }
+    }
+
+    /// Counts the live allocations made through it.
+    #[derive(Clone, Default)]
+    struct CountingAlloc(std::rc::Rc<std::cell::Cell<usize>>);
+
+    unsafe impl Allocator for CountingAlloc {
+        fn allocate(
+            &self,
+            layout: std::alloc::Layout,
+        ) -> Result<std::ptr::NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+            self.0.set(self.0.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: std::ptr::NonNull<u8>, layout: std::alloc::Layout) {
+            self.0.set(self.0.get() - 1);
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn it_allocates_slots_with_a_custom_allocator() {
+        let alloc = CountingAlloc::default();
+        let mut hashmap = RHMap::with_hasher_in(FxBuildHasher::new(), alloc.clone());
+        for x in 0..1000 {
+            hashmap.insert(x, x.to_string());
+        }
+        hashmap.retain(|k, _| k % 2 == 0);
+        let odd = hashmap.split_by(|k, _| k % 4 == 2);
+
+        // Slots and control bytes of both maps.
+        assert_eq!(alloc.0.get(), 4);
+        assert_eq!(hashmap.len(), 250);
+        assert_eq!(odd.get(&998), Some(&"998".to_string()));
+        drop(hashmap);
+        drop(odd);
+        assert_eq!(alloc.0.get(), 0);
+    }
+
+    #[test]
+    fn it_looks_up_batches() {
+        let mut hashmap = RHMap::new();
+        for x in 0..100 {
+            hashmap.insert(x.to_string(), x);
+        }
+
+        let keys: Vec<String> = (50..150).map(|x| x.to_string()).collect();
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let values = hashmap.get_batch(&keys);
+        assert_eq!(values.len(), 100);
+        assert!((0..50).all(|i| values[i] == Some(&(i + 50))));
+        assert!(values[50..].iter().all(Option::is_none));
+        assert!(RHMap::<String, i32, FxBuildHasher>::new()
+            .get_batch(&["a"])
+            .iter()
+            .all(Option::is_none));
+    }
+
+    #[test]
+    fn it_inserts_batches() {
+        let mut hashmap = RHMap::from([(1, 0), (2, 0)]);
+        hashmap.insert_batch((0..1000).map(|x| (x, x)).chain([(5, 50)]));
+        assert_eq!(hashmap.len(), 1000);
+        assert_eq!(hashmap.get(&1), Some(&1));
+        assert_eq!(hashmap.get(&5), Some(&50));
+        assert_eq!(hashmap.get(&999), Some(&999));
+
+        let hasher = hashmap.hasher_builder.clone();
+        hashmap.extend_with_hashes((1000..2000).map(|x| (hasher.hash_one(x), x, x)));
+        assert_eq!(hashmap.len(), 2000);
+        assert!((0..2000).all(|x| hashmap.contains_key(&x)));
+    }
+
+    #[test]
+    fn it_holds_its_capacity_without_resizing() {
+        for initial_capacity in [1, 3, 6, 7, 100, 1000] {
+            let mut hashmap = RHMap::with_capacity(initial_capacity);
+            let capacity = hashmap.capacity();
+            let buckets = hashmap.bucket_count();
+            assert!(capacity >= initial_capacity);
+
+            for x in 0..capacity {
+                hashmap.insert(x, x);
+            }
+            assert_eq!(hashmap.bucket_count(), buckets);
+            hashmap.insert(capacity, capacity);
+            assert_eq!(hashmap.bucket_count(), 2 * buckets);
+        }
+    }
+
+    #[test]
+    fn it_inserts_values_with_initial_capacity() {
+        let mut book_reviews = RHMap::with_capacity(10);
+        let key = "The Adventures of Sherlock Holmes".to_string();
+        let value = "Eye lyked it alot.".to_string();
+        book_reviews.insert(key, value);
+
+        assert_eq!(book_reviews.bucket_count(), 16);
+        assert_eq!(
+            *book_reviews
                 .get(&String::from("The Adventures of Sherlock Holmes"))
                 .unwrap(),
             String::from("Eye lyked it alot.")
         );
     }
 
+    #[test]
+    fn it_constructs_from_an_array() {
+        let hashmap = RHMap::from([(1, "a"), (2, "b"), (1, "c")]);
+
+        assert_eq!(hashmap.len(), 2);
+        assert_eq!(hashmap.get(&1), Some(&"c"));
+        assert_eq!(hashmap.get(&2), Some(&"b"));
+    }
+
+    #[test]
+    fn it_tries_to_insert_without_overwriting() {
+        let mut hashmap = RHMap::new();
+        *hashmap.try_insert(1, 10).unwrap() += 1;
+
+        let err = hashmap.try_insert(1, 20).unwrap_err();
+        assert_eq!(err.value, 20);
+        assert_eq!(err.entry.key(), &1);
+        assert_eq!(err.entry.get(), &11);
+
+        assert_eq!(hashmap.get(&1), Some(&11));
+        assert_eq!(hashmap.len(), 1);
+    }
+
+    #[test]
+    fn it_upserts_through_borrowed_keys() {
+        let mut symbols: RHMap<String, usize, FxBuildHasher> = RHMap::new();
+        for word in ["let", "x", "let", "y", "let"] {
+            *symbols.entry_ref(word).or_insert(0) += 1;
+        }
+
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols.get(&"let".to_string()), Some(&3));
+        assert_eq!(symbols.get(&"x".to_string()), Some(&1));
+
+        match symbols.entry_ref("z") {
+            EntryRef::Vacant(entry) => assert_eq!(entry.key(), "z"),
+            EntryRef::Occupied(_) => panic!("z was never inserted"),
+        }
+        assert_eq!(symbols.len(), 3);
+    }
+
+    #[test]
+    fn it_inserts_unique_keys_without_comparisons() {
+        let mut hashmap = RHMap::new();
+        for x in 0..100 {
+            hashmap.insert_unique_unchecked(x, x * 2);
+        }
+
+        assert_eq!(hashmap.len(), 100);
+        for x in 0..100 {
+            assert_eq!(hashmap.get(&x), Some(&(x * 2)));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn it_asserts_unique_keys_in_debug_builds() {
+        let mut hashmap = RHMap::new();
+        hashmap.insert_unique_unchecked(1, 1);
+        hashmap.insert_unique_unchecked(1, 2);
+    }
+
+    #[test]
+    fn it_grows_in_place() {
+        let mut map = RHMap::with_hasher(BuildHasherDefault::<ZeroHasher>::default());
+        let mut expected = std::collections::HashMap::new();
+        for x in 0..200u64 {
+            map.insert(x, x);
+            expected.insert(x, x);
+        }
+        // A different spread of homes, growing while the table holds one long cluster.
+        let mut map_spread = RHMap::new();
+        for x in 0..5000u64 {
+            map_spread.insert(x.wrapping_mul(0x9e37_79b9_7f4a_7c15), x);
+        }
+
+        fn check<H: BuildHasher + Clone>(map: &RHMap<u64, u64, H>) {
+            assert!(!map.ctrl.contains(&PENDING));
+            for i in 0..map.bucket_count() {
+                if let Some(entry) = map.entry_at(i) {
+                    // Robin Hood order: the next slot is either vacant, a new bucket, or at most one step further from
+                    // its home than this one.
+                    if let Some(next) = map.entry_at((i + 1) & map.mask) {
+                        let next_psl = psl_of((i + 1) & map.mask, next.hash, map.mask);
+                        assert!(next_psl <= psl_of(i, entry.hash, map.mask) + 1);
+                    }
+                    assert_eq!(map.get(&entry.key), Some(&entry.value));
+                }
+            }
+        }
+        check(&map);
+        check(&map_spread);
+        assert_eq!(map_spread.len(), 5000);
+        assert!(expected.iter().all(|(k, v)| map.get(k) == Some(v)));
+    }
+
+    #[test]
+    fn it_shrinks_below_the_threshold() {
+        let mut map = RHMap::new();
+        map.set_shrink_threshold(Some(0.125));
+        for x in 0..1000 {
+            map.insert(x, x);
+        }
+        assert_eq!(map.bucket_count(), 2048);
+
+        map.retain(|&k, _| k < 200);
+        assert_eq!(map.bucket_count(), 1024);
+        for x in 20..200 {
+            map.remove(&x).unwrap();
+        }
+        // The last shrink happened at 31 of 256 slots, leaving room for as many entries again.
+        assert_eq!(map.bucket_count(), 128);
+        assert!((0..20).all(|x| map.get(&x) == Some(&x)));
+
+        map.set_shrink_threshold(None);
+        map.shrink_to_fit();
+        assert_eq!(map.bucket_count(), 32);
+    }
+
+    #[test]
+    fn it_reserves_capacity_up_front() {
+        let mut hashmap = RHMap::new();
+        hashmap.reserve(100);
+        let capacity = hashmap.capacity();
+        assert!(capacity >= 100);
+
+        for x in 0..100 {
+            hashmap.insert(x, x);
+        }
+        assert_eq!(hashmap.capacity(), capacity);
+    }
+
+    #[test]
+    fn it_reserves_exact_capacity() {
+        let mut hashmap = RHMap::with_load_factor(0.5);
+        hashmap.insert(0, 0);
+        hashmap.reserve_exact(99);
+        // 100 entries at half load need 200 slots, rounded up to a power of two.
+        assert_eq!(hashmap.bucket_count(), 256);
+
+        let epoch = hashmap.resize_epoch();
+        for x in 1..100 {
+            hashmap.insert(x, x);
+        }
+        assert_eq!(hashmap.resize_epoch(), epoch);
+        hashmap.reserve_exact(0);
+        assert_eq!(hashmap.bucket_count(), 256);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't allow")]
+    fn it_panics_when_reserve_exact_exceeds_the_growth_policy() {
+        let mut hashmap: RHMap<i32, i32, FxBuildHasher> = RHMap::new();
+        hashmap.set_growth_policy(Capped {
+            policy: Doubling,
+            max: 64,
+        });
+        hashmap.reserve_exact(100);
+    }
+
+    #[test]
+    fn it_checks_invariants() {
+        let mut hashmap: RHMap<usize, usize, _> =
+            RHMap::with_hasher(BuildHasherDefault::<ZeroHasher>::default());
+        assert_eq!(hashmap.check_invariants(), Ok(()));
+        for x in 0..6 {
+            hashmap.insert(x, x);
+        }
+        assert!(hashmap.remove(&2).is_ok());
+        assert_eq!(hashmap.check_invariants(), Ok(()));
+
+        hashmap.num_items += 1;
+        assert!(hashmap.check_invariants().is_err());
+        hashmap.num_items -= 1;
+
+        // Moving the third entry's home one slot ahead leaves the fourth two slots further from home than it.
+        hashmap.entry_at_mut(2).unwrap().hash = 1;
+        assert_eq!(
+            hashmap.check_invariants(),
+            Err("Entries are out of Robin Hood order")
+        );
+        hashmap.entry_at_mut(2).unwrap().hash = 0;
+    }
+
+    #[test]
+    fn it_reports_probe_stats() {
+        let mut hashmap: RHMap<usize, usize, _> =
+            RHMap::with_hasher(BuildHasherDefault::<ZeroHasher>::default());
+        assert_eq!(hashmap.probe_stats(), ProbeStats::default());
+        assert!(hashmap.psl_histogram().is_empty());
+
+        // Every key collides, so the entries sit 0, 1, 2 and 3 slots from home.
+        for x in 0..4 {
+            hashmap.insert(x, x);
+        }
+        assert_eq!(hashmap.psl_histogram(), &[1, 1, 1, 1]);
+        assert_eq!(
+            hashmap.probe_stats(),
+            ProbeStats {
+                mean: 1.5,
+                variance: 1.25,
+                max: 3,
+            }
+        );
+
+        assert!(hashmap.remove(&3).is_ok());
+        assert_eq!(hashmap.psl_histogram(), &[1, 1, 1]);
+        assert_eq!(hashmap.probe_stats().max, 2);
+    }
+
+    #[test]
+    fn it_hashes_keys_like_the_map() {
+        let mut hashmap = RHMap::with_hasher(FxBuildHasher::with_seed(7));
+        hashmap.insert("shard me", 1);
+
+        let hash = hashmap.hash_key("shard me");
+        assert_eq!(hash, FxBuildHasher::with_seed(7).hash_one("shard me"));
+        assert_eq!(hashmap.get_with_hash(hash, |k| *k == "shard me"), Some(&1));
+    }
+
+    #[test]
+    fn it_reports_memory_usage() {
+        let mut hashmap: RHMap<u64, String, FxBuildHasher> = RHMap::new();
+        assert_eq!(hashmap.memory_usage(), 0);
+
+        for x in 0..100 {
+            hashmap.insert(x, x.to_string());
+        }
+        let slots = hashmap.bucket_count() * (mem::size_of::<Entry<u64, String>>() + 1);
+        assert!(hashmap.memory_usage() >= slots);
+
+        let strings: usize = hashmap.values().map(String::capacity).sum();
+        assert_eq!(
+            hashmap.deep_memory_usage(|_, v| v.capacity()),
+            hashmap.memory_usage() + strings
+        );
+    }
+
+    #[test]
+    fn it_plans_capacity_for_a_byte_budget() {
+        let budget = 64 * 1024;
+        let capacity = capacity_for_bytes::<u64, u64>(budget);
+        let bytes = estimated_bytes_for_capacity::<u64, u64>(capacity);
+
+        assert!(bytes <= budget);
+        assert!(estimated_bytes_for_capacity::<u64, u64>(capacity + 1) > budget);
+        assert_eq!(capacity_for_bytes::<u64, u64>(0), 0);
+
+        let mut hashmap: RHMap<u64, u64, FxBuildHasher> = RHMap::new();
+        hashmap.reserve(capacity);
+        assert_eq!(
+            hashmap.bucket_count() * (mem::size_of::<Entry<u64, u64>>() + 1),
+            bytes
+        );
+    }
+
+    #[test]
+    fn it_swaps_entries_between_maps() {
+        let mut front = RHMap::from([(1, "a"), (2, "b"), (3, "c")]);
+        // Shares the hasher of `front`, which with `random-seed` another `RHMap::from` wouldn't.
+        let mut back = RHMap::with_hasher(front.hasher().clone());
+        back.insert_batch(vec![(2, "B"), (3, "C"), (4, "D")]);
+
+        front.swap_entries(&mut back, vec![1, 2, 4, 5]);
+
+        assert_eq!(front.get(&1), None);
+        assert_eq!(back.get(&1), Some(&"a"));
+        assert_eq!(front.get(&2), Some(&"B"));
+        assert_eq!(back.get(&2), Some(&"b"));
+        assert_eq!(front.get(&3), Some(&"c"));
+        assert_eq!(back.get(&3), Some(&"C"));
+        assert_eq!(front.get(&4), Some(&"D"));
+        assert_eq!(back.get(&4), None);
+        assert_eq!((front.len(), back.len()), (3, 3));
+    }
+
+    #[test]
+    fn it_appends_another_map() {
+        let mut a = RHMap::from([(1, "a"), (2, "b")]);
+        let mut b = RHMap::with_capacity(8);
+        b.insert(2, "c");
+        b.insert(3, "d");
+
+        a.append(&mut b);
+
+        assert_eq!(a.len(), 3);
+        assert_eq!(a.get(&1), Some(&"a"));
+        assert_eq!(a.get(&2), Some(&"c"));
+        assert_eq!(a.get(&3), Some(&"d"));
+        assert!(b.is_empty());
+        assert_eq!(b.capacity(), 12);
+    }
+
+    #[test]
+    fn it_compares_and_updates_values() {
+        let mut hashmap = RHMap::from([("counter", 1)]);
+
+        assert_eq!(hashmap.compare_and_update(&"counter", &1, 2), Ok(()));
+        assert_eq!(
+            hashmap.compare_and_update(&"counter", &1, 3),
+            Err(CasError::Mismatch)
+        );
+        assert_eq!(
+            hashmap.compare_and_update(&"missing", &1, 3),
+            Err(CasError::NotFound)
+        );
+        assert_eq!(hashmap.get(&"counter"), Some(&2));
+    }
+
+    #[test]
+    fn it_locates_entries_within_a_resize_epoch() {
+        let mut hashmap = RHMap::new();
+        hashmap.insert(1, 1);
+        let location = hashmap.locate(&1).unwrap();
+
+        assert_eq!(location.epoch, hashmap.resize_epoch());
+        assert_eq!(hashmap.entry_at(location.slot).unwrap().key, 1);
+        assert_eq!(hashmap.locate(&2), None);
+
+        for x in 2..10 {
+            hashmap.insert(x, x);
+        }
+        assert!(hashmap.resize_epoch() > location.epoch);
+        assert_eq!(hashmap.locate(&1).unwrap().epoch, hashmap.resize_epoch());
+    }
+
+    #[test]
+    fn it_gets_or_inserts_lazily() {
+        let mut hashmap = RHMap::new();
+        let mut calls = 0;
+
+        for _ in 0..3 {
+            *hashmap.get_or_insert_with(7, || {
+                calls += 1;
+                0
+            }) += 1;
+        }
+
+        assert_eq!(calls, 1);
+        assert_eq!(hashmap.get(&7), Some(&3));
+        assert_eq!(hashmap.len(), 1);
+    }
+
+    #[test]
+    fn it_merges_with_a_conflict_resolver() {
+        let mut counts = RHMap::from([(1, 2), (2, 1)]);
+        let more = RHMap::from([(2, 3), (3, 1)]);
+
+        counts.merge(more, |_, a, b| a + b);
+
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts.get(&1), Some(&2));
+        assert_eq!(counts.get(&2), Some(&4));
+        assert_eq!(counts.get(&3), Some(&1));
+    }
+
     #[test]
     fn it_checks_if_entry_exists() {
         let mut hashmap = RHMap::new();
         hashmap.insert(1, 2);
 
-        assert_eq!(hashmap.contains_key(&1), true);
-        assert_eq!(hashmap.contains_key(&2), false);
+        assert!(hashmap.contains_key(&1));
+        assert!(!hashmap.contains_key(&2));
     }
 
     #[test]
@@ -414,9 +2746,9 @@ mod tests {
         hashmap.insert(42, 1);
         hashmap.clear();
 
-        assert_eq!(hashmap.capacity(), 70);
+        assert_eq!(hashmap.bucket_count(), 128);
         assert_eq!(hashmap.len(), 0);
-        assert_eq!(hashmap.contains_key(&42), false);
+        assert!(!hashmap.contains_key(&42));
     }
 
     #[test]
@@ -438,7 +2770,203 @@ mod tests {
         assert!(!hashmap.contains_key(&1));
         assert!(!hashmap.contains_key(&7));
         assert!(!hashmap.contains_key(&3));
-        assert_eq!(hashmap.len(), 4)
+        assert_eq!(hashmap.len(), 1)
+    }
+
+    /// Sends every key to slot 0 so each insert lands at the end of one long cluster.
+    #[derive(Default)]
+    struct ZeroHasher;
+
+    impl std::hash::Hasher for ZeroHasher {
+        fn finish(&self) -> u64 {
+            0
+        }
+
+        fn write(&mut self, _bytes: &[u8]) {}
+    }
+
+    thread_local! {
+        static KEY_COMPARISONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    /// A key that counts how often it's compared.
+    #[derive(Eq)]
+    struct CountedKey(u64);
+
+    impl Hash for CountedKey {
+        fn hash<S: std::hash::Hasher>(&self, state: &mut S) {
+            state.write_u64(self.0);
+        }
+    }
+
+    impl PartialEq for CountedKey {
+        fn eq(&self, other: &Self) -> bool {
+            KEY_COMPARISONS.with(|count| count.set(count.get() + 1));
+            self.0 == other.0
+        }
+    }
+
+    /// Hashes into the middle bits only, so every key shares a home slot and a control byte fragment.
+    #[derive(Default)]
+    struct MiddleBitsHasher(u64);
+
+    impl std::hash::Hasher for MiddleBitsHasher {
+        fn finish(&self) -> u64 {
+            self.0 << 20
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = self.0 << 8 | u64::from(byte);
+            }
+        }
+
+        fn write_u64(&mut self, x: u64) {
+            self.0 = x;
+        }
+    }
+
+    #[test]
+    fn it_compares_full_hashes_before_keys() {
+        let mut hashmap = RHMap::with_hasher(BuildHasherDefault::<MiddleBitsHasher>::default());
+        for x in 0..100 {
+            hashmap.insert(CountedKey(x), x);
+        }
+
+        KEY_COMPARISONS.with(|count| count.set(0));
+        for x in 0..100 {
+            assert_eq!(hashmap.get(&CountedKey(x)), Some(&x));
+        }
+        assert_eq!(hashmap.get(&CountedKey(100)), None);
+        assert_eq!(KEY_COMPARISONS.with(|count| count.get()), 100);
+    }
+
+    #[test]
+    fn it_tracks_the_max_probe_length() {
+        let mut hashmap = RHMap::with_hasher(BuildHasherDefault::<ZeroHasher>::default());
+        for x in 0..5 {
+            hashmap.insert(x, x);
+        }
+
+        assert_eq!(hashmap.max_probe_length(), 4);
+        for x in 0..5 {
+            assert_eq!(hashmap.get(&x), Some(&x));
+        }
+        assert_eq!(hashmap.probe(&5).1, 5);
+
+        // The rest of the cluster shifts back one slot, so the longest probe shrinks with it.
+        assert!(hashmap.remove(&0).is_ok());
+        assert!(hashmap.remove(&3).is_ok());
+        assert_eq!(hashmap.max_probe_length(), 2);
+        assert_eq!(hashmap.get(&4), Some(&4));
+        assert_eq!(hashmap.get(&3), None);
+
+        hashmap.clear();
+        assert_eq!(hashmap.max_probe_length(), 0);
+    }
+
+    #[test]
+    fn it_inserts_into_one_long_cluster() {
+        // Every insert walks (and displaces along) the whole cluster, which must not grow the stack.
+        let mut hashmap = RHMap::with_hasher(BuildHasherDefault::<ZeroHasher>::default());
+        for x in 0..2000 {
+            hashmap.insert(x, x);
+        }
+
+        assert_eq!(hashmap.len(), 2000);
+        assert_eq!(hashmap.max_probe_length(), 1999);
+        assert_eq!(hashmap.get(&1999), Some(&1999));
+    }
+
+    #[test]
+    fn it_keeps_control_bytes_in_sync_with_slots() {
+        let mut hashmap = RHMap::new();
+        for x in 0..200 {
+            hashmap.insert(x.to_string(), x);
+        }
+        for x in (0..200).step_by(2) {
+            hashmap.remove(&x.to_string()).unwrap();
+        }
+        hashmap.retain(|_, v| *v % 5 != 0);
+
+        assert_eq!(hashmap.ctrl.len(), hashmap.bucket_count());
+        let occupied = hashmap.ctrl.iter().filter(|&&ctrl| ctrl != EMPTY).count();
+        assert_eq!(occupied, hashmap.len());
+        for (i, &ctrl) in hashmap.ctrl.iter().enumerate() {
+            if let Some(entry) = hashmap.entry_at(i) {
+                assert_eq!(ctrl, h2(entry.hash));
+            }
+        }
+
+        hashmap.clear();
+        assert!(hashmap.ctrl.iter().all(|&ctrl| ctrl == EMPTY));
+    }
+
+    #[test]
+    fn it_drops_every_entry_exactly_once() {
+        let tracker = std::rc::Rc::new(());
+        let mut hashmap = RHMap::new();
+        for x in 0..100 {
+            hashmap.insert(x, std::rc::Rc::clone(&tracker));
+        }
+        // Overwrites, removes, resizes and clears all hand entries over or drop them.
+        hashmap.insert(0, std::rc::Rc::clone(&tracker));
+        for x in 0..50 {
+            hashmap.remove(&x).unwrap();
+        }
+        hashmap.reserve(500);
+        assert_eq!(std::rc::Rc::strong_count(&tracker), 51);
+
+        let kept = hashmap.remove_entry(&99).unwrap();
+        drop(hashmap);
+        assert_eq!(std::rc::Rc::strong_count(&tracker), 2);
+        drop(kept);
+        assert_eq!(std::rc::Rc::strong_count(&tracker), 1);
+    }
+
+    #[test]
+    fn it_rounds_capacities_up_to_powers_of_two() {
+        assert_eq!(
+            RHMap::<i32, i32, FxBuildHasher>::with_capacity(0).capacity(),
+            0
+        );
+        assert_eq!(
+            RHMap::<i32, i32, FxBuildHasher>::with_capacity(8).bucket_count(),
+            16
+        );
+
+        let mut hashmap = RHMap::with_capacity(5);
+        for x in 0..100 {
+            hashmap.insert(x, x);
+        }
+        assert_eq!(hashmap.bucket_count(), 256);
+        assert_eq!(hashmap.get(&42), Some(&42));
+    }
+
+    #[test]
+    fn it_wraps_buckets_around_the_end_of_the_table() {
+        let mut hashmap = RHMap::new();
+        let mut expected = std::collections::HashMap::new();
+        for x in 0..500 {
+            hashmap.insert(x.to_string(), x);
+            expected.insert(x.to_string(), x);
+            // Probing must never grow the table past its bucket count.
+            assert!(hashmap.bucket_count().is_power_of_two());
+        }
+
+        for x in (0..500).step_by(3) {
+            assert!(hashmap.remove(&x.to_string()).is_ok());
+            expected.remove(&x.to_string());
+        }
+        let extracted: Vec<_> = hashmap.extract_if(|_, v| *v % 3 == 1).collect();
+        expected.retain(|_, v| *v % 3 != 1);
+
+        assert_eq!(extracted.len(), 167);
+        assert_eq!(hashmap.len(), expected.len());
+        for (k, v) in &expected {
+            assert_eq!(hashmap.get(k), Some(v));
+        }
+        assert!(hashmap.get(&"0".to_string()).is_none());
     }
 
     #[test]
@@ -450,7 +2978,33 @@ mod tests {
         hashmap.remove(&1);
         assert!(!hashmap.contains_key(&1));
         assert_eq!(hashmap.len(), 0);
-        assert_eq!(hashmap.capacity(), 1);
+        assert_eq!(hashmap.capacity(), 3);
+    }
+
+    #[test]
+    fn it_formats_like_std_maps() {
+        let mut hashmap = RHMap::with_capacity(8);
+        assert_eq!(format!("{:?}", hashmap), "{}");
+
+        hashmap.insert("a", 1);
+        assert_eq!(format!("{:?}", hashmap), r#"{"a": 1}"#);
+    }
+
+    #[test]
+    fn it_rehashes_with_a_new_hasher() {
+        let mut hashmap = RHMap::new();
+        for x in 0..3 {
+            hashmap.insert(x, x + 1);
+        }
+
+        let rehashed = hashmap.rehash_with(BuildHasherDefault::<DefaultHasher>::default());
+
+        assert!(hashmap.is_empty());
+        assert_eq!(rehashed.len(), 3);
+        assert_eq!(rehashed.bucket_count(), 4);
+        for x in 0..3 {
+            assert_eq!(rehashed.get(&x), Some(&(x + 1)));
+        }
     }
 
     #[test]
@@ -467,6 +3021,206 @@ mod tests {
         }
     }
 
+    #[test]
+    fn it_reports_exact_iterator_lengths() {
+        let mut hashmap = RHMap::new();
+        for x in 0..10 {
+            hashmap.insert(x, x);
+        }
+
+        let mut iter = hashmap.iter();
+        assert_eq!(iter.len(), 10);
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.size_hint(), (8, Some(8)));
+        assert_eq!(iter.clone().count(), 8);
+        assert_eq!(iter.by_ref().count(), 8);
+        assert_eq!((iter.next(), iter.next_back()), (None, None));
+
+        let mut forward: Vec<i32> = hashmap.keys().copied().collect();
+        let mut backward: Vec<i32> = hashmap.keys().rev().copied().collect();
+        backward.reverse();
+        assert_eq!(forward, backward);
+        forward.sort_unstable();
+        assert_eq!(forward, (0..10).collect::<Vec<_>>());
+        assert_eq!(hashmap.values_mut().len(), 10);
+        assert_eq!(hashmap.into_values().rev().len(), 10);
+    }
+
+    #[test]
+    fn it_extracts_entries_matching_a_predicate() {
+        let mut hashmap = RHMap::new();
+        for x in 0..32 {
+            hashmap.insert(x, x);
+        }
+
+        let mut evens: Vec<(i32, i32)> = hashmap.extract_if(|k, _| k % 2 == 0).collect();
+        evens.sort_unstable();
+
+        assert_eq!(
+            evens,
+            (0..32).step_by(2).map(|x| (x, x)).collect::<Vec<_>>()
+        );
+        assert_eq!(hashmap.len(), 16);
+        for x in 0..32 {
+            assert_eq!(hashmap.contains_key(&x), x % 2 == 1);
+        }
+    }
+
+    #[test]
+    fn it_keeps_unvisited_entries_when_extract_if_is_dropped() {
+        let mut hashmap = RHMap::new();
+        for x in 0..8 {
+            hashmap.insert(x, x);
+        }
+
+        assert!(hashmap.extract_if(|_, _| true).next().is_some());
+        assert_eq!(hashmap.len(), 7);
+    }
+
+    #[test]
+    fn it_drains_entries_in_key_order() {
+        let mut hashmap = RHMap::with_capacity(16);
+        for x in [5, 3, 9, 1, 7] {
+            hashmap.insert(x, x * 10);
+        }
+
+        let drained: Vec<(i32, i32)> = hashmap.drain_sorted().collect();
+
+        assert_eq!(drained, vec![(1, 10), (3, 30), (5, 50), (7, 70), (9, 90)]);
+        assert!(hashmap.is_empty());
+        assert_eq!(hashmap.capacity(), 24);
+        assert!(!hashmap.contains_key(&5));
+    }
+
+    #[test]
+    fn it_partitions_entries_by_predicate() {
+        let mut hashmap = RHMap::new();
+        for x in 0..20 {
+            hashmap.insert(x, x);
+        }
+
+        let (expired, live) = hashmap.partition(|k, _| *k < 5);
+
+        assert_eq!(expired.len(), 5);
+        assert_eq!(live.len(), 15);
+        for x in 0..20 {
+            assert_eq!(expired.contains_key(&x), x < 5);
+            assert_eq!(live.contains_key(&x), x >= 5);
+        }
+    }
+
+    #[test]
+    fn it_splits_off_matching_entries() {
+        let mut hashmap = RHMap::from([(1, "a"), (2, "b"), (3, "c")]);
+
+        let odd = hashmap.split_by(|k, _| k % 2 == 1);
+
+        assert_eq!(odd.len(), 2);
+        assert_eq!(odd.get(&3), Some(&"c"));
+        assert_eq!(hashmap.len(), 1);
+        assert_eq!(hashmap.get(&2), Some(&"b"));
+    }
+
+    #[test]
+    fn it_splits_into_hash_prefix_shards_and_appends_them_back() {
+        let mut hashmap = RHMap::new();
+        for x in 0..1000 {
+            hashmap.insert(x, x);
+        }
+
+        let sizes: Vec<usize> = hashmap
+            .iter_shards(4)
+            .iter()
+            .map(|shard| shard.len())
+            .collect();
+        let shards = hashmap.split_into(4);
+        assert_eq!(shards.iter().map(RHMap::len).collect::<Vec<_>>(), sizes);
+        assert!(sizes.iter().all(|&size| size > 150));
+
+        let mut merged = RHMap::new();
+        for mut shard in shards {
+            merged.append(&mut shard);
+        }
+        assert_eq!(merged.len(), 1000);
+        assert!((0..1000).all(|x| merged.get(&x) == Some(&x)));
+    }
+
+    #[test]
+    fn it_visits_every_entry_with_for_each() {
+        let mut hashmap = RHMap::new();
+        for x in 0..50 {
+            hashmap.insert(x, x);
+        }
+
+        hashmap.for_each_mut(|k, v| *v += k);
+
+        let mut sum = 0;
+        hashmap.for_each(|k, v| {
+            assert_eq!(*v, 2 * k);
+            sum += v;
+        });
+        assert_eq!(sum, 2 * (0..50).sum::<i32>());
+    }
+
+    #[test]
+    fn it_consumes_into_keys_and_values() {
+        let hashmap = RHMap::from([(1, "a"), (2, "b"), (3, "c")]);
+        let mut keys: Vec<i32> = hashmap.into_keys().collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec![1, 2, 3]);
+
+        let hashmap = RHMap::from([(1, "a"), (2, "b"), (3, "c")]);
+        let mut values: Vec<&str> = hashmap.into_values().collect();
+        values.sort_unstable();
+        assert_eq!(values, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn it_reaches_entries_through_handles() {
+        let mut hashmap = RHMap::new();
+        for x in 0..10 {
+            hashmap.insert(x, x);
+        }
+
+        let handle = hashmap.find_handle(&7).unwrap();
+        for _ in 0..3 {
+            *hashmap.get_mut_by_handle(handle).unwrap() += 1;
+        }
+        hashmap.insert(7, 20);
+        assert_eq!(hashmap.get_by_handle(handle), Some(&20));
+        assert!(hashmap.find_handle(&100).is_none());
+
+        // A new key may move entries around so the handle goes stale.
+        hashmap.insert(10, 10);
+        assert_eq!(hashmap.get_by_handle(handle), None);
+        let handle = hashmap.find_handle(&7).unwrap();
+        assert_eq!(hashmap.get_by_handle(handle), Some(&20));
+
+        hashmap.remove(&0).unwrap();
+        assert_eq!(hashmap.get_mut_by_handle(handle), None);
+    }
+
+    #[test]
+    fn it_consumes_into_presized_vecs() {
+        let mut hashmap = RHMap::new();
+        for x in 0..10 {
+            hashmap.insert(x, x * 2);
+        }
+        let mut keys = hashmap.into_keys_vec();
+        assert_eq!(keys.capacity(), 10);
+        keys.sort_unstable();
+        assert_eq!(keys, (0..10).collect::<Vec<_>>());
+
+        let mut hashmap = RHMap::new();
+        for x in 0..10 {
+            hashmap.insert(x, x * 2);
+        }
+        let mut values = hashmap.into_values_vec();
+        values.sort_unstable();
+        assert_eq!(values, (0..10).map(|x| x * 2).collect::<Vec<_>>());
+    }
+
     #[test]
     fn it_iterates_and_mutates_over_entries() {
         let mut hashmap = RHMap::new();
@@ -0,0 +1,424 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::{Keys, RHMap};
+use super::raw_entry::RawEntryMut;
+use alloc::vec;
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::{FromIterator, FusedIterator},
+    ops::{BitAnd, BitOr, BitXor, Sub},
+};
+
+/// A `RHSet` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxHashSet<T> = RHSet<T, FxBuildHasher>;
+
+/// Robinhood HashSet, a thin wrapper over a `RHMap` whose values are `()`. Shares the map's table, so it probes,
+/// grows and iterates exactly like the map does.
+pub struct RHSet<T: Hash + Eq, H: BuildHasher + Clone> {
+    pub(crate) map: RHMap<T, (), H>,
+}
+
+impl<T: Hash + Eq> RHSet<T, FxBuildHasher> {
+    /// Creates a `RHSet` with the default Fx Hasher and an initial capacity of 0.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+
+    /// Constructs a `RHSet` that can hold at least `initial_capacity` values without resizing.
+    pub fn with_capacity(initial_capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(initial_capacity, FxBuildHasher::new())
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone + Default> Default for RHSet<T, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone> RHSet<T, H> {
+    /// Creates a `RHSet` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            map: RHMap::with_hasher(hasher_builder),
+        }
+    }
+
+    /// Creates a `RHSet` with both an initial capacity (see `with_capacity`) and a custom hasher.
+    pub fn with_capacity_and_hasher(initial_capacity: usize, hasher_builder: H) -> Self {
+        Self {
+            map: RHMap::with_capacity_and_hasher(initial_capacity, hasher_builder),
+        }
+    }
+
+    /// Adds a value to the set. Returns `true` if it wasn't already present, leaving the set untouched otherwise.
+    pub fn insert(&mut self, value: T) -> bool {
+        match self.map.raw_entry_mut().from_key(&value) {
+            RawEntryMut::Occupied(_) => false,
+            RawEntryMut::Vacant(entry) => {
+                entry.insert(value, ());
+                true
+            }
+        }
+    }
+
+    /// Removes a value from the set. Returns `true` if it was present.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<T>>(&mut self, value: &Q) -> bool {
+        self.map.remove(value).is_ok()
+    }
+
+    /// Removes and returns the value in the set equal to the given one, if any.
+    pub fn take<Q: ?Sized + Hash + Equivalent<T>>(&mut self, value: &Q) -> Option<T> {
+        self.map.remove_entry(value).map(|(value, _)| value)
+    }
+
+    /// Checks to see if the set contains the given value, which may be any borrowed form of the set's value type.
+    pub fn contains<Q: ?Sized + Hash + Equivalent<T>>(&self, value: &Q) -> bool {
+        self.map.contains_key(value)
+    }
+
+    /// Gets a reference to the value in the set equal to the given one, if any.
+    pub fn get<Q: ?Sized + Hash + Equivalent<T>>(&self, value: &Q) -> Option<&T> {
+        self.map.get_key_value(value).map(|(value, _)| value)
+    }
+
+    /// Retains only the values for which `f` returns `true`.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        self.map.retain(|value, _| f(value));
+    }
+
+    /// Clears all values but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Removes every value from the set, handing them back in arbitrary order.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> {
+        self.map.drain().map(|(value, _)| value)
+    }
+
+    /// Reserves capacity for at least `additional` more values, so that many inserts won't trigger a resize.
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional);
+    }
+
+    /// Shrinks the table as much as possible while still holding every value.
+    pub fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit();
+    }
+
+    /// Gets the number of values in the set.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the set contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Gets the number of values the set can hold without resizing.
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Gets the hasher builder the set hashes its values with.
+    pub fn hasher(&self) -> &H {
+        self.map.hasher()
+    }
+
+    /// Iterates over every value in arbitrary order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.map.keys(),
+        }
+    }
+
+    /// Iterates over the values in `self` or `other`, each only once.
+    pub fn union<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> + 'a {
+        // Walk the larger set whole and only the extra values of the smaller one.
+        let (large, small) = if self.len() >= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        large.iter().chain(small.difference(large))
+    }
+
+    /// Iterates over the values in both `self` and `other`.
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> + 'a {
+        // Probing the larger set for every value of the smaller one does the fewest lookups.
+        let (large, small) = if self.len() >= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        small.iter().filter(move |value| large.contains(*value))
+    }
+
+    /// Iterates over the values in `self` but not in `other`.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> + 'a {
+        self.iter().filter(move |value| !other.contains(*value))
+    }
+
+    /// Iterates over the values in exactly one of `self` and `other`.
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> + 'a {
+        self.difference(other).chain(other.difference(self))
+    }
+
+    /// Returns `true` if every value of `self` is also in `other`.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.iter().all(|value| other.contains(value))
+    }
+
+    /// Returns `true` if every value of `other` is also in `self`.
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns `true` if `self` and `other` have no values in common.
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+}
+
+impl<T: Hash + Eq + Clone, H: BuildHasher + Clone> Clone for RHSet<T, H> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+        }
+    }
+}
+
+/// Sets are equal when they hold the same values, whatever order their tables keep them in.
+impl<T: Hash + Eq, H: BuildHasher + Clone> PartialEq for RHSet<T, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone> Eq for RHSet<T, H> {}
+
+/// Formats the set like the std sets do (`{value, ...}`).
+impl<T: Hash + Eq + Debug, H: BuildHasher + Clone> Debug for RHSet<T, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone> Extend<T> for RHSet<T, H> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, values: I) {
+        for value in values {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a, T: Hash + Eq + Copy + 'a, H: BuildHasher + Clone> Extend<&'a T> for RHSet<T, H> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, values: I) {
+        self.extend(values.into_iter().copied());
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone + Default> FromIterator<T> for RHSet<T, H> {
+    fn from_iter<I: IntoIterator<Item = T>>(values: I) -> Self {
+        let mut set = Self::default();
+        set.extend(values);
+        set
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone> RHSet<T, H> {
+    /// Collects cloned values into a new set hashed like this one, for the set operators.
+    fn cloned_from<'a, I: Iterator<Item = &'a T>>(&self, values: I) -> Self
+    where
+        T: Clone + 'a,
+    {
+        let mut set = Self::with_hasher(self.hasher().clone());
+        set.extend(values.cloned());
+        set
+    }
+}
+
+/// `&a | &b` is a new set of the values in `a` or `b`.
+impl<T: Hash + Eq + Clone, H: BuildHasher + Clone> BitOr<&RHSet<T, H>> for &RHSet<T, H> {
+    type Output = RHSet<T, H>;
+
+    fn bitor(self, other: &RHSet<T, H>) -> RHSet<T, H> {
+        self.cloned_from(self.union(other))
+    }
+}
+
+/// `&a & &b` is a new set of the values in both `a` and `b`.
+impl<T: Hash + Eq + Clone, H: BuildHasher + Clone> BitAnd<&RHSet<T, H>> for &RHSet<T, H> {
+    type Output = RHSet<T, H>;
+
+    fn bitand(self, other: &RHSet<T, H>) -> RHSet<T, H> {
+        self.cloned_from(self.intersection(other))
+    }
+}
+
+/// `&a ^ &b` is a new set of the values in exactly one of `a` and `b`.
+impl<T: Hash + Eq + Clone, H: BuildHasher + Clone> BitXor<&RHSet<T, H>> for &RHSet<T, H> {
+    type Output = RHSet<T, H>;
+
+    fn bitxor(self, other: &RHSet<T, H>) -> RHSet<T, H> {
+        self.cloned_from(self.symmetric_difference(other))
+    }
+}
+
+/// `&a - &b` is a new set of the values in `a` but not in `b`.
+impl<T: Hash + Eq + Clone, H: BuildHasher + Clone> Sub<&RHSet<T, H>> for &RHSet<T, H> {
+    type Output = RHSet<T, H>;
+
+    fn sub(self, other: &RHSet<T, H>) -> RHSet<T, H> {
+        self.cloned_from(self.difference(other))
+    }
+}
+
+impl<T: Hash + Eq, const N: usize> From<[T; N]> for RHSet<T, FxBuildHasher> {
+    /// Builds a `RHSet` from an array of values, e.g. `RHSet::from([1, 2, 3])`. Duplicates are only kept once.
+    fn from(values: [T; N]) -> Self {
+        let mut set = Self::with_capacity(N);
+        set.extend(values);
+        set
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone> IntoIterator for RHSet<T, H> {
+    type Item = T;
+    type IntoIter = vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_keys_vec().into_iter()
+    }
+}
+
+impl<'a, T: Hash + Eq, H: BuildHasher + Clone> IntoIterator for &'a RHSet<T, H> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the values of a `RHSet`, created by `RHSet::iter`.
+pub struct Iter<'a, T: Hash + Eq> {
+    inner: Keys<'a, T, ()>,
+}
+
+impl<'a, T: Hash + Eq> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T: Hash + Eq> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<T: Hash + Eq> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T: Hash + Eq> FusedIterator for Iter<'_, T> {}
+
+impl<T: Hash + Eq> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_inserts_and_removes_values() {
+        let mut set = FxHashSet::new();
+        assert!(set.insert("a".to_string()));
+        assert!(set.insert("b".to_string()));
+        assert!(!set.insert("a".to_string()));
+
+        assert_eq!(set.len(), 2);
+        assert!(set.contains("a"));
+        assert!(set.remove("a"));
+        assert!(!set.remove("a"));
+        assert!(!set.contains("a"));
+        assert_eq!(set.take("b"), Some("b".to_string()));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn it_collects_and_extends() {
+        let mut set: FxHashSet<u32> = (0..100).chain(50..150).collect();
+        set.extend(&[150, 151]);
+
+        assert_eq!(set.len(), 152);
+        let mut values: Vec<_> = set.into_iter().collect();
+        values.sort_unstable();
+        assert_eq!(values, (0..152).collect::<Vec<_>>());
+        assert_eq!(RHSet::from([1, 1, 2]).len(), 2);
+    }
+
+    #[test]
+    fn it_combines_sets() {
+        let a = RHSet::from([1, 2, 3, 4]);
+        let b = RHSet::from([3, 4, 5]);
+        let sorted = |values: &mut dyn Iterator<Item = &i32>| {
+            let mut values: Vec<i32> = values.copied().collect();
+            values.sort_unstable();
+            values
+        };
+
+        assert_eq!(sorted(&mut a.union(&b)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(sorted(&mut b.union(&a)), vec![1, 2, 3, 4, 5]);
+        assert_eq!(sorted(&mut a.intersection(&b)), vec![3, 4]);
+        assert_eq!(sorted(&mut a.difference(&b)), vec![1, 2]);
+        assert_eq!(sorted(&mut a.symmetric_difference(&b)), vec![1, 2, 5]);
+
+        assert!(RHSet::from([3, 4]).is_subset(&a));
+        assert!(a.is_superset(&RHSet::from([3, 4])));
+        assert!(!a.is_subset(&b));
+        assert!(a.is_disjoint(&RHSet::from([7, 8])));
+        assert!(!a.is_disjoint(&b));
+    }
+
+    #[test]
+    fn it_iterates_with_an_exact_length() {
+        let set = RHSet::from([1, 2, 3]);
+        let mut iter = set.iter();
+        iter.next();
+
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.clone().rev().count(), 2);
+        assert_eq!((&set).into_iter().len(), 3);
+    }
+
+    #[test]
+    fn it_combines_sets_with_operators() {
+        let a = RHSet::from([1, 2, 3, 4]);
+        let b = RHSet::from([3, 4, 5]);
+        let sorted = |set: FxHashSet<i32>| {
+            let mut values: Vec<i32> = set.into_iter().collect();
+            values.sort_unstable();
+            values
+        };
+
+        assert_eq!(sorted(&a | &b), vec![1, 2, 3, 4, 5]);
+        assert_eq!(sorted(&a & &b), vec![3, 4]);
+        assert_eq!(sorted(&a ^ &b), vec![1, 2, 5]);
+        assert_eq!(sorted(&a - &b), vec![1, 2]);
+        assert_eq!(sorted(&(&a - &b) | &(&b - &a)), sorted(&a ^ &b));
+    }
+}
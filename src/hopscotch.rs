@@ -0,0 +1,314 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::map_entry::Entry;
+use super::traits::{MapRead, MapWrite};
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    mem,
+};
+
+/// How many slots, starting at its home slot, an entry may sit in. One bit per slot in a `u32` bitmap.
+const NEIGHBORHOOD: usize = 32;
+/// The table never has fewer slots than a neighborhood, so the slots of a neighborhood are all distinct.
+const INITIAL_CAPACITY: usize = NEIGHBORHOOD;
+/// The table grows once this fraction of its slots are occupied.
+const MAX_LOAD: f64 = 0.85;
+
+/// A `HopscotchMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxHopscotchMap<K, V> = HopscotchMap<K, V, FxBuildHasher>;
+
+/// A hopscotch hash map: every entry sits within `NEIGHBORHOOD` slots of its home slot, and each home slot keeps a
+/// bitmap of which of those slots hold its entries. A lookup reads one bitmap and then only the slots it points to,
+/// all close together in memory; inserts move entries around to bring a free slot into the neighborhood.
+///
+/// Since everything an operation touches lies in one neighborhood, a concurrent version only has to lock (or
+/// version) neighborhoods rather than the table, which makes this map a starting point for experimenting with such
+/// schemes. It implements `MapRead` and `MapWrite` like `RHMap`, so `BenchContext` can compare the two directly.
+pub struct HopscotchMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    slots: Vec<Option<Entry<K, V>>>,
+    /// `hops[i]` has bit `d` set when slot `i + d` holds an entry whose home slot is `i`.
+    hops: Vec<u32>,
+    /// `number of slots - 1`. The slot count is always a power of two.
+    mask: usize,
+    num_items: usize,
+    hasher_builder: H,
+}
+
+impl<K: Hash + Eq, V> HopscotchMap<K, V, FxBuildHasher> {
+    /// Creates an empty `HopscotchMap` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> Default for HopscotchMap<K, V, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> HopscotchMap<K, V, H> {
+    /// Creates an empty `HopscotchMap` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            slots: Vec::new(),
+            hops: Vec::new(),
+            mask: 0,
+            num_items: 0,
+            hasher_builder,
+        }
+    }
+
+    /// Inserts a key value pair, returning the previous value of the key, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hasher_builder.hash_one(&key) as usize;
+        if let (Some(i), _) = self.probe(hash, &key) {
+            let entry = self.slots[i].as_mut()?;
+            return Some(mem::replace(&mut entry.value, value));
+        }
+
+        if self.num_items + 1 > self.capacity() {
+            self.grow();
+        }
+        let mut entry = Entry::new(key, value, hash);
+        while let Err(rejected) = self.place(entry) {
+            self.grow();
+            entry = rejected;
+        }
+        self.num_items += 1;
+        None
+    }
+
+    /// Gets the value associated with the key.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    /// Gets the stored key and the value associated with the key.
+    pub fn get_key_value<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<(&K, &V)> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        let entry = self.slots[self.probe(hash, key).0?].as_ref()?;
+        Some((&entry.key, &entry.value))
+    }
+
+    /// Gets a mutable reference to the value associated with the key.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&mut V> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        let i = self.probe(hash, key).0?;
+        self.slots[i].as_mut().map(|entry| &mut entry.value)
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.get_key_value(key).is_some()
+    }
+
+    /// Removes the entry with the given key and returns its value, if there was one.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        let i = self.probe(hash, key).0?;
+        let home = hash & self.mask;
+        self.hops[home] &= !(1 << (i.wrapping_sub(home) & self.mask));
+        self.num_items -= 1;
+        self.slots[i].take().map(|entry| entry.value)
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+        self.hops.iter_mut().for_each(|hop| *hop = 0);
+        self.num_items = 0;
+    }
+
+    /// Gets the length / number of entries of the map.
+    pub fn len(&self) -> usize {
+        self.num_items
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+
+    /// Gets the number of entries the map can hold before it has to grow.
+    pub fn capacity(&self) -> usize {
+        (self.slots.len() as f64 * MAX_LOAD) as usize
+    }
+
+    /// Gets a reference to the hasher builder.
+    pub fn hasher(&self) -> &H {
+        &self.hasher_builder
+    }
+
+    /// Iterates over every key value pair in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.slots
+            .iter()
+            .flatten()
+            .map(|entry| (&entry.key, &entry.value))
+    }
+
+    /// Finds the slot of the key by walking the bitmap of its home slot. Also returns the number of slots inspected.
+    fn probe<Q: ?Sized + Equivalent<K>>(&self, hash: usize, key: &Q) -> (Option<usize>, usize) {
+        if self.slots.is_empty() {
+            return (None, 0);
+        }
+
+        let home = hash & self.mask;
+        let mut hops = self.hops[home];
+        let mut probes = 0;
+        while hops != 0 {
+            let i = (home + hops.trailing_zeros() as usize) & self.mask;
+            hops &= hops - 1;
+            probes += 1;
+            if matches!(&self.slots[i], Some(entry) if entry.hash == hash && key.equivalent(&entry.key))
+            {
+                return (Some(i), probes);
+            }
+        }
+        (None, probes)
+    }
+
+    /// Puts a new entry into the neighborhood of its home slot. Takes the nearest free slot and, while it lies outside
+    /// the neighborhood, swaps it backwards with an entry that may move into it. Hands the entry back if no such
+    /// entry exists, in which case the table has to grow.
+    fn place(&mut self, entry: Entry<K, V>) -> Result<(), Entry<K, V>> {
+        let home = entry.hash & self.mask;
+        let free = (0..self.slots.len()).find(|&d| self.slots[(home + d) & self.mask].is_none());
+        let mut distance = match free {
+            Some(distance) => distance,
+            None => return Err(entry),
+        };
+
+        while distance >= NEIGHBORHOOD {
+            let free = (home + distance) & self.mask;
+            // Looks for the entry furthest away from the free slot that can still reach it, to cover the most ground.
+            let hop = (1..NEIGHBORHOOD).rev().find_map(|back| {
+                let candidate = free.wrapping_sub(back) & self.mask;
+                let movable = self.hops[candidate] & ((1 << back) - 1);
+                (movable != 0).then_some((candidate, movable.trailing_zeros() as usize, back))
+            });
+            let (candidate, from, to) = match hop {
+                Some(hop) => hop,
+                None => return Err(entry),
+            };
+
+            self.slots[free] = self.slots[(candidate + from) & self.mask].take();
+            self.hops[candidate] = self.hops[candidate] & !(1 << from) | 1 << to;
+            distance -= to - from;
+        }
+
+        self.slots[(home + distance) & self.mask] = Some(entry);
+        self.hops[home] |= 1 << distance;
+        Ok(())
+    }
+
+    /// Doubles the number of slots and puts every entry back in, doubling again on the off chance they don't fit.
+    fn grow(&mut self) {
+        let mut entries: Vec<_> = self.slots.drain(..).flatten().collect();
+        let mut capacity = (self.mask + 1).max(INITIAL_CAPACITY / 2) * 2;
+
+        'resize: loop {
+            self.slots = (0..capacity).map(|_| None).collect();
+            self.hops = vec![0; capacity];
+            self.mask = capacity - 1;
+            while let Some(entry) = entries.pop() {
+                if let Err(entry) = self.place(entry) {
+                    entries.push(entry);
+                    entries.extend(self.slots.drain(..).flatten());
+                    capacity *= 2;
+                    continue 'resize;
+                }
+            }
+            return;
+        }
+    }
+}
+
+/// Formats the map like the std maps do (`{key: value, ...}`).
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone> Debug for HopscotchMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> Extend<(K, V)> for HopscotchMap<K, V, H> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        for (key, value) in pairs {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> FromIterator<(K, V)>
+    for HopscotchMap<K, V, H>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let mut map = Self::default();
+        map.extend(pairs);
+        map
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> MapRead<K, V> for HopscotchMap<K, V, H> {
+    fn get(&self, key: &K) -> Option<&V> {
+        HopscotchMap::get(self, key)
+    }
+
+    fn len(&self) -> usize {
+        self.num_items
+    }
+
+    fn probe_count(&self, key: &K) -> usize {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        self.probe(hash, key).1
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> MapWrite<K, V> for HopscotchMap<K, V, H> {
+    fn insert(&mut self, key: K, value: V) {
+        HopscotchMap::insert(self, key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        HopscotchMap::remove(self, key).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_inserts_gets_and_removes() {
+        let mut map = FxHopscotchMap::new();
+        assert_eq!(map.insert("a", 1), None);
+        assert_eq!(map.insert("a", 2), Some(1));
+        map.insert("b", 3);
+
+        assert_eq!(map.get("a"), Some(&2));
+        *map.get_mut("b").unwrap() += 1;
+        assert_eq!(map.remove("b"), Some(4));
+        assert_eq!(map.remove("b"), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn it_keeps_every_key_within_its_neighborhood() {
+        let mut map: FxHopscotchMap<String, usize> =
+            (0..10_000).map(|x| (format!("key{}", x), x)).collect();
+        for x in (0..10_000).step_by(2) {
+            assert_eq!(map.remove(format!("key{}", x).as_str()), Some(x));
+        }
+
+        assert_eq!(map.iter().count(), 5_000);
+        for x in (1..10_000).step_by(2) {
+            let key = format!("key{}", x);
+            assert_eq!(map.get(key.as_str()), Some(&x));
+            assert!(MapRead::probe_count(&map, &key) <= NEIGHBORHOOD);
+        }
+    }
+}
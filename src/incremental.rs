@@ -0,0 +1,259 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use core::{
+    hash::{BuildHasher, Hash},
+    mem,
+};
+
+/// How many slots of the old table are migrated per operation by default.
+const DEFAULT_MIGRATION_STEP: usize = 8;
+
+/// A `RHMap` that resizes incrementally. Instead of rehashing every entry in the insert that crosses the load factor,
+/// it allocates the larger table and keeps the old one alive, moving a bounded number of old slots over on every
+/// following insert and removal. This keeps the worst case insert latency flat at the cost of looking keys up in two
+/// tables while a migration is in progress.
+///
+/// Every key lives in exactly one of the two tables. Each migrated slot frees room in the new table faster than
+/// inserts can fill it, so a migration always finishes before the new table itself needs to grow.
+pub struct IncrementalMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    /// The table new entries go into.
+    current: RHMap<K, V, H>,
+    /// The table being migrated, and the slot migration continues from.
+    old: Option<(RHMap<K, V, H>, usize)>,
+    /// Slots of `old` migrated per operation.
+    step: usize,
+}
+
+impl<K: Hash + Eq, V> IncrementalMap<K, V, FxBuildHasher> {
+    /// Creates an empty `IncrementalMap` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V> Default for IncrementalMap<K, V, FxBuildHasher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> IncrementalMap<K, V, H> {
+    /// Creates an empty `IncrementalMap` with a custom hasher builder.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self::from_map(RHMap::with_hasher(hasher_builder))
+    }
+
+    /// Wraps an existing map, keeping its load factor and growth policy for the tables allocated from now on.
+    pub fn from_map(map: RHMap<K, V, H>) -> Self {
+        Self {
+            current: map,
+            old: None,
+            step: DEFAULT_MIGRATION_STEP,
+        }
+    }
+
+    /// Sets how many slots of the old table are moved per operation while resizing. Larger steps finish migrations
+    /// sooner, smaller ones bound the work a single insert does more tightly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is below 2, which wouldn't be enough to finish a migration before the new table fills up.
+    pub fn with_migration_step(mut self, step: usize) -> Self {
+        assert!(step >= 2, "migration step must be at least 2");
+        self.step = step;
+        self
+    }
+
+    /// Inserts a value with its associated key, overwriting any previous value.
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some((old, _)) = &mut self.old {
+            let _ = old.remove(&key);
+        }
+        self.migrate();
+
+        if self.current.needs_growth() && !self.current.contains_key(&key) {
+            self.start_migration();
+        }
+        self.current.insert(key, value);
+    }
+
+    /// Gets the value associated with the key.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        match &self.old {
+            Some((old, _)) => self.current.get(key).or_else(|| old.get(key)),
+            None => self.current.get(key),
+        }
+    }
+
+    /// Gets a mutable reference to the value associated with the key.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&mut V> {
+        match &mut self.old {
+            Some((old, _)) => match self.current.get_mut(key) {
+                Some(value) => Some(value),
+                None => old.get_mut(key),
+            },
+            None => self.current.get_mut(key),
+        }
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Deletes the entry with the given key. Returns an `Err` if no such entry with the provided key exists.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(
+        &mut self,
+        key: &Q,
+    ) -> Result<(), &'static str> {
+        let removed = match &mut self.old {
+            Some((old, _)) => self.current.remove(key).or_else(|_| old.remove(key)),
+            None => self.current.remove(key),
+        };
+        self.migrate();
+        removed
+    }
+
+    /// Gets the length / number of entries of the map, across both tables.
+    pub fn len(&self) -> usize {
+        self.current.len() + self.old.as_ref().map_or(0, |(old, _)| old.len())
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the capacity of the table new entries go into.
+    pub fn capacity(&self) -> usize {
+        self.current.capacity()
+    }
+
+    /// Returns `true` while entries are still being moved out of an old table.
+    pub fn is_migrating(&self) -> bool {
+        self.old.is_some()
+    }
+
+    /// Moves every remaining entry out of the old table right away.
+    pub fn finish_migration(&mut self) {
+        while self.old.is_some() {
+            self.migrate();
+        }
+    }
+
+    /// Unwraps the map, finishing any migration in progress first.
+    pub fn into_inner(mut self) -> RHMap<K, V, H> {
+        self.finish_migration();
+        self.current
+    }
+
+    /// Swaps in a larger empty table, keeping the current one as the table to migrate from.
+    fn start_migration(&mut self) {
+        // A new table can't fill up before the last migration is over, but finishing it keeps this cheap to reason
+        // about if the load factor was changed in between.
+        self.finish_migration();
+        if let Some(grown) = self.current.empty_grown() {
+            let mut old = mem::replace(&mut self.current, grown);
+            // Shrinking would rehash entries into slots the cursor has already passed, and they'd never be migrated.
+            old.set_shrink_threshold(None);
+            self.old = Some((old, 0));
+        }
+    }
+
+    /// Moves up to `step` slots of the old table into the current one.
+    fn migrate(&mut self) {
+        let (old, cursor) = match &mut self.old {
+            Some(old) => old,
+            None => return,
+        };
+
+        let end = (*cursor + self.step).min(old.bucket_count());
+        while *cursor < end {
+            // Removing shifts the rest of the cluster back into this slot, so keep taking from it until it's vacant.
+            while old.entry_at(*cursor).is_some() {
+                let entry = old.remove_at(*cursor);
+                self.current
+                    .insert_vacant(entry.hash, entry.key, entry.value);
+            }
+            *cursor += 1;
+        }
+
+        if *cursor == old.bucket_count() {
+            assert!(
+                old.is_empty(),
+                "migration passed the end of the old table with entries left in it"
+            );
+        }
+        if old.is_empty() {
+            self.old = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_migrates_entries_across_inserts() {
+        let mut map = IncrementalMap::new();
+        let mut migrations = 0;
+        for x in 0..1000 {
+            let was_migrating = map.is_migrating();
+            map.insert(x, x * 2);
+            if map.is_migrating() && !was_migrating {
+                migrations += 1;
+            }
+            assert_eq!(map.len(), x + 1);
+        }
+
+        assert!(migrations > 5);
+        assert!((0..1000).all(|x| map.get(&x) == Some(&(x * 2))));
+        for x in (0..1000).step_by(2) {
+            assert!(map.remove(&x).is_ok());
+        }
+        map.insert(1, 0);
+
+        let map = map.into_inner();
+        assert_eq!(map.len(), 500);
+        assert_eq!(map.get(&1), Some(&0));
+        assert_eq!(map.get(&999), Some(&1998));
+        assert_eq!(map.get(&998), None);
+    }
+
+    #[test]
+    fn it_keeps_the_old_table_whole_when_removals_would_shrink_it() {
+        let mut inner = RHMap::new();
+        inner.set_shrink_threshold(Some(0.25));
+        let mut map = IncrementalMap::from_map(inner).with_migration_step(2);
+
+        // Fill until a migration starts out of a table of more than 1000 entries.
+        let mut next = 0;
+        loop {
+            let was_migrating = map.is_migrating();
+            map.insert(next, next);
+            next += 1;
+            if map.is_migrating() && !was_migrating && next > 1000 {
+                break;
+            }
+        }
+
+        // Removing the keys homed in the top half of the old table drops it below the shrink threshold long before
+        // the migration gets to them.
+        let top = {
+            let (old, _) = map.old.as_ref().unwrap();
+            (old.bucket_count() / 2..old.bucket_count())
+                .filter_map(|i| old.entry_at(i).map(|entry| entry.key))
+                .collect::<Vec<_>>()
+        };
+        for key in &top {
+            assert!(map.remove(key).is_ok());
+        }
+        assert_eq!(map.len(), next - top.len());
+
+        let map = map.into_inner();
+        assert_eq!(map.len(), next - top.len());
+        assert!((0..next).all(|x| map.contains_key(&x) != top.contains(&x)));
+    }
+}
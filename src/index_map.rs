@@ -0,0 +1,305 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::positions::PositionTable;
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    mem,
+};
+
+/// An `IndexMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxIndexMap<K, V> = IndexMap<K, V, FxBuildHasher>;
+
+struct Bucket<K, V> {
+    hash: usize,
+    key: K,
+    value: V,
+}
+
+/// A map that remembers the order its keys were first inserted in. Entries live in a dense vector in that order, and
+/// a Robin Hood table on the side maps hashes to positions in the vector, so iteration is a plain slice walk and every
+/// entry also has a stable ordinal (until something before it is removed).
+///
+/// Removing comes in two flavours: `swap_remove` fills the hole with the last entry in O(1), `shift_remove` keeps the
+/// order of the remaining entries at the cost of renumbering everything after the hole.
+pub struct IndexMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    entries: Vec<Bucket<K, V>>,
+    /// Finds the position in `entries` of a key.
+    indices: PositionTable,
+    hasher_builder: H,
+}
+
+impl<K: Hash + Eq, V> IndexMap<K, V, FxBuildHasher> {
+    /// Creates an `IndexMap` with the default Fx Hasher and an initial capacity of 0.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> Default for IndexMap<K, V, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> IndexMap<K, V, H> {
+    /// Creates an `IndexMap` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            entries: Vec::new(),
+            indices: PositionTable::new(),
+            hasher_builder,
+        }
+    }
+
+    /// Inserts a key value pair. A new key goes to the end of the order, an existing key keeps its position and only
+    /// has its value replaced. Returns the previous value, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Like `insert`, but also returns the position of the entry.
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<V>) {
+        let hash = self.hasher_builder.hash_one(&key) as usize;
+        if let Some(slot) = self.find_slot(hash, &key) {
+            let index = self.indices.position(slot);
+            let old = mem::replace(&mut self.entries[index].value, value);
+            return (index, Some(old));
+        }
+
+        let index = self.entries.len();
+        self.entries.push(Bucket { hash, key, value });
+        self.indices.insert(hash, index);
+        (index, None)
+    }
+
+    /// Gets the value associated with the key.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        self.get_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Gets a mutable reference to the value associated with the key.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&mut V> {
+        let index = self.get_index_of(key)?;
+        Some(&mut self.entries[index].value)
+    }
+
+    /// Gets the position, stored key and value of the entry with the given key.
+    pub fn get_full<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<(usize, &K, &V)> {
+        let index = self.get_index_of(key)?;
+        let bucket = &self.entries[index];
+        Some((index, &bucket.key, &bucket.value))
+    }
+
+    /// Gets the position of the entry with the given key.
+    pub fn get_index_of<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<usize> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        self.find_slot(hash, key)
+            .map(|slot| self.indices.position(slot))
+    }
+
+    /// Gets the key value pair at the given position in insertion order.
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        self.entries
+            .get(index)
+            .map(|bucket| (&bucket.key, &bucket.value))
+    }
+
+    /// Gets the key and a mutable reference to the value at the given position in insertion order.
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut V)> {
+        self.entries
+            .get_mut(index)
+            .map(|bucket| (&bucket.key, &mut bucket.value))
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.get_index_of(key).is_some()
+    }
+
+    /// Removes the entry with the given key by moving the last entry into its position, which disturbs the order but
+    /// takes constant time. Returns the removed value, if any.
+    pub fn swap_remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        self.swap_remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Like `swap_remove`, but also returns the position the entry had and its stored key.
+    pub fn swap_remove_full<Q: ?Sized + Hash + Equivalent<K>>(
+        &mut self,
+        key: &Q,
+    ) -> Option<(usize, K, V)> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        let slot = self.find_slot(hash, key)?;
+        let index = self.indices.position(slot);
+        self.indices.remove(slot);
+
+        let last = self.entries.len() - 1;
+        if index != last {
+            // The last entry is about to move into the hole, so its slot has to point there instead.
+            let moved = self.indices.slot_of(self.entries[last].hash, last);
+            self.indices.set_position(moved, index);
+        }
+        let bucket = self.entries.swap_remove(index);
+        Some((index, bucket.key, bucket.value))
+    }
+
+    /// Removes the entry with the given key and shifts every later entry one position down, which keeps the order but
+    /// takes linear time. Returns the removed value, if any.
+    pub fn shift_remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        self.shift_remove_full(key).map(|(_, _, value)| value)
+    }
+
+    /// Like `shift_remove`, but also returns the position the entry had and its stored key.
+    pub fn shift_remove_full<Q: ?Sized + Hash + Equivalent<K>>(
+        &mut self,
+        key: &Q,
+    ) -> Option<(usize, K, V)> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        let slot = self.find_slot(hash, key)?;
+        let index = self.indices.position(slot);
+        self.indices.remove(slot);
+
+        for position in self.indices.positions_mut() {
+            if *position > index {
+                *position -= 1;
+            }
+        }
+        let bucket = self.entries.remove(index);
+        Some((index, bucket.key, bucket.value))
+    }
+
+    /// Removes and returns the last entry in insertion order.
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        let last = self.entries.len().checked_sub(1)?;
+        let slot = self.indices.slot_of(self.entries[last].hash, last);
+        self.indices.remove(slot);
+        self.entries.pop().map(|bucket| (bucket.key, bucket.value))
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+    }
+
+    /// Gets the length / number of entries of the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Gets the hasher builder the map hashes its keys with.
+    pub fn hasher(&self) -> &H {
+        &self.hasher_builder
+    }
+
+    /// Iterates over every key value pair in insertion order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + '_ {
+        self.entries
+            .iter()
+            .map(|bucket| (&bucket.key, &bucket.value))
+    }
+
+    /// Iterates over every key value pair in insertion order, with mutable references to the values.
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = (&K, &mut V)> + '_ {
+        self.entries
+            .iter_mut()
+            .map(|bucket| (&bucket.key, &mut bucket.value))
+    }
+
+    /// Iterates over every key in insertion order.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + '_ {
+        self.entries.iter().map(|bucket| &bucket.key)
+    }
+
+    /// Iterates over every value in insertion order.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + '_ {
+        self.entries.iter().map(|bucket| &bucket.value)
+    }
+
+    /// The index table slot holding the entry with the given hash and key.
+    fn find_slot<Q: ?Sized + Equivalent<K>>(&self, hash: usize, key: &Q) -> Option<usize> {
+        let entries = &self.entries;
+        self.indices
+            .find(hash, |index| key.equivalent(&entries[index].key))
+    }
+}
+
+/// Formats the map like the std maps do (`{key: value, ...}`), in insertion order.
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone> Debug for IndexMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> Extend<(K, V)> for IndexMap<K, V, H> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        for (key, value) in pairs {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> FromIterator<(K, V)> for IndexMap<K, V, H> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let mut map = Self::default();
+        map.extend(pairs);
+        map
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> IntoIterator for IndexMap<K, V, H> {
+    type Item = (K, V);
+    type IntoIter = vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries
+            .into_iter()
+            .map(|bucket| (bucket.key, bucket.value))
+            .collect::<Vec<(K, V)>>()
+            .into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_iterates_in_insertion_order() {
+        let mut map = FxIndexMap::new();
+        for x in (0..1000).rev() {
+            assert_eq!(map.insert_full(x, x * 2), (999 - x as usize, None));
+        }
+        assert_eq!(map.insert(500, 0), Some(1000));
+
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.get_full(&500), Some((499, &500, &0)));
+        assert_eq!(map.get_index(0), Some((&999, &1998)));
+        assert_eq!(map.get_index_of(&0), Some(999));
+        assert!(map.keys().copied().eq((0..1000).rev()));
+    }
+
+    #[test]
+    fn it_swap_and_shift_removes() {
+        let mut map: FxIndexMap<&str, i32> = vec![("a", 1), ("b", 2), ("c", 3), ("d", 4)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(map.swap_remove_full(&"a"), Some((0, "a", 1)));
+        assert!(map.keys().copied().eq(["d", "b", "c"]));
+        assert_eq!(map.shift_remove(&"d"), Some(4));
+        assert!(map.keys().copied().eq(["b", "c"]));
+        assert_eq!(map.get_index_of(&"c"), Some(1));
+        assert_eq!(map.pop(), Some(("c", 3)));
+        assert_eq!(map.swap_remove(&"a"), None);
+        assert_eq!(map.get(&"b"), Some(&2));
+        assert_eq!(map.len(), 1);
+    }
+}
@@ -0,0 +1,170 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::index_map::IndexMap;
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+};
+
+/// An `IndexSet` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxIndexSet<T> = IndexSet<T, FxBuildHasher>;
+
+/// A set that remembers the order its values were first inserted in, a thin wrapper over an `IndexMap` whose values
+/// are `()`. Every value has an ordinal, its position in that order, which makes it a natural symbol table: `insert_full`
+/// hands back the ordinal of a new or existing symbol and `get_index` turns it back into the symbol.
+pub struct IndexSet<T: Hash + Eq, H: BuildHasher + Clone> {
+    map: IndexMap<T, (), H>,
+}
+
+impl<T: Hash + Eq> IndexSet<T, FxBuildHasher> {
+    /// Creates an `IndexSet` with the default Fx Hasher and an initial capacity of 0.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone + Default> Default for IndexSet<T, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone> IndexSet<T, H> {
+    /// Creates an `IndexSet` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            map: IndexMap::with_hasher(hasher_builder),
+        }
+    }
+
+    /// Adds a value to the end of the order. Returns `true` if it wasn't already present, leaving the set untouched
+    /// otherwise.
+    pub fn insert(&mut self, value: T) -> bool {
+        self.insert_full(value).1
+    }
+
+    /// Like `insert`, but also returns the position of the value, whether it was just added or already present.
+    pub fn insert_full(&mut self, value: T) -> (usize, bool) {
+        let (index, old) = self.map.insert_full(value, ());
+        (index, old.is_none())
+    }
+
+    /// Checks to see if the set contains the given value.
+    pub fn contains<Q: ?Sized + Hash + Equivalent<T>>(&self, value: &Q) -> bool {
+        self.map.contains_key(value)
+    }
+
+    /// Gets the position of the given value.
+    pub fn get_index_of<Q: ?Sized + Hash + Equivalent<T>>(&self, value: &Q) -> Option<usize> {
+        self.map.get_index_of(value)
+    }
+
+    /// Gets the position and stored value equal to the given one.
+    pub fn get_full<Q: ?Sized + Hash + Equivalent<T>>(&self, value: &Q) -> Option<(usize, &T)> {
+        self.map
+            .get_full(value)
+            .map(|(index, value, _)| (index, value))
+    }
+
+    /// Gets the value at the given position in insertion order.
+    pub fn get_index(&self, index: usize) -> Option<&T> {
+        self.map.get_index(index).map(|(value, _)| value)
+    }
+
+    /// Removes a value by moving the last value into its position, which disturbs the order but takes constant time.
+    /// Returns `true` if it was present.
+    pub fn swap_remove<Q: ?Sized + Hash + Equivalent<T>>(&mut self, value: &Q) -> bool {
+        self.map.swap_remove(value).is_some()
+    }
+
+    /// Removes a value and shifts every later value one position down, which keeps the order but takes linear time.
+    /// Returns `true` if it was present.
+    pub fn shift_remove<Q: ?Sized + Hash + Equivalent<T>>(&mut self, value: &Q) -> bool {
+        self.map.shift_remove(value).is_some()
+    }
+
+    /// Removes and returns the last value in insertion order.
+    pub fn pop(&mut self) -> Option<T> {
+        self.map.pop().map(|(value, _)| value)
+    }
+
+    /// Clears all values but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Gets the number of values in the set.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the set contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Iterates over every value in insertion order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + '_ {
+        self.map.keys()
+    }
+}
+
+/// Formats the set like the std sets do (`{value, ...}`), in insertion order.
+impl<T: Hash + Eq + Debug, H: BuildHasher + Clone> Debug for IndexSet<T, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone> Extend<T> for IndexSet<T, H> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, values: I) {
+        for value in values {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone + Default> FromIterator<T> for IndexSet<T, H> {
+    fn from_iter<I: IntoIterator<Item = T>>(values: I) -> Self {
+        let mut set = Self::default();
+        set.extend(values);
+        set
+    }
+}
+
+impl<T: Hash + Eq, H: BuildHasher + Clone> IntoIterator for IndexSet<T, H> {
+    type Item = T;
+    type IntoIter = vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map
+            .into_iter()
+            .map(|(value, _)| value)
+            .collect::<Vec<T>>()
+            .into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_numbers_symbols_in_insertion_order() {
+        let mut symbols = FxIndexSet::new();
+        assert_eq!(symbols.insert_full("main"), (0, true));
+        assert_eq!(symbols.insert_full("print"), (1, true));
+        assert_eq!(symbols.insert_full("main"), (0, false));
+        assert!(symbols.insert("exit"));
+
+        assert_eq!(symbols.get_index_of(&"exit"), Some(2));
+        assert_eq!(symbols.get_index(1), Some(&"print"));
+        assert!(symbols.shift_remove(&"main"));
+        assert!(symbols.iter().copied().eq(["print", "exit"]));
+        assert_eq!(symbols.get_full(&"exit"), Some((1, &"exit")));
+        assert_eq!(symbols.pop(), Some("exit"));
+        assert!(!symbols.contains(&"exit"));
+    }
+}
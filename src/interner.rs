@@ -0,0 +1,207 @@
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use super::no_hash::NoHashBuilder;
+use super::raw_entry::RawEntryMut;
+use alloc::{string::String, vec::Vec};
+use core::{
+    convert::TryFrom,
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash, Hasher},
+    iter::FromIterator,
+};
+
+/// An `Interner` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxInterner = Interner<FxBuildHasher>;
+
+/// The handle of a string interned by an `Interner`. Symbols are handed out in order from 0, so they also make dense
+/// indices into side tables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Gets the number of the symbol.
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+/// A table slot: the symbol of a string along with the string's hash. Hashes to the stored hash, so under
+/// `NoHashBuilder` the table never needs the string to place or move it.
+#[derive(PartialEq, Eq)]
+struct Interned {
+    hash: u64,
+    symbol: Symbol,
+}
+
+impl Hash for Interned {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// Deduplicates strings, handing out a small `Symbol` per distinct string that can be resolved back to it. Symbols are
+/// cheaper to copy, compare and hash than the strings themselves, e.g. for identifiers in a compiler.
+///
+/// Every string is stored once, back to back in a single arena. The table only holds symbols along with the string
+/// hashes, and is probed through its raw entry points with the hash computed here, so the strings are never copied
+/// into it.
+pub struct Interner<H: BuildHasher + Clone> {
+    table: RHMap<Interned, (), NoHashBuilder>,
+    /// Every interned string, in symbol order.
+    arena: String,
+    /// `ends[i]` is where the string of symbol `i` ends in `arena`. It starts where the previous one ends.
+    ends: Vec<usize>,
+    hasher_builder: H,
+}
+
+impl Interner<FxBuildHasher> {
+    /// Creates an empty `Interner` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<H: BuildHasher + Clone + Default> Default for Interner<H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<H: BuildHasher + Clone> Interner<H> {
+    /// Creates an empty `Interner` with a custom hasher builder for the strings which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            table: RHMap::with_hasher(NoHashBuilder),
+            arena: String::new(),
+            ends: Vec::new(),
+            hasher_builder,
+        }
+    }
+
+    /// Gets the symbol of the string, interning it first if it is new.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than `u32::MAX` distinct strings are interned.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        let hash = self.hasher_builder.hash_one(s);
+        let (arena, ends) = (&self.arena, &self.ends);
+        let entry = self
+            .table
+            .raw_entry_mut()
+            .from_hash(hash, |interned| resolve(arena, ends, interned.symbol) == s);
+        match entry {
+            RawEntryMut::Occupied(entry) => entry.key().symbol,
+            RawEntryMut::Vacant(entry) => {
+                let symbol = Symbol(
+                    u32::try_from(self.ends.len()).expect("more than u32::MAX strings interned"),
+                );
+                self.arena.push_str(s);
+                self.ends.push(self.arena.len());
+                entry.insert_hashed_nocheck(hash, Interned { hash, symbol }, ());
+                symbol
+            }
+        }
+    }
+
+    /// Gets the symbol of the string, if it has been interned.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        let hash = self.hasher_builder.hash_one(s);
+        self.table
+            .raw_entry()
+            .from_hash(hash, |interned| self.resolve(interned.symbol) == Some(s))
+            .map(|(interned, _)| interned.symbol)
+    }
+
+    /// Gets the string of a symbol, `None` for symbols this interner didn't hand out.
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        if symbol.0 as usize >= self.ends.len() {
+            return None;
+        }
+        Some(resolve(&self.arena, &self.ends, symbol))
+    }
+
+    /// Gets the number of distinct strings interned.
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Returns `true` if no string has been interned.
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Gets the combined length in bytes of every distinct string interned.
+    pub fn arena_len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Iterates over every symbol along with its string, in symbol order.
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> + '_ {
+        (0..self.ends.len() as u32)
+            .map(move |i| (Symbol(i), resolve(&self.arena, &self.ends, Symbol(i))))
+    }
+}
+
+/// The string of a symbol known to be in range.
+fn resolve<'a>(arena: &'a str, ends: &[usize], symbol: Symbol) -> &'a str {
+    let i = symbol.0 as usize;
+    let start = if i == 0 { 0 } else { ends[i - 1] };
+    &arena[start..ends[i]]
+}
+
+/// Formats the interner like the std maps do, from each symbol to its string.
+impl<H: BuildHasher + Clone> Debug for Interner<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, H: BuildHasher + Clone> Extend<&'a str> for Interner<H> {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, strings: I) {
+        for s in strings {
+            self.intern(s);
+        }
+    }
+}
+
+impl<'a, H: BuildHasher + Clone + Default> FromIterator<&'a str> for Interner<H> {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(strings: I) -> Self {
+        let mut interner = Self::default();
+        interner.extend(strings);
+        interner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_stores_each_string_once() {
+        let mut interner = FxInterner::new();
+        let a = interner.intern("alpha");
+        let b = interner.intern("beta");
+
+        assert_eq!(interner.intern("alpha"), a);
+        assert_ne!(a, b);
+        assert_eq!((a.as_u32(), b.as_u32()), (0, 1));
+        assert_eq!(interner.resolve(b), Some("beta"));
+        assert_eq!(interner.get("beta"), Some(b));
+        assert_eq!(interner.get("gamma"), None);
+        assert_eq!(interner.resolve(Symbol(2)), None);
+        assert_eq!(interner.arena_len(), "alphabeta".len());
+    }
+
+    #[test]
+    fn it_resolves_every_symbol_after_growing() {
+        let words: Vec<String> = (0..5000).map(|x| format!("word{}", x % 1000)).collect();
+        let interner: FxInterner = words.iter().map(String::as_str).collect();
+
+        assert_eq!(interner.len(), 1000);
+        for (symbol, s) in interner.iter() {
+            assert_eq!(interner.get(s), Some(symbol));
+        }
+        assert_eq!(interner.resolve(Symbol(999)), Some("word999"));
+    }
+}
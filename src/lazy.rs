@@ -0,0 +1,152 @@
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use alloc::boxed::Box;
+use core::{
+    cell::{Cell, OnceCell},
+    hash::{BuildHasher, Hash},
+};
+
+/// A value that is computed the first time it is read.
+struct LazyValue<V> {
+    value: OnceCell<V>,
+    /// The thunk producing the value, taken once it has been run.
+    init: Cell<Option<Box<dyn FnOnce() -> V>>>,
+}
+
+impl<V> LazyValue<V> {
+    fn force(&self) -> &V {
+        self.value.get_or_init(|| match self.init.take() {
+            Some(init) => init(),
+            None => panic!("lazy value was read while it was being computed"),
+        })
+    }
+}
+
+/// A `RHMap` whose values are registered as thunks and only computed the first time they are read, after which the
+/// result is cached in place. Handy for config or plugin registries where most registered entries are never looked
+/// at.
+pub struct LazyValueMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    map: RHMap<K, LazyValue<V>, H>,
+}
+
+impl<K: Hash + Eq, V> LazyValueMap<K, V, FxBuildHasher> {
+    /// Creates an empty `LazyValueMap` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V> Default for LazyValueMap<K, V, FxBuildHasher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> LazyValueMap<K, V, H> {
+    /// Creates an empty `LazyValueMap` with a custom hasher builder.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            map: RHMap::with_hasher(hasher_builder),
+        }
+    }
+
+    /// Registers `init` as the producer of the value for `key`, overwriting any previous value. `init` isn't run
+    /// until the value is first read.
+    pub fn insert<F: FnOnce() -> V + 'static>(&mut self, key: K, init: F) {
+        self.map.insert(
+            key,
+            LazyValue {
+                value: OnceCell::new(),
+                init: Cell::new(Some(Box::new(init))),
+            },
+        );
+    }
+
+    /// Inserts an already computed value with its associated key, overwriting any previous value.
+    pub fn insert_value(&mut self, key: K, value: V) {
+        self.map.insert(
+            key,
+            LazyValue {
+                value: OnceCell::from(value),
+                init: Cell::new(None),
+            },
+        );
+    }
+
+    /// Gets the value associated with the key, computing and caching it first if this is its first read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value's own thunk tries to read it.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key).map(LazyValue::force)
+    }
+
+    /// Gets a mutable reference to the value associated with the key, computing it first if needed.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let lazy = self.map.get_mut(key)?;
+        lazy.force();
+        lazy.value.get_mut()
+    }
+
+    /// Returns `true` if the value for `key` exists and has already been computed.
+    pub fn is_forced(&self, key: &K) -> bool {
+        self.map
+            .get(key)
+            .is_some_and(|lazy| lazy.value.get().is_some())
+    }
+
+    /// Checks to see if the provided key is associated with any value, without computing it.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Deletes the entry with the given key. Returns an `Err` if no such entry with the provided key exists.
+    pub fn remove(&mut self, key: &K) -> Result<(), &'static str> {
+        self.map.remove(key)
+    }
+
+    /// Gets the length / number of entries of the map, computed or not.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn it_computes_values_on_first_read() {
+        let runs = Rc::new(Cell::new(0));
+        let mut map = LazyValueMap::new();
+        for x in 0..10 {
+            let runs = Rc::clone(&runs);
+            map.insert(x, move || {
+                runs.set(runs.get() + 1);
+                x * 10
+            });
+        }
+        map.insert_value(10, 100);
+
+        assert_eq!(runs.get(), 0);
+        assert!(map.is_forced(&10));
+        assert!(!map.is_forced(&3));
+        assert_eq!(map.get(&3), Some(&30));
+        assert_eq!(map.get(&3), Some(&30));
+        assert_eq!(runs.get(), 1);
+        assert!(map.is_forced(&3));
+
+        *map.get_mut(&4).unwrap() += 1;
+        assert_eq!(map.get(&4), Some(&41));
+        assert_eq!(runs.get(), 2);
+        assert_eq!(map.get(&11), None);
+        assert_eq!(map.len(), 11);
+    }
+}
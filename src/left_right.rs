@@ -0,0 +1,342 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use std::{
+    cell::{Cell, UnsafeCell},
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+    mem,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicUsize, Ordering::SeqCst},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+/// A mutation the writer made to one copy of the map, replayed onto the other copy once readers have left it.
+enum Op<K, V> {
+    Insert(K, V),
+    Remove(K),
+    Clear,
+}
+
+/// The two copies of the map and the bookkeeping both handles share.
+struct Inner<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    maps: [UnsafeCell<RHMap<K, V, H>>; 2],
+    /// The copy readers read from. The writer owns the other one.
+    active: AtomicUsize,
+    /// One counter per read handle, odd while that handle is reading. The writer waits on these before touching a
+    /// copy readers may still be in.
+    epochs: Mutex<Vec<Arc<AtomicUsize>>>,
+}
+
+// SAFETY: readers only ever get shared references into the active copy, and the writer only mutates the inactive copy
+// once every reader that could have seen it as active has moved on (see `WriteHandle::publish`).
+unsafe impl<K: Hash + Eq + Send + Sync, V: Send + Sync, H: BuildHasher + Clone + Send + Sync> Sync
+    for Inner<K, V, H>
+{
+}
+
+// SAFETY: as above, the maps are only ever accessed through the epoch protocol, whichever thread holds the handles.
+unsafe impl<K: Hash + Eq + Send + Sync, V: Send + Sync, H: BuildHasher + Clone + Send + Sync> Send
+    for Inner<K, V, H>
+{
+}
+
+/// The writing half of a left-right map, for read-mostly workloads where lookups must never wait on a lock.
+///
+/// The map is kept twice. Readers (`ReadHandle`s, from `reader`) read one copy without any locking, while the writer
+/// updates the other copy and logs what it did. `publish` swaps the copies, waits until no reader is left in the old
+/// one, then replays the log onto it. Readers so see the writes in batches, one `publish` at a time, and every write
+/// is applied twice, which is why keys and values have to be `Clone`.
+///
+/// Readers only announce when they start and finish reading (an atomic increment each), which is all the writer needs
+/// to know when the old copy can be reused.
+pub struct WriteHandle<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone> {
+    inner: Arc<Inner<K, V, H>>,
+    /// Writes applied to the inactive copy since the last `publish`.
+    log: Vec<Op<K, V>>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> WriteHandle<K, V, FxBuildHasher> {
+    /// Creates an empty left-right map with the default Fx Hasher, returning its writer.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone + Default> Default
+    for WriteHandle<K, V, H>
+{
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone> WriteHandle<K, V, H> {
+    /// Creates an empty left-right map with a custom hasher builder (used for both copies), returning its writer.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        let maps = [
+            UnsafeCell::new(RHMap::with_hasher(hasher_builder.clone())),
+            UnsafeCell::new(RHMap::with_hasher(hasher_builder)),
+        ];
+
+        Self {
+            inner: Arc::new(Inner {
+                maps,
+                active: AtomicUsize::new(0),
+                epochs: Mutex::new(Vec::new()),
+            }),
+            log: Vec::new(),
+        }
+    }
+
+    /// Creates a new reader of the map. Readers can be sent to other threads and live on after the writer is gone,
+    /// still seeing the map as of the last `publish`.
+    pub fn reader(&self) -> ReadHandle<K, V, H> {
+        ReadHandle::register(self.inner.clone())
+    }
+
+    /// Inserts a key value pair, overwriting any previous value. Readers see it after the next `publish`.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.write_map().insert(key.clone(), value.clone());
+        self.log.push(Op::Insert(key, value));
+    }
+
+    /// Removes the entry with the given key and returns its value, if there was one. Readers see it gone after the
+    /// next `publish`.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        let (key, value) = self.write_map().remove_entry(key)?;
+        self.log.push(Op::Remove(key));
+        Some(value)
+    }
+
+    /// Clears all entries. Readers see the map empty after the next `publish`.
+    pub fn clear(&mut self) {
+        self.write_map().clear();
+        self.log.push(Op::Clear);
+    }
+
+    /// Gets the map as it will be after the next `publish`, including the writes readers can't see yet.
+    pub fn map(&self) -> &RHMap<K, V, H> {
+        // SAFETY: readers never touch the inactive copy.
+        unsafe { &*self.inner.maps[self.inactive()].get() }
+    }
+
+    /// Returns `true` if there are writes readers can't see yet.
+    pub fn has_pending(&self) -> bool {
+        !self.log.is_empty()
+    }
+
+    /// Makes every write so far visible to readers. Blocks until readers that were reading the previous copy have
+    /// finished their current read, then brings that copy up to date for the next round of writes. Publishing while
+    /// the same thread holds a `ReadGuard` therefore never returns.
+    pub fn publish(&mut self) {
+        if self.log.is_empty() {
+            return;
+        }
+
+        let old = self.inner.active.load(SeqCst);
+        self.inner.active.store(1 - old, SeqCst);
+
+        // Readers that weren't mid read, or that start one from now on, will see the new copy. The ones that were
+        // mid read have to finish it.
+        let mut epochs = self.inner.epochs.lock().expect("epoch list poisoned");
+        epochs.retain(|epoch| Arc::strong_count(epoch) > 1);
+        for epoch in epochs.iter() {
+            let seen = epoch.load(SeqCst);
+            if seen % 2 == 1 {
+                while epoch.load(SeqCst) == seen {
+                    thread::yield_now();
+                }
+            }
+        }
+        drop(epochs);
+
+        let log = mem::take(&mut self.log);
+        let map = self.write_map();
+        for op in log {
+            match op {
+                Op::Insert(key, value) => map.insert(key, value),
+                Op::Remove(key) => {
+                    map.remove_entry(&key);
+                }
+                Op::Clear => map.clear(),
+            }
+        }
+    }
+
+    fn inactive(&self) -> usize {
+        1 - self.inner.active.load(SeqCst)
+    }
+
+    fn write_map(&mut self) -> &mut RHMap<K, V, H> {
+        // SAFETY: only the writer touches the inactive copy, and `publish` waits for readers to leave a copy before
+        // it becomes the inactive one. `&mut self` rules out overlapping borrows from `map`.
+        unsafe { &mut *self.inner.maps[self.inactive()].get() }
+    }
+}
+
+/// The reading half of a left-right map, created by `WriteHandle::reader`. Reads never block, not even while the
+/// writer publishes.
+///
+/// A handle can be sent to another thread but not shared between threads: clone it for each reader instead.
+pub struct ReadHandle<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    inner: Arc<Inner<K, V, H>>,
+    epoch: Arc<AtomicUsize>,
+    /// How many guards of this handle are alive, so nested reads only announce themselves once.
+    depth: Cell<usize>,
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> ReadHandle<K, V, H> {
+    fn register(inner: Arc<Inner<K, V, H>>) -> Self {
+        let epoch = Arc::new(AtomicUsize::new(0));
+        inner
+            .epochs
+            .lock()
+            .expect("epoch list poisoned")
+            .push(epoch.clone());
+
+        Self {
+            inner,
+            epoch,
+            depth: Cell::new(0),
+        }
+    }
+
+    /// Gets a snapshot of the map as of the last `publish`. The writer can't reuse this copy until the guard is
+    /// dropped, so don't hold on to it for long.
+    pub fn read(&self) -> ReadGuard<'_, K, V, H> {
+        if self.depth.get() == 0 {
+            self.epoch.fetch_add(1, SeqCst);
+        }
+        self.depth.set(self.depth.get() + 1);
+        let active = self.inner.active.load(SeqCst);
+
+        ReadGuard {
+            handle: self,
+            // SAFETY: the odd epoch keeps the writer out of this copy until the guard is dropped.
+            map: unsafe { &*self.inner.maps[active].get() },
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Gets a clone of the value associated with the key, without holding on to the snapshot.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.read().get(key).cloned()
+    }
+
+    /// Checks to see if the provided key is associated with any value as of the last `publish`.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.read().contains_key(key)
+    }
+
+    /// Gets the number of entries as of the last `publish`.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Returns `true` if the map was empty as of the last `publish`.
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+}
+
+/// Registers a new reader of the same map.
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> Clone for ReadHandle<K, V, H> {
+    fn clone(&self) -> Self {
+        Self::register(self.inner.clone())
+    }
+}
+
+/// A snapshot of a left-right map, created by `ReadHandle::read`. Derefs to the map.
+pub struct ReadGuard<'a, K: Hash + Eq, V, H: BuildHasher + Clone> {
+    handle: &'a ReadHandle<K, V, H>,
+    map: &'a RHMap<K, V, H>,
+    /// The guard announces the end of the read on its handle's thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> Deref for ReadGuard<'a, K, V, H> {
+    type Target = RHMap<K, V, H>;
+
+    fn deref(&self) -> &RHMap<K, V, H> {
+        self.map
+    }
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone> Drop for ReadGuard<'a, K, V, H> {
+    fn drop(&mut self) {
+        let depth = self.handle.depth.get() - 1;
+        self.handle.depth.set(depth);
+        if depth == 0 {
+            self.handle.epoch.fetch_add(1, SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_shows_writes_once_published() {
+        let mut writer = WriteHandle::new();
+        let reader = writer.reader();
+        writer.insert("a", 1);
+        writer.insert("b", 2);
+
+        assert!(reader.is_empty());
+        assert_eq!(writer.map().len(), 2);
+        writer.publish();
+        assert_eq!(reader.get("a"), Some(1));
+
+        let snapshot = reader.read();
+        assert_eq!(writer.remove("a"), Some(1));
+        writer.insert("c", 3);
+        assert!(writer.has_pending());
+        assert_eq!(snapshot.len(), 2);
+        drop(snapshot);
+
+        writer.publish();
+        assert_eq!((reader.get("a"), reader.get("c")), (None, Some(3)));
+        // Both copies have caught up, whichever one the next round of writes goes to.
+        writer.insert("d", 4);
+        writer.publish();
+        assert_eq!(reader.len(), 3);
+    }
+
+    #[test]
+    fn it_reads_while_another_thread_publishes() {
+        let mut writer = WriteHandle::new();
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = writer.reader();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    while last < 1000 {
+                        let map = reader.read();
+                        // Every published batch is a prefix of 0..1000, so a snapshot is never torn.
+                        assert!((0..map.len() as u64).all(|x| map.get(&x) == Some(&x)));
+                        assert!(map.len() >= last);
+                        last = map.len();
+                    }
+                })
+            })
+            .collect();
+
+        for x in 0..1000u64 {
+            writer.insert(x, x);
+            if x % 10 == 9 {
+                writer.publish();
+            }
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
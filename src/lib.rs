@@ -1,3 +1,76 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
+
+extern crate alloc;
+
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "aes-hash")]
+pub mod aes_hash;
+pub mod any_map;
+#[cfg(feature = "std")]
+pub mod bench;
+pub mod bimap;
+pub mod builder;
+mod control;
+pub mod counter;
+pub mod cuckoo;
+#[cfg(feature = "std")]
+pub mod dashmap;
+pub mod entry;
+pub mod equivalent;
+mod features;
+pub mod frozen;
 pub mod fx_build_hasher;
+pub mod growth;
+#[cfg(all(test, feature = "hash-quality"))]
+mod hash_quality;
 pub mod hashmap;
+pub mod hashset;
+pub mod hopscotch;
+pub mod incremental;
+pub mod index_map;
+pub mod index_set;
+pub mod interner;
+pub mod lazy;
+#[cfg(feature = "std")]
+pub mod left_right;
+pub mod linked_map;
+pub mod lru;
 mod map_entry;
+pub mod memo;
+pub mod miss_cache;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod multimap;
+pub mod no_hash;
+#[cfg(feature = "std")]
+pub mod once_map;
+pub mod packed;
+#[cfg(feature = "rayon")]
+pub mod par;
+pub mod persistent;
+mod positions;
+pub mod prehashed;
+pub mod prelude;
+pub mod raw_entry;
+#[cfg(feature = "rkyv")]
+pub mod rkyv;
+#[cfg(feature = "secure")]
+pub mod secure;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "serde")]
+pub mod serde_seq;
+pub mod slot_map;
+#[cfg(feature = "std")]
+pub mod snapshot;
+pub mod soa;
+pub mod std_compat;
+pub mod traits;
+pub mod ttl;
+#[cfg(feature = "wyhash")]
+pub mod wyhash;
+
+pub use features::{features, Features};
@@ -0,0 +1,382 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::positions::PositionTable;
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    mem,
+};
+
+/// Link to no node, at either end of the list.
+const NIL: usize = usize::MAX;
+
+/// A `LinkedHashMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxLinkedHashMap<K, V> = LinkedHashMap<K, V, FxBuildHasher>;
+
+/// How a `LinkedHashMap` orders its entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// Entries stay where they were first inserted. Overwriting a value doesn't move its entry.
+    Insertion,
+    /// Inserting or getting an entry moves it to the back, so the front is always the least recently used entry.
+    Access,
+}
+
+struct Node<K, V> {
+    hash: usize,
+    key: K,
+    value: V,
+    /// The node in front of this one.
+    prev: usize,
+    /// The node behind this one.
+    next: usize,
+}
+
+/// A map that keeps its entries in a doubly linked list, in insertion or access order (see `Order`), with `pop_front`
+/// and `pop_back` at both ends. Builds bounded FIFO and LRU caches or ordered config maps.
+///
+/// Entries live in a dense vector and the list is threaded through them as `prev`/`next` positions, so there is no
+/// allocation per node: the vector and a Robin Hood table of positions are the only allocations.
+pub struct LinkedHashMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    nodes: Vec<Node<K, V>>,
+    /// Finds the position in `nodes` of a key.
+    positions: PositionTable,
+    /// The front of the list, `NIL` when empty.
+    head: usize,
+    /// The back of the list, `NIL` when empty.
+    tail: usize,
+    order: Order,
+    hasher_builder: H,
+}
+
+impl<K: Hash + Eq, V> LinkedHashMap<K, V, FxBuildHasher> {
+    /// Creates a `LinkedHashMap` in insertion order with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher_and_order(FxBuildHasher::new(), Order::Insertion)
+    }
+
+    /// Creates a `LinkedHashMap` in access order with the default Fx Hasher.
+    pub fn with_access_order() -> Self {
+        Self::with_hasher_and_order(FxBuildHasher::new(), Order::Access)
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> Default for LinkedHashMap<K, V, H> {
+    fn default() -> Self {
+        Self::with_hasher_and_order(H::default(), Order::Insertion)
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> LinkedHashMap<K, V, H> {
+    /// Creates a `LinkedHashMap` with a custom hasher builder and order.
+    pub fn with_hasher_and_order(hasher_builder: H, order: Order) -> Self {
+        Self {
+            nodes: Vec::new(),
+            positions: PositionTable::new(),
+            head: NIL,
+            tail: NIL,
+            order,
+            hasher_builder,
+        }
+    }
+
+    /// Gets the order the map keeps its entries in.
+    pub fn order(&self) -> Order {
+        self.order
+    }
+
+    /// Inserts a key value pair. New keys go to the back. An existing key has its value replaced, and moves to the
+    /// back in access order only. Returns the previous value, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hasher_builder.hash_one(&key) as usize;
+        if let Some(slot) = self.find_slot(hash, &key) {
+            let i = self.positions.position(slot);
+            self.touch(i);
+            return Some(mem::replace(&mut self.nodes[i].value, value));
+        }
+
+        let i = self.nodes.len();
+        self.nodes.push(Node {
+            hash,
+            key,
+            value,
+            prev: NIL,
+            next: NIL,
+        });
+        self.positions.insert(hash, i);
+        self.push_back(i);
+        None
+    }
+
+    /// Gets the value associated with the key, moving its entry to the back in access order.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&V> {
+        let i = self.find(key)?;
+        self.touch(i);
+        Some(&self.nodes[i].value)
+    }
+
+    /// Gets a mutable reference to the value associated with the key, moving its entry to the back in access order.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&mut V> {
+        let i = self.find(key)?;
+        self.touch(i);
+        Some(&mut self.nodes[i].value)
+    }
+
+    /// Gets the value associated with the key without moving its entry, whatever the order.
+    pub fn peek<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        self.find(key).map(|i| &self.nodes[i].value)
+    }
+
+    /// Checks to see if the provided key is associated with any value, without moving its entry.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.find(key).is_some()
+    }
+
+    /// Removes the entry with the given key and returns its value, if there was one.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        let slot = self.find_slot(hash, key)?;
+        Some(self.remove_slot(slot).1)
+    }
+
+    /// Gets the entry at the front: the oldest in insertion order, the least recently used in access order.
+    pub fn front(&self) -> Option<(&K, &V)> {
+        self.nodes
+            .get(self.head)
+            .map(|node| (&node.key, &node.value))
+    }
+
+    /// Gets the entry at the back: the newest in insertion order, the most recently used in access order.
+    pub fn back(&self) -> Option<(&K, &V)> {
+        self.nodes
+            .get(self.tail)
+            .map(|node| (&node.key, &node.value))
+    }
+
+    /// Removes and returns the entry at the front.
+    pub fn pop_front(&mut self) -> Option<(K, V)> {
+        self.pop_at(self.head)
+    }
+
+    /// Removes and returns the entry at the back.
+    pub fn pop_back(&mut self) -> Option<(K, V)> {
+        self.pop_at(self.tail)
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.positions.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+
+    /// Gets the length / number of entries of the map.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Iterates over every key value pair from the front to the back, without moving any of them.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            nodes: &self.nodes,
+            front: self.head,
+            back: self.tail,
+            remaining: self.nodes.len(),
+        }
+    }
+
+    /// Iterates over every key from the front to the back.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterates over every value from the front to the back.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+
+    fn find<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<usize> {
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        self.find_slot(hash, key)
+            .map(|slot| self.positions.position(slot))
+    }
+
+    fn find_slot<Q: ?Sized + Equivalent<K>>(&self, hash: usize, key: &Q) -> Option<usize> {
+        let nodes = &self.nodes;
+        self.positions.find(hash, |i| key.equivalent(&nodes[i].key))
+    }
+
+    /// Moves a node that was just used to the back, in access order.
+    fn touch(&mut self, i: usize) {
+        if self.order == Order::Access && self.tail != i {
+            self.unlink(i);
+            self.push_back(i);
+        }
+    }
+
+    fn pop_at(&mut self, i: usize) -> Option<(K, V)> {
+        let hash = self.nodes.get(i)?.hash;
+        let slot = self.positions.slot_of(hash, i);
+        Some(self.remove_slot(slot))
+    }
+
+    /// Removes the node in the given table slot, filling its hole in `nodes` with the last node.
+    fn remove_slot(&mut self, slot: usize) -> (K, V) {
+        let i = self.positions.position(slot);
+        self.positions.remove(slot);
+        self.unlink(i);
+
+        let last = self.nodes.len() - 1;
+        if i != last {
+            // The last node is about to move into the hole, so everything pointing at it has to point there instead.
+            let moved = self.positions.slot_of(self.nodes[last].hash, last);
+            self.positions.set_position(moved, i);
+            let (prev, next) = (self.nodes[last].prev, self.nodes[last].next);
+            match prev {
+                NIL => self.head = i,
+                prev => self.nodes[prev].next = i,
+            }
+            match next {
+                NIL => self.tail = i,
+                next => self.nodes[next].prev = i,
+            }
+        }
+
+        let node = self.nodes.swap_remove(i);
+        (node.key, node.value)
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let (prev, next) = (self.nodes[i].prev, self.nodes[i].next);
+        match prev {
+            NIL => self.head = next,
+            prev => self.nodes[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.nodes[next].prev = prev,
+        }
+    }
+
+    fn push_back(&mut self, i: usize) {
+        self.nodes[i].next = NIL;
+        self.nodes[i].prev = self.tail;
+        match self.tail {
+            NIL => self.head = i,
+            tail => self.nodes[tail].next = i,
+        }
+        self.tail = i;
+    }
+}
+
+/// An iterator over the entries of a `LinkedHashMap` in list order, created by `LinkedHashMap::iter`.
+pub struct Iter<'a, K, V> {
+    nodes: &'a [Node<K, V>],
+    front: usize,
+    back: usize,
+    /// Stops the two ends from walking past each other.
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let node = &self.nodes[self.front];
+        self.front = node.next;
+        self.remaining -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let node = &self.nodes[self.back];
+        self.back = node.prev;
+        self.remaining -= 1;
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+/// Formats the map like the std maps do (`{key: value, ...}`), from the front to the back.
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone> Debug for LinkedHashMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> Extend<(K, V)> for LinkedHashMap<K, V, H> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        for (key, value) in pairs {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> FromIterator<(K, V)>
+    for LinkedHashMap<K, V, H>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let mut map = Self::default();
+        map.extend(pairs);
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_insertion_order() {
+        let mut map: FxLinkedHashMap<&str, i32> =
+            vec![("b", 1), ("a", 2), ("c", 3)].into_iter().collect();
+        assert_eq!(map.insert("b", 10), Some(1));
+        assert_eq!(map.get(&"a"), Some(&2));
+
+        assert!(map.keys().copied().eq(["b", "a", "c"]));
+        assert!(map.keys().rev().copied().eq(["c", "a", "b"]));
+        assert_eq!(map.pop_front(), Some(("b", 10)));
+        assert_eq!(map.pop_back(), Some(("c", 3)));
+        assert_eq!(map.front(), map.back());
+        assert_eq!(map.remove(&"a"), Some(2));
+        assert_eq!(map.pop_front(), None);
+    }
+
+    #[test]
+    fn it_moves_used_entries_back_in_access_order() {
+        let mut map = FxLinkedHashMap::with_access_order();
+        for x in 0..5 {
+            map.insert(x, x);
+        }
+        map.get(&1);
+        map.insert(0, 0);
+        assert_eq!(map.peek(&2), Some(&2));
+
+        assert!(map.keys().copied().eq([2, 3, 4, 1, 0]));
+        assert_eq!(map.front(), Some((&2, &2)));
+        assert_eq!(map.iter().len(), 5);
+    }
+}
@@ -0,0 +1,209 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::linked_map::{LinkedHashMap, Order};
+use alloc::boxed::Box;
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+};
+
+/// A `LruCache` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxLruCache<K, V> = LruCache<K, V, FxBuildHasher>;
+
+/// A cache holding at most `capacity` entries, evicting the least recently used one to make room for a new key.
+///
+/// Built on a `LinkedHashMap` in access order, whose recency list is threaded through the entries themselves, so
+/// there is no allocation per node.
+pub struct LruCache<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    /// The front is the least recently used entry, the back the most recently used one.
+    map: LinkedHashMap<K, V, H>,
+    capacity: usize,
+    /// Called with every entry evicted to make room, never for entries removed or overwritten explicitly. `Send` so
+    /// the cache can still move between threads, e.g. behind a `Mutex`.
+    on_evict: Option<Box<dyn FnMut(K, V) + Send>>,
+}
+
+impl<K: Hash + Eq, V> LruCache<K, V, FxBuildHasher> {
+    /// Creates a `LruCache` with the default Fx Hasher that holds at most `capacity` entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_hasher(capacity, FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> LruCache<K, V, H> {
+    /// Creates a `LruCache` with a custom hasher builder which overrides the default fx hasher.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn with_hasher(capacity: usize, hasher_builder: H) -> Self {
+        assert!(capacity > 0, "LruCache capacity must be at least 1");
+        Self {
+            map: LinkedHashMap::with_hasher_and_order(hasher_builder, Order::Access),
+            capacity,
+            on_evict: None,
+        }
+    }
+
+    /// Calls `f` with every entry the cache evicts to make room, e.g. to write it back to slower storage. Replaces any
+    /// previous callback.
+    pub fn set_on_evict<F: FnMut(K, V) + Send + 'static>(&mut self, f: F) {
+        self.on_evict = Some(Box::new(f));
+    }
+
+    /// Inserts a key value pair as the most recently used entry, evicting the least recently used entry if the cache
+    /// is full. Returns the previous value of the key, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if self.map.len() == self.capacity && !self.map.contains_key(&key) {
+            self.evict_lru();
+        }
+
+        self.map.insert(key, value)
+    }
+
+    /// Gets the value associated with the key and marks it as the most recently used entry.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    /// Gets a mutable reference to the value associated with the key and marks it as the most recently used entry.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&mut V> {
+        self.map.get_mut(key)
+    }
+
+    /// Gets the value associated with the key without changing how recently it was used.
+    pub fn peek<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        self.map.peek(key)
+    }
+
+    /// Checks to see if the provided key is cached, without changing how recently it was used.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Removes the entry with the given key and returns its value, if there was one.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        self.map.remove(key)
+    }
+
+    /// Removes and returns the least recently used entry.
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        self.map.pop_front()
+    }
+
+    /// Changes the maximum number of entries, evicting least recently used entries until the cache fits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        assert!(capacity > 0, "LruCache capacity must be at least 1");
+        self.capacity = capacity;
+        while self.map.len() > capacity {
+            self.evict_lru();
+        }
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later. Nothing is reported as evicted.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Gets the number of cached entries.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the cache holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Gets the maximum number of entries the cache holds.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Iterates over every entry from the most to the least recently used, without changing how recently any of them
+    /// was used.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.map.iter().rev()
+    }
+
+    /// Pops the least recently used entry and hands it to the eviction callback.
+    fn evict_lru(&mut self) {
+        if let Some((key, value)) = self.pop_lru() {
+            if let Some(on_evict) = self.on_evict.as_mut() {
+                on_evict(key, value);
+            }
+        }
+    }
+}
+
+/// Formats the cache like the std maps do (`{key: value, ...}`), from the most to the least recently used entry.
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone> Debug for LruCache<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    #[test]
+    fn it_evicts_the_least_recently_used_entry() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut cache = FxLruCache::new(3);
+        let log = Arc::clone(&evicted);
+        cache.set_on_evict(move |key, value| log.lock().unwrap().push((key, value)));
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.peek(&"b"), Some(&2));
+        cache.insert("d", 4);
+
+        assert_eq!(*evicted.lock().unwrap(), vec![("b", 2)]);
+        assert!(cache.iter().map(|(k, _)| *k).eq(["d", "a", "c"]));
+        assert_eq!(cache.insert("c", 30), Some(3));
+        assert_eq!(cache.remove(&"a"), Some(1));
+        assert_eq!(cache.pop_lru(), Some(("d", 4)));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(evicted.lock().unwrap().len(), 1);
+
+        // The cache, callback included, can be handed to another thread.
+        let shared = Arc::new(Mutex::new(cache));
+        let inserter = Arc::clone(&shared);
+        thread::spawn(move || inserter.lock().unwrap().insert("e", 5))
+            .join()
+            .unwrap();
+        assert_eq!(shared.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn it_shrinks_to_a_new_capacity() {
+        let mut cache = FxLruCache::new(100);
+        for x in 0..100 {
+            cache.insert(x, x);
+        }
+        for x in (0..100).step_by(3) {
+            *cache.get_mut(&x).unwrap() += 1;
+        }
+        cache.set_capacity(10);
+
+        assert_eq!(cache.len(), 10);
+        assert!(cache.iter().map(|(k, _)| *k).eq((72..100).step_by(3).rev()));
+        assert!(cache.iter().all(|(k, v)| *v == k + 1));
+        assert!(!cache.contains_key(&98));
+    }
+}
@@ -0,0 +1,64 @@
+/// Creates a `RHMap` (with the default Fx Hasher) from a list of key value pairs, e.g. `fxhashmap! { 1 => "a" }`,
+/// reserving room for all of them up front.
+#[macro_export]
+macro_rules! fxhashmap {
+    (@single $($x:tt)*) => (());
+    (@count $($rest:expr),*) => (<[()]>::len(&[$($crate::fxhashmap!(@single $rest)),*]));
+
+    ($($key:expr => $value:expr,)+) => { $crate::fxhashmap!($($key => $value),+) };
+    ($($key:expr => $value:expr),*) => {{
+        let mut map = $crate::hashmap::RHMap::new();
+        map.reserve($crate::fxhashmap!(@count $($key),*));
+        $(
+            map.insert($key, $value);
+        )*
+        map
+    }};
+}
+
+/// Creates a `RHSet` (with the default Fx Hasher) from a list of values, e.g. `fxhashset! { 1, 2, 3 }`, reserving
+/// room for all of them up front.
+#[macro_export]
+macro_rules! fxhashset {
+    ($($value:expr,)+) => { $crate::fxhashset!($($value),+) };
+    ($($value:expr),*) => {{
+        let mut set = $crate::hashset::RHSet::new();
+        set.reserve($crate::fxhashmap!(@count $($value),*));
+        $(
+            set.insert($value);
+        )*
+        set
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{fx_build_hasher::FxBuildHasher, hashmap::RHMap};
+
+    #[test]
+    fn it_builds_maps_from_literals() {
+        let map = fxhashmap! {
+            1 => "a",
+            2 => "b",
+            3 => "c",
+        };
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&1), Some(&"a"));
+        assert_eq!(map.get(&3), Some(&"c"));
+        assert!(map.capacity() >= 3);
+
+        let empty: RHMap<i32, i32, FxBuildHasher> = fxhashmap! {};
+        assert!(empty.is_empty());
+        assert_eq!(empty.capacity(), 0);
+    }
+
+    #[test]
+    fn it_builds_sets_from_literals() {
+        let set = fxhashset! { "a", "b", "a" };
+
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&"b"));
+        assert!(set.capacity() >= 3);
+    }
+}
@@ -0,0 +1,163 @@
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use core::{
+    cell::Cell,
+    hash::{BuildHasher, Hash},
+};
+
+/// A `RHMap` fronted by a small most-recently-used memo of the slots that recent lookups resolved to. Workloads that
+/// keep querying the same handful of keys in a tight loop get answered straight from the memo, skipping hashing and
+/// probing entirely. This is opt-in (wrap your map in it) since every miss pays for the extra key comparisons.
+///
+/// `N` is the number of remembered lookups and defaults to a single entry.
+pub struct MemoMap<K: Hash + Eq, V, H: BuildHasher + Clone, const N: usize = 1> {
+    map: RHMap<K, V, H>,
+    /// Slot indices of the most recent successful lookups, most recent first.
+    recent: Cell<[Option<usize>; N]>,
+}
+
+impl<K: Hash + Eq, V, const N: usize> MemoMap<K, V, FxBuildHasher, N> {
+    /// Creates an empty `MemoMap` backed by a `RHMap` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::from_map(RHMap::new())
+    }
+}
+
+impl<K: Hash + Eq, V, const N: usize> Default for MemoMap<K, V, FxBuildHasher, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone, const N: usize> MemoMap<K, V, H, N> {
+    /// Wraps an existing map. The memo starts out empty.
+    pub fn from_map(map: RHMap<K, V, H>) -> Self {
+        Self {
+            map,
+            recent: Cell::new([None; N]),
+        }
+    }
+
+    /// Gets the value associated with the key, consulting the memo before probing the map.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut recent = self.recent.get();
+
+        for pos in 0..N {
+            if let Some(entry) = recent[pos].and_then(|i| self.map.entry_at(i)) {
+                if entry.key == *key {
+                    // Promote the hit to the front so the memo stays in most-recently-used order.
+                    recent[..=pos].rotate_right(1);
+                    self.recent.set(recent);
+                    return Some(&entry.value);
+                }
+            }
+        }
+
+        let index = self.map.find_index(key)?;
+        if N > 0 {
+            // Evicts the least recently used slot.
+            recent.rotate_right(1);
+            recent[0] = Some(index);
+            self.recent.set(recent);
+        }
+
+        self.map.entry_at(index).map(|entry| &entry.value)
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Inserts a value with its associated key. Since inserting can displace entries or resize the map, the memo is
+    /// forgotten.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.forget();
+        self.map.insert(key, value);
+    }
+
+    /// Deletes the entry with the given key. Returns an `Err` if no such entry with the provided key exists.
+    pub fn remove(&mut self, key: &K) -> Result<(), &'static str> {
+        self.forget();
+        self.map.remove(key)
+    }
+
+    /// Clears all entries (and the memo) but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.forget();
+        self.map.clear();
+    }
+
+    /// Gets the length / number of entries of the underlying map.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the underlying map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Gets a reference to the underlying map.
+    pub fn map(&self) -> &RHMap<K, V, H> {
+        &self.map
+    }
+
+    /// Unwraps the underlying map, discarding the memo.
+    pub fn into_inner(self) -> RHMap<K, V, H> {
+        self.map
+    }
+
+    fn forget(&mut self) {
+        self.recent = Cell::new([None; N]);
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone, const N: usize> From<RHMap<K, V, H>>
+    for MemoMap<K, V, H, N>
+{
+    fn from(map: RHMap<K, V, H>) -> Self {
+        Self::from_map(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_remembers_recent_lookups() {
+        let mut memo: MemoMap<i32, i32, FxBuildHasher, 2> = MemoMap::new();
+        for x in 0..10 {
+            memo.insert(x, x * 10);
+        }
+
+        assert_eq!(memo.get(&3), Some(&30));
+        assert_eq!(memo.get(&4), Some(&40));
+        assert_eq!(memo.recent.get()[0], memo.map().find_index(&4));
+        assert_eq!(memo.recent.get()[1], memo.map().find_index(&3));
+
+        // A hit promotes the slot back to the front and a miss leaves the memo untouched.
+        assert_eq!(memo.get(&3), Some(&30));
+        assert_eq!(memo.get(&42), None);
+        assert_eq!(memo.recent.get()[0], memo.map().find_index(&3));
+
+        // The least recently used slot is evicted once the memo is full.
+        assert_eq!(memo.get(&5), Some(&50));
+        assert_eq!(memo.recent.get()[0], memo.map().find_index(&5));
+        assert_eq!(memo.recent.get()[1], memo.map().find_index(&3));
+    }
+
+    #[test]
+    #[allow(unused_must_use)]
+    fn it_forgets_lookups_on_mutation() {
+        let mut memo: MemoMap<i32, i32, FxBuildHasher> = MemoMap::new();
+        memo.insert(1, 1);
+        assert_eq!(memo.get(&1), Some(&1));
+
+        memo.remove(&1);
+        assert_eq!(memo.recent.get(), [None]);
+        assert_eq!(memo.get(&1), None);
+        assert!(memo.is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use alloc::vec::Vec;
+use core::{
+    cell::{Cell, RefCell},
+    hash::{BuildHasher, Hash},
+};
+
+/// A `RHMap` fronted by a small direct-mapped cache of keys that recent lookups failed to find. Workloads dominated
+/// by repeated misses (e.g. spell-checking words against a dictionary) get those answered after hashing and a single
+/// key comparison, skipping the probe sequence. This is opt-in (wrap your map in it) since every miss pays for
+/// cloning the key into the cache.
+///
+/// `N` is the number of cached misses and defaults to 64.
+pub struct MissCacheMap<K: Hash + Eq + Clone, V, H: BuildHasher + Clone, const N: usize = 64> {
+    map: RHMap<K, V, H>,
+    /// Recently missed keys along with their hashes, each stored at `hash % N`.
+    misses: RefCell<Vec<Option<(usize, K)>>>,
+    /// Number of lookups answered straight from the cache.
+    cache_hits: Cell<u64>,
+}
+
+impl<K: Hash + Eq + Clone, V, const N: usize> MissCacheMap<K, V, FxBuildHasher, N> {
+    /// Creates an empty `MissCacheMap` backed by a `RHMap` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::from_map(RHMap::new())
+    }
+}
+
+impl<K: Hash + Eq + Clone, V, const N: usize> Default for MissCacheMap<K, V, FxBuildHasher, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V, H: BuildHasher + Clone, const N: usize> MissCacheMap<K, V, H, N> {
+    /// Wraps an existing map. The cache starts out empty.
+    pub fn from_map(map: RHMap<K, V, H>) -> Self {
+        Self {
+            map,
+            misses: RefCell::new((0..N).map(|_| None).collect()),
+            cache_hits: Cell::new(0),
+        }
+    }
+
+    /// Gets the value associated with the key, consulting the cache of known misses before probing the map.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let hash = self.map.make_hash(key);
+        if N == 0 {
+            return self.probe(hash, key);
+        }
+
+        let mut misses = self.misses.borrow_mut();
+        let cached = &mut misses[hash % N];
+        if matches!(cached, Some((h, k)) if *h == hash && k == key) {
+            self.cache_hits.set(self.cache_hits.get() + 1);
+            return None;
+        }
+
+        let res = self.probe(hash, key);
+        if res.is_none() {
+            *cached = Some((hash, key.clone()));
+        }
+
+        res
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Inserts a value with its associated key. The key is dropped from the cache of misses if it was in there.
+    pub fn insert(&mut self, key: K, value: V) {
+        if N > 0 {
+            let hash = self.map.make_hash(&key);
+            let cached = &mut self.misses.get_mut()[hash % N];
+            if matches!(cached, Some((h, k)) if *h == hash && *k == key) {
+                *cached = None;
+            }
+        }
+
+        self.map.insert(key, value);
+    }
+
+    /// Deletes the entry with the given key. Returns an `Err` if no such entry with the provided key exists. Removing
+    /// entries can't turn a cached miss into a hit, so the cache is left alone.
+    pub fn remove(&mut self, key: &K) -> Result<(), &'static str> {
+        self.map.remove(key)
+    }
+
+    /// Clears all entries (and the cache) but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.misses
+            .get_mut()
+            .iter_mut()
+            .for_each(|miss| *miss = None);
+        self.map.clear();
+    }
+
+    /// Gets the length / number of entries of the underlying map.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the underlying map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Gets the number of lookups that were answered from the cache of misses without probing.
+    pub fn cache_hits(&self) -> u64 {
+        self.cache_hits.get()
+    }
+
+    /// Gets a reference to the underlying map.
+    pub fn map(&self) -> &RHMap<K, V, H> {
+        &self.map
+    }
+
+    /// Unwraps the underlying map, discarding the cache.
+    pub fn into_inner(self) -> RHMap<K, V, H> {
+        self.map
+    }
+
+    fn probe(&self, hash: usize, key: &K) -> Option<&V> {
+        let index = self.map.probe_hashed(hash, key).0?;
+        self.map.entry_at(index).map(|entry| &entry.value)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V, H: BuildHasher + Clone, const N: usize> From<RHMap<K, V, H>>
+    for MissCacheMap<K, V, H, N>
+{
+    fn from(map: RHMap<K, V, H>) -> Self {
+        Self::from_map(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_answers_repeated_misses_from_the_cache() {
+        let mut words: MissCacheMap<String, usize, FxBuildHasher> = MissCacheMap::new();
+        for (i, word) in ["apple", "banana", "cherry"].iter().enumerate() {
+            words.insert(word.to_string(), i);
+        }
+
+        let typo = "bananna".to_string();
+        assert_eq!(words.get(&typo), None);
+        assert_eq!(words.get(&typo), None);
+        assert!(!words.contains_key(&typo));
+        assert_eq!(words.cache_hits(), 2);
+        assert_eq!(words.get(&"cherry".to_string()), Some(&2));
+
+        // Inserting a cached miss must make it visible again.
+        words.insert(typo.clone(), 3);
+        assert_eq!(words.get(&typo), Some(&3));
+        assert_eq!(words.cache_hits(), 2);
+    }
+}
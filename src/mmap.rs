@@ -0,0 +1,166 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::snapshot::{invalid, FixedSize, Header, HEADER_LEN};
+use memmap2::Mmap;
+use std::{
+    convert::TryInto,
+    fmt::{self, Debug},
+    fs::File,
+    hash::{BuildHasher, Hash},
+    io,
+    marker::PhantomData,
+    path::Path,
+};
+
+/// A `MmapMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type MmapFxMap<K, V> = MmapMap<K, V, FxBuildHasher>;
+
+/// A read-only map served straight from a snapshot file (written by `RHMap::write_to`) mapped into memory. Opening only
+/// validates the header, and lookups probe the mapped records like `RHMap` probes its slots, so a multi-gigabyte table
+/// is ready at once and its pages are shared by every process mapping the same file.
+///
+/// Keys and values are decoded from their fixed size records on access, so `get` hands out values by copy.
+pub struct MmapMap<K: Hash + Eq + FixedSize, V: FixedSize, H: BuildHasher> {
+    mmap: Mmap,
+    header: Header,
+    hasher_builder: H,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K: Hash + Eq + FixedSize, V: FixedSize, H: BuildHasher + Default> MmapMap<K, V, H> {
+    /// Maps the snapshot file at `path`, hashing with a default hasher builder.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified while it is mapped, by this process or any other.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::open_with_hasher(path, H::default())
+    }
+}
+
+impl<K: Hash + Eq + FixedSize, V: FixedSize, H: BuildHasher> MmapMap<K, V, H> {
+    /// Maps the snapshot file at `path`, hashing with the given hasher builder. Fails with `InvalidData` if the file
+    /// isn't a snapshot of this key and value type written with an equivalent hasher, or if it's truncated.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified while it is mapped, by this process or any other.
+    pub unsafe fn open_with_hasher<P: AsRef<Path>>(path: P, hasher_builder: H) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mmap = Mmap::map(&file)?;
+        let header = Header::parse::<K, V, H>(&mmap, &hasher_builder)?;
+        let records_len = header.buckets.checked_mul(header.record_len());
+        if records_len.and_then(|len| len.checked_add(HEADER_LEN)) != Some(mmap.len()) {
+            return Err(invalid("snapshot file length doesn't match its table size"));
+        }
+
+        Ok(Self {
+            mmap,
+            header,
+            hasher_builder,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Gets a copy of the value associated with the key.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<V> {
+        if self.header.buckets == 0 {
+            return None;
+        }
+
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        let mask = self.header.buckets - 1;
+        let mut i = hash & mask;
+        // Bounded by the table size so a corrupt file can't make a lookup spin.
+        for psl in 0..self.header.buckets {
+            let (stored_hash, record_key, value) = self.record(i)?;
+            // Robin Hood order: an entry closer to home than we are means the key would have been placed before it.
+            if (i.wrapping_sub(stored_hash) & mask) < psl {
+                return None;
+            }
+            if stored_hash == hash && key.equivalent(&K::read_le(record_key)) {
+                return Some(V::read_le(value));
+            }
+            i = (i + 1) & mask;
+        }
+        None
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Gets the number of entries of the map.
+    pub fn len(&self) -> usize {
+        self.header.num_items
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.header.num_items == 0
+    }
+
+    /// Iterates over copies of every key value pair, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (K, V)> + '_ {
+        (0..self.header.buckets).filter_map(move |i| {
+            let (_, key, value) = self.record(i)?;
+            Some((K::read_le(key), V::read_le(value)))
+        })
+    }
+
+    /// The stored hash, key bytes and value bytes of the slot at `index`, if it is occupied.
+    fn record(&self, index: usize) -> Option<(usize, &[u8], &[u8])> {
+        let record_len = self.header.record_len();
+        let start = HEADER_LEN + index * record_len;
+        let record = &self.mmap[start..start + record_len];
+        if record[0] == 0 {
+            return None;
+        }
+
+        let hash = u64::from_le_bytes(record[1..9].try_into().unwrap()) as usize;
+        let (key, value) = record[9..].split_at(K::SIZE);
+        Some((hash, key, value))
+    }
+}
+
+/// Shows the size of the mapped table rather than its entries, which could be many gigabytes.
+impl<K: Hash + Eq + FixedSize, V: FixedSize, H: BuildHasher> Debug for MmapMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MmapMap")
+            .field("len", &self.header.num_items)
+            .field("buckets", &self.header.buckets)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn it_serves_lookups_from_a_mapped_snapshot() {
+        let mut map = FxHashMap::with_hasher(FxBuildHasher::deterministic());
+        for x in 0..5_000u32 {
+            map.insert(x, u64::from(x) * 3);
+        }
+        let path = env::temp_dir().join(format!("rhmap-mmap-test-{}.snap", process::id()));
+        map.write_to(&mut fs::File::create(&path).unwrap()).unwrap();
+
+        // SAFETY: the file belongs to this test and isn't modified while mapped.
+        let mapped = unsafe {
+            MmapFxMap::<u32, u64>::open_with_hasher(&path, FxBuildHasher::deterministic())
+        }
+        .unwrap();
+        assert_eq!(mapped.len(), 5_000);
+        assert_eq!(mapped.get(&1_234), Some(3_702));
+        assert_eq!(mapped.get(&5_000), None);
+        assert_eq!(mapped.iter().count(), 5_000);
+        assert!(unsafe { MmapFxMap::<u64, u64>::open(&path) }.is_err());
+
+        drop(mapped);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,295 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use super::raw_entry::RawEntryMut;
+use alloc::{vec, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    mem, slice,
+};
+
+/// A `MultiMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxMultiMap<K, V> = MultiMap<K, V, FxBuildHasher>;
+
+/// The values of one key. Most keys of a multimap only ever get one value, so that one is kept inline in the table
+/// slot and a vector is only allocated once a second value arrives.
+enum Values<V> {
+    One(V),
+    Many(Vec<V>),
+}
+
+impl<V> Values<V> {
+    fn as_slice(&self) -> &[V] {
+        match self {
+            Values::One(value) => slice::from_ref(value),
+            Values::Many(values) => values,
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [V] {
+        match self {
+            Values::One(value) => slice::from_mut(value),
+            Values::Many(values) => values,
+        }
+    }
+
+    fn push(&mut self, value: V) {
+        match self {
+            Values::Many(values) => values.push(value),
+            Values::One(_) => {
+                // Moves the inline value out by briefly parking an empty vector in its place.
+                if let Values::One(first) = mem::replace(self, Values::Many(Vec::new())) {
+                    *self = Values::Many(vec![first, value]);
+                }
+            }
+        }
+    }
+
+    fn into_vec(self) -> Vec<V> {
+        match self {
+            Values::One(value) => vec![value],
+            Values::Many(values) => values,
+        }
+    }
+}
+
+/// A map from each key to a list of values, in the order they were inserted. Saves the `RHMap<K, Vec<V>>` dance of
+/// `entry(...).or_default().push(...)`, and keys with a single value don't allocate a vector at all.
+pub struct MultiMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    map: RHMap<K, Values<V>, H>,
+    /// Number of values across all keys.
+    num_values: usize,
+}
+
+impl<K: Hash + Eq, V> MultiMap<K, V, FxBuildHasher> {
+    /// Creates a `MultiMap` with the default Fx Hasher and an initial capacity of 0.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> Default for MultiMap<K, V, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> MultiMap<K, V, H> {
+    /// Groups the items by the key `key_fn` derives from each, in one pass. Every key lists its items in iteration
+    /// order.
+    pub fn from_grouping<I: IntoIterator<Item = V>, F: FnMut(&V) -> K>(
+        items: I,
+        mut key_fn: F,
+    ) -> Self {
+        let mut map = Self::default();
+        for item in items {
+            map.insert(key_fn(&item), item);
+        }
+        map
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> MultiMap<K, V, H> {
+    /// Creates a `MultiMap` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            map: RHMap::with_hasher(hasher_builder),
+            num_values: 0,
+        }
+    }
+
+    /// Appends a value to the list of the given key.
+    pub fn insert(&mut self, key: K, value: V) {
+        match self.map.raw_entry_mut().from_key(&key) {
+            RawEntryMut::Occupied(mut entry) => entry.get_mut().push(value),
+            RawEntryMut::Vacant(entry) => {
+                entry.insert(key, Values::One(value));
+            }
+        }
+        self.num_values += 1;
+    }
+
+    /// Gets every value of the given key in insertion order, an empty slice if it has none.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> &[V] {
+        self.map.get(key).map_or(&[], Values::as_slice)
+    }
+
+    /// Gets mutable references to every value of the given key, an empty slice if it has none.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> &mut [V] {
+        self.map.get_mut(key).map_or(&mut [], Values::as_mut_slice)
+    }
+
+    /// Checks to see if the given key has any values.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Removes the first value of the given key equal to `value`, and the key itself once it has no values left.
+    /// Returns `true` if a value was removed.
+    pub fn remove_one<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q, value: &V) -> bool
+    where
+        V: PartialEq,
+    {
+        let values = match self.map.get_mut(key) {
+            Some(values) => values,
+            None => return false,
+        };
+        let removed = match values {
+            Values::One(only) if only == value => {
+                self.map.remove(key).ok();
+                true
+            }
+            Values::One(_) => false,
+            Values::Many(list) => match list.iter().position(|candidate| candidate == value) {
+                Some(i) => {
+                    list.remove(i);
+                    if list.is_empty() {
+                        self.map.remove(key).ok();
+                    }
+                    true
+                }
+                None => false,
+            },
+        };
+
+        if removed {
+            self.num_values -= 1;
+        }
+        removed
+    }
+
+    /// Removes the given key and returns all of its values in insertion order, an empty vector if it had none.
+    pub fn remove_all<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Vec<V> {
+        let values = self
+            .map
+            .remove_entry(key)
+            .map_or_else(Vec::new, |(_, values)| values.into_vec());
+        self.num_values -= values.len();
+        values
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.num_values = 0;
+    }
+
+    /// Gets the number of distinct keys.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Gets the number of values across all keys.
+    pub fn total_len(&self) -> usize {
+        self.num_values
+    }
+
+    /// Returns `true` if the map contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Iterates over every key in arbitrary order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.map.keys()
+    }
+
+    /// Iterates over every key along with all of its values, keys in arbitrary order.
+    pub fn iter_all(&self) -> impl Iterator<Item = (&K, &[V])> + '_ {
+        self.map
+            .iter()
+            .map(|(key, values)| (key, values.as_slice()))
+    }
+
+    /// Iterates over every key value pair, once per value.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.iter_all()
+            .flat_map(|(key, values)| values.iter().map(move |value| (key, value)))
+    }
+}
+
+/// Formats the map like the std maps do, listing every key with all of its values (`{key: [value, ...], ...}`).
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone> Debug for MultiMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter_all()).finish()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> Extend<(K, V)> for MultiMap<K, V, H> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        for (key, value) in pairs {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> FromIterator<(K, V)> for MultiMap<K, V, H> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let mut map = Self::default();
+        map.extend(pairs);
+        map
+    }
+}
+
+/// Adds `group_by` to every iterator, collecting its items into an `FxMultiMap` keyed by a derived key.
+pub trait GroupBy: Iterator + Sized {
+    /// Groups the items by the key `key_fn` derives from each, like `FxMultiMap::from_grouping`.
+    fn group_by<K: Hash + Eq, F: FnMut(&Self::Item) -> K>(
+        self,
+        key_fn: F,
+    ) -> FxMultiMap<K, Self::Item> {
+        MultiMap::from_grouping(self, key_fn)
+    }
+}
+
+impl<I: Iterator> GroupBy for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_every_value_of_a_key() {
+        let mut edges: FxMultiMap<&str, &str> = vec![("a", "b"), ("a", "c"), ("b", "c")]
+            .into_iter()
+            .collect();
+        edges.insert("a", "d");
+
+        assert_eq!(edges.get(&"a"), &["b", "c", "d"]);
+        assert_eq!(edges.get(&"b"), &["c"]);
+        assert!(edges.get(&"c").is_empty());
+        assert_eq!(edges.len(), 2);
+        assert_eq!(edges.total_len(), 4);
+        assert_eq!(edges.iter().count(), 4);
+        edges.get_mut(&"b")[0] = "e";
+        assert_eq!(edges.get(&"b"), &["e"]);
+    }
+
+    #[test]
+    fn it_removes_one_or_all_values() {
+        let mut map = FxMultiMap::new();
+        map.extend(vec![(1, 10), (1, 11), (2, 20)]);
+
+        assert!(map.remove_one(&1, &10));
+        assert!(!map.remove_one(&1, &10));
+        assert!(map.remove_one(&2, &20));
+        assert!(!map.contains_key(&2));
+        assert_eq!(map.remove_all(&1), vec![11]);
+        assert!(map.remove_all(&1).is_empty());
+        assert!(map.is_empty());
+        assert_eq!(map.total_len(), 0);
+    }
+
+    #[test]
+    fn it_groups_items_by_a_derived_key() {
+        let words = vec!["apple", "avocado", "banana", "blueberry", "cherry"];
+        let by_letter = words.iter().copied().group_by(|word| word.as_bytes()[0]);
+
+        assert_eq!(by_letter.get(&b'b'), &["banana", "blueberry"]);
+        assert_eq!(by_letter.len(), 3);
+        let by_len = FxMultiMap::from_grouping(words, |word| word.len());
+        assert_eq!(by_len.get(&6), &["banana", "cherry"]);
+    }
+}
@@ -0,0 +1,106 @@
+use super::hashmap::RHMap;
+use core::hash::{BuildHasher, Hasher};
+
+/// A `BuildHasher` whose hashers pass integer keys through unchanged, for keys that are already well distributed
+/// (entity ids, pre-hashed values) so they skip hashing entirely.
+///
+/// Slots are picked with the low bits of the hash and control bytes hold the top bits, so keys should vary in both.
+/// Sequential ids fill the table evenly, but their top bits are all zero, so lookups fall back to comparing keys
+/// within a probe.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoHashBuilder;
+
+impl BuildHasher for NoHashBuilder {
+    type Hasher = NoHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        NoHasher(0)
+    }
+}
+
+/// The hasher built by `NoHashBuilder`. Takes a single integer and hands it back as the hash.
+///
+/// # Panics
+///
+/// Panics when fed anything but an integer, e.g. a string or a tuple (which writes each field), since passing those
+/// through would silently throw most of the key away.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoHasher(u64);
+
+impl Hasher for NoHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {
+        panic!("NoHasher only hashes single integer keys");
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.0 = i as u64;
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.0 = i as u64;
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.0 = i as u64;
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0 = i;
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.0 = i as u64;
+    }
+
+    fn write_i8(&mut self, i: i8) {
+        self.0 = i as u64;
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.0 = i as u64;
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.0 = i as u64;
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.0 = i as u64;
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.0 = i as u64;
+    }
+}
+
+/// A `RHMap` keyed by `u64` ids that are used as their own hash. Create one with `IntFxHashMap::default()`. Other
+/// integer key types work the same way with `RHMap<K, V, NoHashBuilder>`.
+pub type IntFxHashMap<V> = RHMap<u64, V, NoHashBuilder>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_uses_integer_keys_as_their_hash() {
+        let mut map: IntFxHashMap<&str> = IntFxHashMap::default();
+        for id in 0..100 {
+            map.insert(id, "entity");
+        }
+
+        assert_eq!(NoHashBuilder.hash_one(42u32), 42);
+        assert_eq!(NoHashBuilder.hash_one(-1i8), u64::MAX);
+        assert_eq!(map.get(&42), Some(&"entity"));
+        assert_eq!(map.len(), 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "single integer keys")]
+    fn it_refuses_non_integer_keys() {
+        NoHashBuilder.hash_one("not an id");
+    }
+}
@@ -0,0 +1,170 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use super::raw_entry::RawEntryMut;
+use std::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+};
+
+/// A `OnceMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxOnceMap<K, V> = OnceMap<K, V, FxBuildHasher>;
+
+/// A concurrent memoization map: `get_or_init` runs the initializer of each key at most once across all threads and
+/// hands out a `&V` that stays valid for as long as the map is borrowed.
+///
+/// Every value lives in its own heap cell that entries only ever point to, so the table can grow under a short lock
+/// without moving a value anyone holds a reference to. Values can only be removed through `&mut self`, once no such
+/// reference can be left. Initializers run outside the lock, so slow ones only hold up threads asking for the same
+/// key.
+pub struct OnceMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    map: Mutex<RHMap<K, Arc<OnceLock<V>>, H>>,
+}
+
+impl<K: Hash + Eq, V> OnceMap<K, V, FxBuildHasher> {
+    /// Creates an empty `OnceMap` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> Default for OnceMap<K, V, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> OnceMap<K, V, H> {
+    /// Creates an empty `OnceMap` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            map: Mutex::new(RHMap::with_hasher(hasher_builder)),
+        }
+    }
+
+    /// Gets the value of the key, running `init` to produce it if no thread has yet. Threads asking for a key that is
+    /// being initialized wait for that initializer instead of running their own.
+    ///
+    /// If `init` panics, the key stays uninitialized and the next caller runs its own initializer.
+    pub fn get_or_init<F: FnOnce() -> V>(&self, key: K, init: F) -> &V {
+        let cell = {
+            let mut map = self.lock();
+            match map.raw_entry_mut().from_key(&key) {
+                RawEntryMut::Occupied(entry) => entry.get().clone(),
+                RawEntryMut::Vacant(entry) => entry.insert(key, Arc::default()).1.clone(),
+            }
+        };
+
+        // SAFETY: the map holds on to the cell until an entry is removed, which takes `&mut self`.
+        let cell = unsafe { &*Arc::as_ptr(&cell) };
+        cell.get_or_init(init)
+    }
+
+    /// Gets the value of the key, if it has been initialized.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        let map = self.lock();
+        let cell = Arc::as_ptr(map.get(key)?);
+        // SAFETY: as in `get_or_init`.
+        unsafe { &*cell }.get()
+    }
+
+    /// Checks to see if the key has an initialized value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Gets the number of initialized values.
+    pub fn len(&self) -> usize {
+        self.lock()
+            .values()
+            .filter(|cell| cell.get().is_some())
+            .count()
+    }
+
+    /// Returns `true` if no value has been initialized.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes the entry with the given key and returns its value, if it was initialized.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        let (_, cell) = self.map_mut().remove_entry(key)?;
+        Arc::try_unwrap(cell).ok()?.into_inner()
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.map_mut().clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RHMap<K, Arc<OnceLock<V>>, H>> {
+        self.map
+            .lock()
+            .expect("a thread panicked while holding the OnceMap lock")
+    }
+
+    fn map_mut(&mut self) -> &mut RHMap<K, Arc<OnceLock<V>>, H> {
+        self.map
+            .get_mut()
+            .expect("a thread panicked while holding the OnceMap lock")
+    }
+}
+
+/// Formats the map like the std maps do (`{key: value, ...}`), skipping keys whose value is still being initialized.
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone> Debug for OnceMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let map = self.lock();
+        f.debug_map()
+            .entries(
+                map.iter()
+                    .filter_map(|(key, cell)| Some((key, cell.get()?))),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn it_initializes_each_key_once_across_threads() {
+        let map = FxOnceMap::new();
+        let runs = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for x in 0..100u64 {
+                        let value = map.get_or_init(x, || {
+                            runs.fetch_add(1, Ordering::SeqCst);
+                            x * x
+                        });
+                        assert_eq!(*value, x * x);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(runs.load(Ordering::SeqCst), 100);
+        assert_eq!(map.len(), 100);
+    }
+
+    #[test]
+    fn it_keeps_references_stable_while_growing() {
+        let mut map = FxOnceMap::new();
+        let first = map.get_or_init(0, || "zero".to_string());
+        for x in 1..1000 {
+            map.get_or_init(x, || x.to_string());
+        }
+        assert_eq!(first, "zero");
+        assert_eq!(map.get(&999).map(String::as_str), Some("999"));
+
+        assert_eq!(map.remove(&0), Some("zero".to_string()));
+        assert!(!map.contains_key(&0));
+        assert_eq!(map.len(), 999);
+    }
+}
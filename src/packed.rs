@@ -0,0 +1,389 @@
+use super::fx_build_hasher::FxBuildHasher;
+use alloc::{vec, vec::Vec};
+use core::{
+    cmp::min,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
+
+const INITIAL_SIZE: usize = 8;
+
+/// Small plain-old-data types that can be packed into a few bits of a machine word.
+pub trait Pod: Copy + Eq + Hash {
+    /// How many bits the packed form takes up.
+    const BITS: u32;
+
+    /// Packs the value into the low `BITS` bits of a word.
+    fn to_bits(self) -> u64;
+
+    /// Unpacks a value from the low `BITS` bits of a word.
+    fn from_bits(bits: u64) -> Self;
+}
+
+macro_rules! impl_pod {
+    ($($t:ty => $unsigned:ty),*) => {
+        $(
+            impl Pod for $t {
+                const BITS: u32 = <$unsigned>::BITS;
+
+                fn to_bits(self) -> u64 {
+                    self as $unsigned as u64
+                }
+
+                fn from_bits(bits: u64) -> Self {
+                    bits as $unsigned as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_pod!(u8 => u8, u16 => u16, u32 => u32, i8 => u8, i16 => u16, i32 => u32);
+
+/// Robinhood HashMap for small `Copy` keys and values (e.g. `u32` -> `u32` id mappings) that packs every key value
+/// pair into a single `u64` slot, about half the memory a `RHMap` slot takes for the same types (8 bytes against 17
+/// for `u32` keys and values).
+///
+/// Slot layout, from the low bits up: the key, the value and then whatever bits are left over as metadata. The
+/// metadata caches the entry's PSL (saturating); when it is saturated or there are no spare bits at all, the PSL is
+/// recomputed from the key's hash, which is a single multiply for integer keys under Fx. Vacant slots are marked by
+/// a key with all bits set, so that one key is kept in a side slot instead of the table.
+pub struct PackedMap<K: Pod, V: Pod, H: BuildHasher + Clone> {
+    slots: Vec<u64>,
+    /// The value stored under the all-ones key, which can't live in `slots` since it marks vacant slots.
+    sentinel_value: Option<V>,
+    hasher_builder: H,
+    num_items: usize,
+    _marker: PhantomData<K>,
+}
+
+impl<K: Pod, V: Pod> PackedMap<K, V, FxBuildHasher> {
+    /// Creates a `PackedMap` with the default Fx Hasher and an initial capacity of 0.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Pod, V: Pod> Default for PackedMap<K, V, FxBuildHasher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Pod, V: Pod, H: BuildHasher + Clone> PackedMap<K, V, H> {
+    /// Fails to compile if the pair doesn't fit into a single word.
+    const FITS: () = assert!(
+        K::BITS + V::BITS <= 64,
+        "key and value don't fit into a single u64 slot"
+    );
+    const KEY_MASK: u64 = u64::MAX >> (64 - K::BITS);
+    const VALUE_MASK: u64 = u64::MAX >> (64 - V::BITS);
+    const META_SHIFT: u32 = K::BITS + V::BITS;
+    /// The largest PSL the spare bits can hold, 0 if there are no spare bits.
+    const META_MAX: u64 = match Self::META_SHIFT {
+        64 => 0,
+        shift => u64::MAX >> shift,
+    };
+    /// A vacant slot is one whose key bits are all set.
+    const VACANT: u64 = Self::KEY_MASK;
+
+    /// Creates a `PackedMap` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS;
+
+        Self {
+            slots: Vec::new(),
+            sentinel_value: None,
+            hasher_builder,
+            num_items: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Inserts a value with its associated key into the map, overwriting any previous value.
+    pub fn insert(&mut self, key: K, value: V) {
+        if key.to_bits() == Self::KEY_MASK {
+            if self.sentinel_value.replace(value).is_none() {
+                self.num_items += 1;
+            }
+            return;
+        }
+
+        if self.slots.is_empty() || self.table_items() >= self.capacity() {
+            self.resize();
+        }
+
+        if self.insert_word(key.to_bits() | value.to_bits() << K::BITS) {
+            self.num_items += 1;
+        }
+    }
+
+    /// Gets the value associated with the key. Values are handed out by copy since they only exist packed.
+    pub fn get(&self, key: &K) -> Option<V> {
+        if key.to_bits() == Self::KEY_MASK {
+            return self.sentinel_value;
+        }
+
+        self.find_index(key).map(|i| self.unpack(self.slots[i]).1)
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Deletes the entry with the given key. Returns an `Err` if no such entry with the provided key exists.
+    pub fn remove(&mut self, key: &K) -> Result<(), &'static str> {
+        if key.to_bits() == Self::KEY_MASK {
+            return match self.sentinel_value.take() {
+                Some(_) => {
+                    self.num_items -= 1;
+                    Ok(())
+                }
+                None => Err("Entry not found"),
+            };
+        }
+
+        let mut i = self.find_index(key).ok_or("Entry not found")?;
+        let mask = self.slots.len() - 1;
+
+        // Backward shift deletion: pull the rest of the cluster one slot closer to home until we hit a vacancy or an
+        // entry that already sits in its home slot.
+        loop {
+            let next = (i + 1) & mask;
+            let word = self.slots[next];
+            if word & Self::KEY_MASK == Self::VACANT || self.psl(word, next) == 0 {
+                break;
+            }
+
+            let psl = self.psl(word, next);
+            self.slots[i] = self.with_psl(word, psl - 1);
+            i = next;
+        }
+
+        self.slots[i] = Self::VACANT;
+        self.num_items -= 1;
+        Ok(())
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = Self::VACANT);
+        self.sentinel_value = None;
+        self.num_items = 0;
+    }
+
+    /// Gets the length / number of entries of the map.
+    pub fn len(&self) -> usize {
+        self.num_items
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+
+    /// Gets the number of entries the map can hold before it has to grow: the bucket count scaled by the 0.75 load
+    /// factor.
+    pub fn capacity(&self) -> usize {
+        3 * self.slots.len() / 4
+    }
+
+    /// Gets the number of slots in the table, occupied or not.
+    pub fn bucket_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Iterates over copies of every key value pair in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (K, V)> + '_ {
+        self.slots
+            .iter()
+            .filter(|&&word| word & Self::KEY_MASK != Self::VACANT)
+            .map(move |&word| self.unpack(word))
+            .chain(
+                self.sentinel_value
+                    .map(|value| (K::from_bits(Self::KEY_MASK), value)),
+            )
+    }
+
+    fn find_index(&self, key: &K) -> Option<usize> {
+        if self.slots.is_empty() {
+            return None;
+        }
+
+        let mask = self.slots.len() - 1;
+        let key_bits = key.to_bits();
+        let mut i = self.home(key);
+        let mut psl = 0;
+
+        loop {
+            let word = self.slots[i];
+            if word & Self::KEY_MASK == Self::VACANT {
+                return None;
+            }
+            if word & Self::KEY_MASK == key_bits {
+                return Some(i);
+            }
+            // The robinhood invariant means our key would have evicted this richer entry, so it isn't here.
+            if psl > self.psl(word, i) {
+                return None;
+            }
+
+            psl += 1;
+            i = (i + 1) & mask;
+        }
+    }
+
+    /// Places a packed key value pair (without metadata) and returns `true` if it wasn't already present.
+    fn insert_word(&mut self, mut word: u64) -> bool {
+        let mask = self.slots.len() - 1;
+        let mut i = self.home(&K::from_bits(word & Self::KEY_MASK));
+        let mut psl = 0;
+        // Once the word we were given has been placed we're only carrying evicted entries, which can't be updates.
+        let mut displaced = false;
+
+        loop {
+            let cur = self.slots[i];
+            if cur & Self::KEY_MASK == Self::VACANT {
+                self.slots[i] = self.with_psl(word, psl);
+                return true;
+            }
+
+            if !displaced && cur & Self::KEY_MASK == word & Self::KEY_MASK {
+                self.slots[i] = self.with_psl(word, psl);
+                return false;
+            }
+
+            let cur_psl = self.psl(cur, i);
+            if psl > cur_psl {
+                self.slots[i] = self.with_psl(word, psl);
+                word = cur;
+                psl = cur_psl;
+                displaced = true;
+            }
+
+            psl += 1;
+            i = (i + 1) & mask;
+        }
+    }
+
+    fn resize(&mut self) {
+        let target_size = match self.slots.len() {
+            0 => INITIAL_SIZE,
+            n => 2 * n,
+        };
+
+        let old = core::mem::replace(&mut self.slots, vec![Self::VACANT; target_size]);
+        for word in old {
+            if word & Self::KEY_MASK != Self::VACANT {
+                self.insert_word(word & Self::pair_mask());
+            }
+        }
+    }
+
+    /// The PSL of the occupied slot at index `i`, read from the metadata bits when they aren't saturated.
+    fn psl(&self, word: u64, i: usize) -> usize {
+        if Self::META_MAX > 0 {
+            let meta = word >> Self::META_SHIFT;
+            if meta < Self::META_MAX {
+                return meta as usize;
+            }
+        }
+
+        let home = self.home(&K::from_bits(word & Self::KEY_MASK));
+        i.wrapping_sub(home) & (self.slots.len() - 1)
+    }
+
+    /// Replaces the metadata bits of a word with the (saturated) PSL.
+    fn with_psl(&self, word: u64, psl: usize) -> u64 {
+        let pair = word & Self::pair_mask();
+        if Self::META_MAX == 0 {
+            return pair;
+        }
+
+        pair | min(psl as u64, Self::META_MAX) << Self::META_SHIFT
+    }
+
+    fn unpack(&self, word: u64) -> (K, V) {
+        (
+            K::from_bits(word & Self::KEY_MASK),
+            V::from_bits(word >> K::BITS & Self::VALUE_MASK),
+        )
+    }
+
+    fn pair_mask() -> u64 {
+        match Self::META_SHIFT {
+            64 => u64::MAX,
+            shift => !(u64::MAX << shift),
+        }
+    }
+
+    /// Number of entries stored in `slots`, i.e. excluding the side slot.
+    fn table_items(&self) -> usize {
+        self.num_items - self.sentinel_value.is_some() as usize
+    }
+
+    fn home(&self, key: &K) -> usize {
+        self.hasher_builder.hash_one(key) as usize & (self.slots.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_packs_pairs_into_a_single_word() {
+        let mut map: PackedMap<u32, u32, FxBuildHasher> = PackedMap::new();
+        for x in 0..1000 {
+            map.insert(x, x * 2);
+        }
+
+        assert_eq!(map.len(), 1000);
+        assert_eq!(
+            std::mem::size_of_val(&map.slots[..]),
+            8 * map.bucket_count()
+        );
+        assert_eq!(map.capacity(), 3 * map.bucket_count() / 4);
+        assert!(map.len() <= map.capacity());
+        for x in 0..1000 {
+            assert_eq!(map.get(&x), Some(x * 2));
+        }
+        assert_eq!(map.get(&1000), None);
+    }
+
+    #[test]
+    fn it_stores_the_sentinel_key_on_the_side() {
+        let mut map: PackedMap<u32, u32, FxBuildHasher> = PackedMap::new();
+        map.insert(u32::MAX, u32::MAX);
+        map.insert(1, 1);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&u32::MAX), Some(u32::MAX));
+        assert_eq!(map.iter().count(), 2);
+        assert!(map.remove(&u32::MAX).is_ok());
+        assert_eq!(map.get(&u32::MAX), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn it_overwrites_and_removes_with_metadata_bits() {
+        let mut map: PackedMap<i16, i8, FxBuildHasher> = PackedMap::new();
+        for x in -500..500 {
+            map.insert(x, (x % 100) as i8);
+        }
+        map.insert(-3, -128);
+
+        for x in (-500..500).step_by(2) {
+            assert!(map.remove(&x).is_ok());
+        }
+        assert!(map.remove(&-500).is_err());
+
+        assert_eq!(map.len(), 500);
+        assert_eq!(map.get(&-3), Some(-128));
+        for x in (-499..500).step_by(2).filter(|&x| x != -3) {
+            assert_eq!(map.get(&x), Some((x % 100) as i8));
+        }
+    }
+}
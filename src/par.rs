@@ -0,0 +1,210 @@
+use super::control::EMPTY;
+use super::hashmap::{RHMap, Slot};
+use super::hashset::RHSet;
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use core::hash::{BuildHasher, Hash};
+use rayon::iter::{
+    plumbing::UnindexedConsumer, FromParallelIterator, IndexedParallelIterator,
+    IntoParallelIterator, IntoParallelRefIterator, ParallelExtend, ParallelIterator,
+};
+
+/// A parallel iterator over the entries of a `RHMap`, created by `par_iter`. Threads split the slots between them,
+/// so scanning a large table doesn't have to walk it on one core first.
+pub struct ParIter<'a, K: Hash + Eq, V> {
+    ctrl: &'a [u8],
+    slots: &'a [Slot<K, V>],
+}
+
+impl<'a, K: Hash + Eq + Sync, V: Sync> ParallelIterator for ParIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.ctrl
+            .into_par_iter()
+            .zip(self.slots)
+            .filter(|(&ctrl, _)| ctrl != EMPTY)
+            .map(|(_, slot)| {
+                // SAFETY: the control byte says the slot is initialized.
+                let entry = unsafe { slot.assume_init_ref() };
+                (&entry.key, &entry.value)
+            })
+            .drive_unindexed(consumer)
+    }
+}
+
+/// A parallel iterator over the entries of a `RHMap` with mutable references to the values, created by
+/// `par_iter_mut`.
+pub struct ParIterMut<'a, K: Hash + Eq, V> {
+    ctrl: &'a [u8],
+    slots: &'a mut [Slot<K, V>],
+}
+
+impl<'a, K: Hash + Eq + Send + Sync, V: Send> ParallelIterator for ParIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.ctrl
+            .into_par_iter()
+            .zip(self.slots)
+            .filter(|(&ctrl, _)| ctrl != EMPTY)
+            .map(|(_, slot)| {
+                // SAFETY: the control byte says the slot is initialized.
+                let entry = unsafe { slot.assume_init_mut() };
+                (&entry.key, &mut entry.value)
+            })
+            .drive_unindexed(consumer)
+    }
+}
+
+/// A parallel iterator over the values of a `RHSet`, created by `par_iter`.
+pub struct ParSetIter<'a, T: Hash + Eq> {
+    inner: ParIter<'a, T, ()>,
+}
+
+impl<'a, T: Hash + Eq + Sync> ParallelIterator for ParSetIter<'a, T> {
+    type Item = &'a T;
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.inner.map(|(value, _)| value).drive_unindexed(consumer)
+    }
+}
+
+impl<'a, K: Hash + Eq + Sync, V: Sync, H: BuildHasher + Clone, A: Allocator + Clone>
+    IntoParallelIterator for &'a RHMap<K, V, H, A>
+{
+    type Item = (&'a K, &'a V);
+    type Iter = ParIter<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        let (ctrl, slots) = self.raw_slots();
+        ParIter { ctrl, slots }
+    }
+}
+
+impl<'a, K: Hash + Eq + Send + Sync, V: Send, H: BuildHasher + Clone, A: Allocator + Clone>
+    IntoParallelIterator for &'a mut RHMap<K, V, H, A>
+{
+    type Item = (&'a K, &'a mut V);
+    type Iter = ParIterMut<'a, K, V>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        let (ctrl, slots) = self.raw_slots_mut();
+        ParIterMut { ctrl, slots }
+    }
+}
+
+/// Moves the entries out into a vector first, like the sequential `into_iter`, which rayon then splits up.
+impl<K: Hash + Eq + Send, V: Send, H: BuildHasher + Clone, A: Allocator + Clone>
+    IntoParallelIterator for RHMap<K, V, H, A>
+{
+    type Item = (K, V);
+    type Iter = rayon::vec::IntoIter<(K, V)>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.into_iter().collect::<Vec<_>>().into_par_iter()
+    }
+}
+
+/// Every thread collects its share of the pairs into a map of its own, and the maps are then merged pairwise, reusing
+/// the hashes computed by the threads. As with sequential inserts, the pair that comes last wins.
+impl<K: Hash + Eq + Send, V: Send, H: BuildHasher + Clone + Send + Sync> ParallelExtend<(K, V)>
+    for RHMap<K, V, H>
+{
+    fn par_extend<I: IntoParallelIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        let hasher_builder = self.hasher().clone();
+        let shard = || RHMap::with_hasher(hasher_builder.clone());
+        let mut merged = pairs
+            .into_par_iter()
+            .fold(shard, |mut shard, (key, value)| {
+                shard.insert(key, value);
+                shard
+            })
+            .reduce(shard, |mut left, mut right| {
+                left.append(&mut right);
+                left
+            });
+        self.append(&mut merged);
+    }
+}
+
+impl<K: Hash + Eq + Send, V: Send, H: BuildHasher + Clone + Default + Send + Sync>
+    FromParallelIterator<(K, V)> for RHMap<K, V, H>
+{
+    fn from_par_iter<I: IntoParallelIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let mut map = Self::with_hasher(H::default());
+        map.par_extend(pairs);
+        map
+    }
+}
+
+impl<'a, T: Hash + Eq + Sync, H: BuildHasher + Clone> IntoParallelIterator for &'a RHSet<T, H> {
+    type Item = &'a T;
+    type Iter = ParSetIter<'a, T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParSetIter {
+            inner: self.map.par_iter(),
+        }
+    }
+}
+
+impl<T: Hash + Eq + Send, H: BuildHasher + Clone> IntoParallelIterator for RHSet<T, H> {
+    type Item = T;
+    type Iter = rayon::vec::IntoIter<T>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.map.into_keys_vec().into_par_iter()
+    }
+}
+
+impl<T: Hash + Eq + Send, H: BuildHasher + Clone + Send + Sync> ParallelExtend<T> for RHSet<T, H> {
+    fn par_extend<I: IntoParallelIterator<Item = T>>(&mut self, values: I) {
+        self.map
+            .par_extend(values.into_par_iter().map(|value| (value, ())));
+    }
+}
+
+impl<T: Hash + Eq + Send, H: BuildHasher + Clone + Default + Send + Sync> FromParallelIterator<T>
+    for RHSet<T, H>
+{
+    fn from_par_iter<I: IntoParallelIterator<Item = T>>(values: I) -> Self {
+        let mut set = Self::with_hasher(H::default());
+        set.par_extend(values);
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use rayon::prelude::*;
+
+    #[test]
+    fn it_collects_and_scans_maps_in_parallel() {
+        let mut map: FxHashMap<u64, u64> = (0..100_000u64)
+            .into_par_iter()
+            .map(|x| (x % 50_000, x))
+            .collect();
+
+        assert_eq!(map.len(), 50_000);
+        // The later of the two pairs of every key wins, as with sequential inserts.
+        assert_eq!(map.get(&7), Some(&50_007));
+        map.par_iter_mut().for_each(|(_, value)| *value -= 50_000);
+        assert_eq!(
+            map.par_iter().map(|(_, &value)| value).sum::<u64>(),
+            (0..50_000u64).sum::<u64>()
+        );
+        assert_eq!(map.into_par_iter().count(), 50_000);
+    }
+
+    #[test]
+    fn it_collects_and_scans_sets_in_parallel() {
+        let mut set: FxHashSet<u32> = (0..10_000u32).into_par_iter().collect();
+        set.par_extend((5_000..20_000u32).into_par_iter());
+
+        assert_eq!(set.len(), 20_000);
+        assert_eq!(set.par_iter().filter(|&&x| x % 2 == 0).count(), 10_000);
+        assert_eq!(set.into_par_iter().max(), Some(19_999));
+    }
+}
@@ -0,0 +1,553 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    slice,
+};
+
+/// Hash bits consumed per level of the trie.
+const BITS: u32 = 5;
+const LEVEL_MASK: u64 = (1 << BITS) - 1;
+
+/// A `PersistentMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxPersistentMap<K, V> = PersistentMap<K, V, FxBuildHasher>;
+
+enum Node<K, V> {
+    /// Up to 32 children, one per 5-bit chunk of the hash at this level, stored densely in `bitmap` order.
+    Branch {
+        bitmap: u32,
+        children: Vec<Arc<Node<K, V>>>,
+    },
+    /// Every entry with one full hash. More than one only when hashes collide completely.
+    Leaf { hash: u64, entries: Vec<(K, V)> },
+}
+
+/// The slot of `hash` among the 32 children of a branch `shift` bits deep.
+fn bit_of(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & LEVEL_MASK)
+}
+
+/// The index into the dense children of a branch of the child for `bit`.
+fn child_index(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+impl<K: Clone, V: Clone> Node<K, V> {
+    fn get<Q: ?Sized + Equivalent<K>>(&self, hash: u64, shift: u32, key: &Q) -> Option<(&K, &V)> {
+        match self {
+            Node::Branch { bitmap, children } => {
+                let bit = bit_of(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                children[child_index(*bitmap, bit)].get(hash, shift + BITS, key)
+            }
+            Node::Leaf { hash: h, entries } if *h == hash => entries
+                .iter()
+                .find(|(k, _)| key.equivalent(k))
+                .map(|(k, v)| (k, v)),
+            Node::Leaf { .. } => None,
+        }
+    }
+
+    /// Returns the node with the entry inserted, sharing every untouched child, and whether the key is new.
+    fn insert(node: &Arc<Self>, hash: u64, shift: u32, key: K, value: V) -> (Arc<Self>, bool)
+    where
+        K: Eq,
+    {
+        match &**node {
+            Node::Branch { bitmap, children } => {
+                let bit = bit_of(hash, shift);
+                let i = child_index(*bitmap, bit);
+                let mut children = children.clone();
+                if bitmap & bit == 0 {
+                    children.insert(i, Arc::new(Node::leaf(hash, key, value)));
+                    let branch = Node::Branch {
+                        bitmap: bitmap | bit,
+                        children,
+                    };
+                    return (Arc::new(branch), true);
+                }
+
+                let (child, added) = Node::insert(&children[i], hash, shift + BITS, key, value);
+                children[i] = child;
+                let branch = Node::Branch {
+                    bitmap: *bitmap,
+                    children,
+                };
+                (Arc::new(branch), added)
+            }
+            Node::Leaf { hash: h, entries } if *h == hash => {
+                let mut entries = entries.clone();
+                let added = match entries.iter_mut().find(|(k, _)| *k == key) {
+                    Some(entry) => {
+                        entry.1 = value;
+                        false
+                    }
+                    None => {
+                        entries.push((key, value));
+                        true
+                    }
+                };
+                (Arc::new(Node::Leaf { hash, entries }), added)
+            }
+            Node::Leaf { hash: h, .. } => {
+                let leaf = Arc::new(Node::leaf(hash, key, value));
+                (Node::split(node.clone(), *h, leaf, hash, shift), true)
+            }
+        }
+    }
+
+    /// Pushes two leaves with different hashes down until their hashes differ, under a chain of single child
+    /// branches where they don't.
+    fn split(a: Arc<Self>, a_hash: u64, b: Arc<Self>, b_hash: u64, shift: u32) -> Arc<Self> {
+        let (a_bit, b_bit) = (bit_of(a_hash, shift), bit_of(b_hash, shift));
+        let branch = if a_bit == b_bit {
+            Node::Branch {
+                bitmap: a_bit,
+                children: vec![Node::split(a, a_hash, b, b_hash, shift + BITS)],
+            }
+        } else if a_bit < b_bit {
+            Node::Branch {
+                bitmap: a_bit | b_bit,
+                children: vec![a, b],
+            }
+        } else {
+            Node::Branch {
+                bitmap: a_bit | b_bit,
+                children: vec![b, a],
+            }
+        };
+        Arc::new(branch)
+    }
+
+    /// Returns `None` if the key isn't there, otherwise the node without it (`None` once the node is empty) and the
+    /// removed entry.
+    #[allow(clippy::type_complexity)]
+    fn remove<Q: ?Sized + Equivalent<K>>(
+        node: &Arc<Self>,
+        hash: u64,
+        shift: u32,
+        key: &Q,
+    ) -> Option<(Option<Arc<Self>>, (K, V))> {
+        match &**node {
+            Node::Branch { bitmap, children } => {
+                let bit = bit_of(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                let i = child_index(*bitmap, bit);
+                let (child, removed) = Node::remove(&children[i], hash, shift + BITS, key)?;
+
+                let mut children = children.clone();
+                let bitmap = match child {
+                    Some(child) => {
+                        children[i] = child;
+                        *bitmap
+                    }
+                    None => {
+                        children.remove(i);
+                        bitmap & !bit
+                    }
+                };
+                // A branch left with a single leaf is replaced by the leaf, so a trie shrinks back as it empties.
+                let node = match children.as_slice() {
+                    [] => None,
+                    [only] if matches!(**only, Node::Leaf { .. }) => Some(only.clone()),
+                    _ => Some(Arc::new(Node::Branch { bitmap, children })),
+                };
+                Some((node, removed))
+            }
+            Node::Leaf { hash: h, entries } if *h == hash => {
+                let i = entries.iter().position(|(k, _)| key.equivalent(k))?;
+                let mut entries = entries.clone();
+                let removed = entries.swap_remove(i);
+                let node = (!entries.is_empty()).then(|| Arc::new(Node::Leaf { hash, entries }));
+                Some((node, removed))
+            }
+            Node::Leaf { .. } => None,
+        }
+    }
+}
+
+impl<K, V> Node<K, V> {
+    fn leaf(hash: u64, key: K, value: V) -> Self {
+        Node::Leaf {
+            hash,
+            entries: vec![(key, value)],
+        }
+    }
+}
+
+/// An immutable map whose `insert` and `remove` return a new map and leave the old one untouched, e.g. for the scopes
+/// of an interpreter or an undo history. Cloning one is a reference count bump.
+///
+/// A hash array mapped trie: each level branches on the next 5 bits of the hash, so an update copies only the
+/// (at most 13) nodes on the path to its key and shares everything else with the old map. Both versions can be read
+/// from any number of threads.
+pub struct PersistentMap<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone> {
+    root: Option<Arc<Node<K, V>>>,
+    num_items: usize,
+    hasher_builder: H,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> PersistentMap<K, V, FxBuildHasher> {
+    /// Creates an empty `PersistentMap` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone + Default> Default
+    for PersistentMap<K, V, H>
+{
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone> Clone for PersistentMap<K, V, H> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            num_items: self.num_items,
+            hasher_builder: self.hasher_builder.clone(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone> PersistentMap<K, V, H> {
+    /// Creates an empty `PersistentMap` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            root: None,
+            num_items: 0,
+            hasher_builder,
+        }
+    }
+
+    /// Returns a new map with the key value pair inserted, overwriting any previous value of the key.
+    pub fn insert(&self, key: K, value: V) -> Self {
+        let hash = self.hasher_builder.hash_one(&key);
+        let (root, added) = match &self.root {
+            Some(root) => Node::insert(root, hash, 0, key, value),
+            None => (Arc::new(Node::leaf(hash, key, value)), true),
+        };
+
+        Self {
+            root: Some(root),
+            num_items: self.num_items + added as usize,
+            hasher_builder: self.hasher_builder.clone(),
+        }
+    }
+
+    /// Returns a new map without the given key. The map is cloned as is when the key isn't there.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Self {
+        self.remove_entry(key)
+            .map_or_else(|| self.clone(), |(map, _)| map)
+    }
+
+    /// Returns a new map without the given key along with the removed entry, or `None` if the key isn't there.
+    pub fn remove_entry<Q: ?Sized + Hash + Equivalent<K>>(
+        &self,
+        key: &Q,
+    ) -> Option<(Self, (K, V))> {
+        let hash = self.hasher_builder.hash_one(key);
+        let (root, removed) = Node::remove(self.root.as_ref()?, hash, 0, key)?;
+        let map = Self {
+            root,
+            num_items: self.num_items - 1,
+            hasher_builder: self.hasher_builder.clone(),
+        };
+        Some((map, removed))
+    }
+
+    /// Gets the value associated with the key.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    /// Gets the stored key and the value associated with the key.
+    pub fn get_key_value<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<(&K, &V)> {
+        let hash = self.hasher_builder.hash_one(key);
+        self.root.as_ref()?.get(hash, 0, key)
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.get_key_value(key).is_some()
+    }
+
+    /// Gets the length / number of entries of the map.
+    pub fn len(&self) -> usize {
+        self.num_items
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+
+    /// Returns `true` if both maps share the same root, i.e. one is an unmodified clone of the other. Cheaper than
+    /// comparing entries, and enough to skip work when a version hasn't changed.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Gets a reference to the hasher builder.
+    pub fn hasher(&self) -> &H {
+        &self.hasher_builder
+    }
+
+    /// Iterates over every key value pair in hash order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            stack: self.root.iter().map(|root| &**root).collect(),
+            entries: [].iter(),
+            remaining: self.num_items,
+        }
+    }
+
+    /// Iterates over every key in hash order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterates over every value in hash order.
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+/// Iterator over the entries of a `PersistentMap`, created by `PersistentMap::iter`.
+pub struct Iter<'a, K, V> {
+    /// Nodes still to visit, depth first.
+    stack: Vec<&'a Node<K, V>>,
+    /// The rest of the leaf being visited.
+    entries: slice::Iter<'a, (K, V)>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, value)) = self.entries.next() {
+                self.remaining -= 1;
+                return Some((key, value));
+            }
+            match self.stack.pop()? {
+                Node::Branch { children, .. } => self
+                    .stack
+                    .extend(children.iter().rev().map(|child| &**child)),
+                Node::Leaf { entries, .. } => self.entries = entries.iter(),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+/// Formats the map like the std maps do (`{key: value, ...}`).
+impl<K: Hash + Eq + Clone + Debug, V: Clone + Debug, H: BuildHasher + Clone> Debug
+    for PersistentMap<K, V, H>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Replaces the map with a new version holding the extra pairs. Any clones taken before keep the old version.
+impl<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone> Extend<(K, V)>
+    for PersistentMap<K, V, H>
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        for (key, value) in pairs {
+            *self = self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone + Default> FromIterator<(K, V)>
+    for PersistentMap<K, V, H>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let mut map = Self::default();
+        map.extend(pairs);
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::hash::{BuildHasherDefault, Hasher};
+
+    #[test]
+    fn it_keeps_old_versions_intact() {
+        let empty = FxPersistentMap::new();
+        let one = empty.insert("x", 1);
+        let two = one.insert("y", 2);
+        let shadowed = two.insert("x", 10);
+        let removed = shadowed.remove("y");
+
+        assert!(empty.is_empty());
+        assert_eq!((one.get("x"), one.get("y")), (Some(&1), None));
+        assert_eq!((two.get("x"), two.get("y")), (Some(&1), Some(&2)));
+        assert_eq!(shadowed.get("x"), Some(&10));
+        assert_eq!(shadowed.len(), 2);
+        assert_eq!(removed.len(), 1);
+        assert!(removed.remove("y").ptr_eq(&removed));
+
+        let big: FxPersistentMap<u32, u32> = (0..2000).map(|x| (x, x * 2)).collect();
+        let smaller = (0..2000)
+            .step_by(2)
+            .fold(big.clone(), |map, x| map.remove(&x));
+        assert_eq!(big.iter().len(), 2000);
+        assert_eq!(smaller.len(), 1000);
+        assert!((0..2000).all(|x| smaller.contains_key(&x) == (x % 2 == 1)));
+        assert!((0..2000).all(|x| big.get(&x) == Some(&(x * 2))));
+    }
+
+    /// Sends every key to the same hash.
+    #[derive(Default)]
+    struct Colliding;
+
+    impl Hasher for Colliding {
+        fn finish(&self) -> u64 {
+            42
+        }
+
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    #[test]
+    fn it_keeps_colliding_keys_apart() {
+        let map: PersistentMap<u8, char, BuildHasherDefault<Colliding>> =
+            vec![(1, 'a'), (2, 'b'), (3, 'c')].into_iter().collect();
+        let map = map.insert(2, 'B');
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&2), Some(&'B'));
+        let map = map.remove(&1).remove(&3);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&2, &'B')]);
+        assert!(map.remove(&2).is_empty());
+    }
+
+    /// Hashes a `(u64, u8)` key to its first field, so keys sharing the field collide completely and every other hash
+    /// is picked by the test.
+    #[derive(Default)]
+    struct FirstField {
+        hash: u64,
+        written: usize,
+    }
+
+    impl Hasher for FirstField {
+        fn finish(&self) -> u64 {
+            self.hash
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                if self.written < 8 {
+                    self.hash |= u64::from(byte) << (8 * self.written);
+                    self.written += 1;
+                }
+            }
+        }
+    }
+
+    type ChosenHashMap = PersistentMap<(u64, u8), u32, BuildHasherDefault<FirstField>>;
+
+    fn root_children<K: Hash + Eq + Clone, V: Clone, H: BuildHasher + Clone>(
+        map: &PersistentMap<K, V, H>,
+    ) -> &[Arc<Node<K, V>>] {
+        match map.root.as_deref() {
+            Some(Node::Branch { children, .. }) => children,
+            _ => panic!("expected a branch at the root"),
+        }
+    }
+
+    #[test]
+    fn it_splits_and_shrinks_full_hash_collisions() {
+        let map = ChosenHashMap::default()
+            .insert((7, 0), 0)
+            .insert((7, 1), 1)
+            .insert((7, 2), 2);
+        assert!(
+            matches!(map.root.as_deref(), Some(Node::Leaf { entries, .. }) if entries.len() == 3)
+        );
+
+        // Shares the low 5 bits with the collisions, so their leaf is pushed a level down.
+        let map = map.insert((7 + 32, 0), 3).insert((7, 1), 10);
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get(&(7, 1)), Some(&10));
+        assert_eq!(map.get(&(7 + 32, 0)), Some(&3));
+        assert_eq!(map.get(&(7, 3)), None);
+
+        let map = map.remove(&(7, 0)).remove(&(7, 2));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&(7, 1)), Some(&10));
+        assert_eq!(map.remove(&(7, 9)).len(), 2);
+        assert_eq!(map.remove(&(7, 1)).get(&(7 + 32, 0)), Some(&3));
+    }
+
+    #[test]
+    fn it_collapses_branches_left_with_a_single_leaf() {
+        // Hashes 1 and 1 + 2^10 agree on two levels, so they sit under a chain of two single child branches.
+        let deep = ChosenHashMap::default()
+            .insert((1, 0), 0)
+            .insert((1 + (1 << 10), 0), 1);
+        let map = deep.insert((2, 0), 2);
+        assert_eq!(root_children(&map).len(), 2);
+
+        // Removing one of the pair collapses the chain, leaving the other one as a leaf right under the root.
+        let map = map.remove(&(1 + (1 << 10), 0));
+        assert!(root_children(&map)
+            .iter()
+            .all(|child| matches!(**child, Node::Leaf { .. })));
+        assert_eq!(map.get(&(1, 0)), Some(&0));
+
+        // With nothing else around, the root itself ends up a leaf, then empty.
+        let map = deep.remove(&(1, 0));
+        assert!(
+            matches!(map.root.as_deref(), Some(Node::Leaf { hash, .. }) if *hash == 1 + (1 << 10))
+        );
+        assert!(map.remove(&(1 + (1 << 10), 0)).root.is_none());
+    }
+
+    #[test]
+    fn it_shares_untouched_nodes_with_older_versions() {
+        let old: FxPersistentMap<u32, u32> = (0..1000).map(|x| (x, x)).collect();
+        let new = old.insert(1000, 1000).insert(5, 50);
+
+        assert_eq!(old.len(), 1000);
+        assert_eq!(old.get(&5), Some(&5));
+        assert_eq!(old.get(&1000), None);
+        assert_eq!(new.get(&5), Some(&50));
+        assert_eq!(new.len(), 1001);
+
+        // Only the root children on the paths to the two updated keys were copied.
+        let (old_children, new_children) = (root_children(&old), root_children(&new));
+        assert_eq!(old_children.len(), new_children.len());
+        let copied = old_children
+            .iter()
+            .zip(new_children)
+            .filter(|(a, b)| !Arc::ptr_eq(a, b))
+            .count();
+        assert!((1..=2).contains(&copied));
+        assert!(old.clone().ptr_eq(&old));
+    }
+}
@@ -0,0 +1,166 @@
+use alloc::{vec, vec::Vec};
+use core::mem;
+
+const INITIAL_SIZE: usize = 8;
+
+/// Position of a vacant slot.
+const VACANT: usize = usize::MAX;
+
+#[derive(Clone, Copy)]
+struct Slot {
+    hash: usize,
+    position: usize,
+}
+
+/// A Robin Hood table mapping hashes to positions in some other storage, for the collections that keep their entries
+/// in a dense vector or slab (the index map, the linked map, the slot map) and only need a way to find them by key.
+/// Keys never live in the table: lookups hand each candidate position to a closure that compares the key stored there.
+///
+/// Hashes are stored next to the positions, so the table grows and computes PSLs without touching the entries.
+pub(crate) struct PositionTable {
+    slots: Vec<Slot>,
+    num_items: usize,
+}
+
+impl PositionTable {
+    pub(crate) fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            num_items: 0,
+        }
+    }
+
+    /// Finds the slot holding a position with the given hash for which `is_match` returns `true`.
+    pub(crate) fn find<F: FnMut(usize) -> bool>(
+        &self,
+        hash: usize,
+        mut is_match: F,
+    ) -> Option<usize> {
+        if self.slots.is_empty() {
+            return None;
+        }
+
+        let mask = self.slots.len() - 1;
+        let mut i = hash & mask;
+        let mut psl = 0;
+
+        loop {
+            // Stop at a vacancy, or at an entry richer than us: the robinhood invariant means our key would have
+            // evicted it.
+            let slot = self.slots[i];
+            if slot.position == VACANT || psl > self.psl(i) {
+                return None;
+            }
+            if slot.hash == hash && is_match(slot.position) {
+                return Some(i);
+            }
+
+            psl += 1;
+            i = (i + 1) & mask;
+        }
+    }
+
+    /// Finds the slot holding exactly `position`, which must be in the table under `hash`.
+    pub(crate) fn slot_of(&self, hash: usize, position: usize) -> usize {
+        self.find(hash, |candidate| candidate == position)
+            .expect("position is missing from the table")
+    }
+
+    /// The position stored in an occupied slot.
+    pub(crate) fn position(&self, slot: usize) -> usize {
+        self.slots[slot].position
+    }
+
+    /// Points an occupied slot at a new position, e.g. after its entry moved within the storage.
+    pub(crate) fn set_position(&mut self, slot: usize, position: usize) {
+        self.slots[slot].position = position;
+    }
+
+    /// Iterates over every stored position, to renumber them in bulk.
+    pub(crate) fn positions_mut(&mut self) -> impl Iterator<Item = &mut usize> + '_ {
+        self.slots
+            .iter_mut()
+            .map(|slot| &mut slot.position)
+            .filter(|position| **position != VACANT)
+    }
+
+    /// Adds a position under the given hash, growing the table at a load factor of 0.75.
+    pub(crate) fn insert(&mut self, hash: usize, position: usize) {
+        if self.slots.is_empty() || self.num_items >= 3 * self.slots.len() / 4 {
+            self.resize();
+        }
+
+        self.place(Slot { hash, position });
+        self.num_items += 1;
+    }
+
+    /// Vacates a slot with backward shift deletion.
+    pub(crate) fn remove(&mut self, mut i: usize) {
+        let mask = self.slots.len() - 1;
+        loop {
+            let next = (i + 1) & mask;
+            if self.slots[next].position == VACANT || self.psl(next) == 0 {
+                break;
+            }
+
+            self.slots[i] = self.slots[next];
+            i = next;
+        }
+
+        self.slots[i].position = VACANT;
+        self.num_items -= 1;
+    }
+
+    /// Vacates every slot but keeps the allocation.
+    pub(crate) fn clear(&mut self) {
+        self.slots
+            .iter_mut()
+            .for_each(|slot| slot.position = VACANT);
+        self.num_items = 0;
+    }
+
+    fn place(&mut self, mut slot: Slot) {
+        let mask = self.slots.len() - 1;
+        let mut i = slot.hash & mask;
+        let mut psl = 0;
+
+        loop {
+            if self.slots[i].position == VACANT {
+                self.slots[i] = slot;
+                return;
+            }
+
+            let cur_psl = self.psl(i);
+            if psl > cur_psl {
+                slot = mem::replace(&mut self.slots[i], slot);
+                psl = cur_psl;
+            }
+
+            psl += 1;
+            i = (i + 1) & mask;
+        }
+    }
+
+    /// The PSL of an occupied slot.
+    fn psl(&self, i: usize) -> usize {
+        i.wrapping_sub(self.slots[i].hash) & (self.slots.len() - 1)
+    }
+
+    fn resize(&mut self) {
+        let target_size = match self.slots.len() {
+            0 => INITIAL_SIZE,
+            n => 2 * n,
+        };
+
+        let vacant = Slot {
+            hash: 0,
+            position: VACANT,
+        };
+        let slots = mem::replace(&mut self.slots, vec![vacant; target_size]);
+        for slot in slots {
+            if slot.position != VACANT {
+                self.place(slot);
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use core::{
+    hash::{BuildHasher, Hash, Hasher},
+    ops::Deref,
+};
+
+/// A key bundled with its hash, computed once up front. Hashing a `PreHashed` only feeds the stored hash to the hasher
+/// rather than the whole key, and equality compares hashes before keys, so keys that are expensive to hash (long
+/// strings, composite keys) are hashed once however many maps and lookups they pass through.
+///
+/// When the hash was computed with a map's own hasher (see `RHMap::hasher`), it is also what `RHMap::get_with_hash`
+/// and `RHMap::insert_with_hash` expect for the bare key.
+#[derive(Clone, Copy, Debug)]
+pub struct PreHashed<K> {
+    hash: u64,
+    key: K,
+}
+
+impl<K: Hash> PreHashed<K> {
+    /// Hashes `key` with `hasher_builder` and keeps the result next to it.
+    pub fn new<S: BuildHasher>(key: K, hasher_builder: &S) -> Self {
+        Self {
+            hash: hasher_builder.hash_one(&key),
+            key,
+        }
+    }
+}
+
+impl<K> PreHashed<K> {
+    /// Bundles `key` with a hash computed elsewhere, e.g. by the router that picked the shard it belongs to.
+    pub fn with_hash(hash: u64, key: K) -> Self {
+        Self { hash, key }
+    }
+
+    /// Gets the stored hash.
+    pub fn hash_value(&self) -> u64 {
+        self.hash
+    }
+
+    /// Gets the key.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Unwraps the key, dropping the hash.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+impl<K> Deref for PreHashed<K> {
+    type Target = K;
+
+    fn deref(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K> Hash for PreHashed<K> {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl<K: PartialEq> PartialEq for PreHashed<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.key == other.key
+    }
+}
+
+impl<K: Eq> Eq for PreHashed<K> {}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fx_build_hasher::FxBuildHasher;
+    use super::super::hashmap::RHMap;
+    use super::*;
+
+    #[test]
+    fn it_looks_keys_up_by_their_precomputed_hash() {
+        let mut map: RHMap<String, usize, FxBuildHasher> = RHMap::new();
+        let keys: Vec<_> = (0..100)
+            .map(|x| PreHashed::new(format!("key {}", x), map.hasher()))
+            .collect();
+        for (x, key) in keys.iter().enumerate() {
+            map.insert_with_hash(key.hash_value(), key.to_string(), x);
+        }
+
+        let key = &keys[42];
+        assert_eq!(
+            map.get_with_hash(key.hash_value(), |k| k == key.key()),
+            Some(&42)
+        );
+        assert_eq!(map.get(&"key 42".to_string()), Some(&42));
+        assert_eq!(map.get_with_hash(key.hash_value(), |k| k == "key 43"), None);
+
+        let mut wrapped = RHMap::new();
+        for key in keys {
+            let len = key.len();
+            wrapped.insert(key, len);
+        }
+        let probe = PreHashed::new("key 7".to_string(), map.hasher());
+        assert_eq!(wrapped.get(&probe), Some(&5));
+    }
+}
@@ -0,0 +1,53 @@
+#[cfg(feature = "aes-hash")]
+pub use super::aes_hash::{AesBuildHasher, AesHashMap};
+pub use super::any_map::AnyMap;
+pub use super::bimap::{BiMap, FxBiMap};
+pub use super::builder::RHMapBuilder;
+pub use super::counter::{Counter, FxCounter};
+pub use super::cuckoo::{CuckooMap, FxCuckooMap};
+#[cfg(feature = "std")]
+pub use super::dashmap::{DashMap, FxDashMap};
+pub use super::equivalent::Equivalent;
+pub use super::frozen::{FrozenFxMap, FrozenMap};
+pub use super::fx_build_hasher::{FxBuildHasher, FxBuildHasherDefault, FxHasher};
+pub use super::hashmap::{FxHashMap, RHMap};
+pub use super::hashset::{FxHashSet, RHSet};
+pub use super::hopscotch::{FxHopscotchMap, HopscotchMap};
+pub use super::index_map::{FxIndexMap, IndexMap};
+pub use super::index_set::{FxIndexSet, IndexSet};
+pub use super::interner::{FxInterner, Interner, Symbol};
+pub use super::linked_map::{FxLinkedHashMap, LinkedHashMap};
+pub use super::lru::{FxLruCache, LruCache};
+#[cfg(feature = "mmap")]
+pub use super::mmap::{MmapFxMap, MmapMap};
+pub use super::multimap::{FxMultiMap, GroupBy, MultiMap};
+pub use super::no_hash::{IntFxHashMap, NoHashBuilder};
+#[cfg(feature = "std")]
+pub use super::once_map::{FxOnceMap, OnceMap};
+pub use super::persistent::{FxPersistentMap, PersistentMap};
+#[cfg(feature = "secure")]
+pub use super::secure::{SecureBuildHasher, SecureHashMap};
+pub use super::slot_map::{FxSlotMap, SlotHandle, SlotMap};
+pub use super::traits::{MapRead, MapWrite};
+#[cfg(feature = "std")]
+pub use super::ttl::FxTtlMap;
+#[cfg(feature = "wyhash")]
+pub use super::wyhash::{WyBuildHasher, WyHashMap};
+pub use crate::{fxhashmap, fxhashset};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total<M: MapRead<i32, i32>>(map: &M, keys: &[i32]) -> i32 {
+        keys.iter().filter_map(|k| map.get(k)).sum()
+    }
+
+    #[test]
+    fn it_covers_the_common_surface() {
+        let mut map: FxHashMap<i32, i32> = fxhashmap! { 1 => 10, 2 => 20 };
+        MapWrite::insert(&mut map, 3, 30);
+
+        assert_eq!(total(&map, &[1, 2, 3, 4]), 60);
+    }
+}
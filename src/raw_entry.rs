@@ -0,0 +1,347 @@
+use super::equivalent::Equivalent;
+use super::hashmap::RHMap;
+use allocator_api2::alloc::{Allocator, Global};
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    mem,
+};
+
+/// A builder for looking up entries by hash and a match closure, created by `RHMap::raw_entry`.
+pub struct RawEntryBuilder<
+    'a,
+    K: Hash + Eq,
+    V,
+    H: BuildHasher + Clone,
+    A: Allocator + Clone = Global,
+> {
+    map: &'a RHMap<K, V, H, A>,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone>
+    RawEntryBuilder<'a, K, V, H, A>
+{
+    pub(crate) fn new(map: &'a RHMap<K, V, H, A>) -> Self {
+        Self { map }
+    }
+
+    /// Looks up an entry by a borrowed form of its key.
+    pub fn from_key<Q: ?Sized + Hash + Equivalent<K>>(self, key: &Q) -> Option<(&'a K, &'a V)> {
+        let hash = self.map.hash_key(key);
+        self.from_key_hashed_nocheck(hash, key)
+    }
+
+    /// Looks up an entry by a borrowed form of its key whose hash is already known. `hash` must be what the map's
+    /// hasher produces for the key, otherwise the entry isn't found.
+    pub fn from_key_hashed_nocheck<Q: ?Sized + Equivalent<K>>(
+        self,
+        hash: u64,
+        key: &Q,
+    ) -> Option<(&'a K, &'a V)> {
+        self.from_hash(hash, |k| key.equivalent(k))
+    }
+
+    /// Looks up the entry with the given hash whose key satisfies `is_match`. The closure is only called for keys
+    /// stored under exactly `hash`.
+    pub fn from_hash<F: FnMut(&K) -> bool>(self, hash: u64, is_match: F) -> Option<(&'a K, &'a V)> {
+        let index = self.map.probe_with(hash as usize, is_match).0?;
+        self.map
+            .entry_at(index)
+            .map(|entry| (&entry.key, &entry.value))
+    }
+}
+
+/// A builder for looking up entries by hash and a match closure with the option of inserting, created by
+/// `RHMap::raw_entry_mut`.
+pub struct RawEntryBuilderMut<
+    'a,
+    K: Hash + Eq,
+    V,
+    H: BuildHasher + Clone,
+    A: Allocator + Clone = Global,
+> {
+    map: &'a mut RHMap<K, V, H, A>,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone>
+    RawEntryBuilderMut<'a, K, V, H, A>
+{
+    pub(crate) fn new(map: &'a mut RHMap<K, V, H, A>) -> Self {
+        Self { map }
+    }
+
+    /// Gets the entry for a borrowed form of its key.
+    pub fn from_key<Q: ?Sized + Hash + Equivalent<K>>(
+        self,
+        key: &Q,
+    ) -> RawEntryMut<'a, K, V, H, A> {
+        let hash = self.map.hash_key(key);
+        self.from_key_hashed_nocheck(hash, key)
+    }
+
+    /// Gets the entry for a borrowed form of its key whose hash is already known. `hash` must be what the map's hasher
+    /// produces for the key, otherwise the entry isn't found.
+    pub fn from_key_hashed_nocheck<Q: ?Sized + Equivalent<K>>(
+        self,
+        hash: u64,
+        key: &Q,
+    ) -> RawEntryMut<'a, K, V, H, A> {
+        self.from_hash(hash, |k| key.equivalent(k))
+    }
+
+    /// Gets the entry with the given hash whose key satisfies `is_match`. The closure is only called for keys stored
+    /// under exactly `hash`.
+    pub fn from_hash<F: FnMut(&K) -> bool>(
+        self,
+        hash: u64,
+        is_match: F,
+    ) -> RawEntryMut<'a, K, V, H, A> {
+        match self.map.probe_with(hash as usize, is_match).0 {
+            Some(index) => RawEntryMut::Occupied(RawOccupiedEntryMut {
+                map: self.map,
+                index,
+            }),
+            None => RawEntryMut::Vacant(RawVacantEntryMut { map: self.map }),
+        }
+    }
+}
+
+/// A view into a single entry of a `RHMap` found through `RHMap::raw_entry_mut`. Keys are only handed out by shared
+/// reference, since changing a key's hash in place would strand its entry.
+pub enum RawEntryMut<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone = Global> {
+    Occupied(RawOccupiedEntryMut<'a, K, V, H, A>),
+    Vacant(RawVacantEntryMut<'a, K, V, H, A>),
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone>
+    RawEntryMut<'a, K, V, H, A>
+{
+    /// Ensures the entry exists by inserting the given key and value if it's vacant, and returns references to the
+    /// key and value in the map.
+    pub fn or_insert(self, default_key: K, default_value: V) -> (&'a K, &'a mut V) {
+        self.or_insert_with(|| (default_key, default_value))
+    }
+
+    /// Ensures the entry exists by inserting the key and value `default` builds if it's vacant, and returns
+    /// references to the key and value in the map. `default` only runs on a miss, so the owned key is only built when
+    /// it's actually needed.
+    pub fn or_insert_with<F: FnOnce() -> (K, V)>(self, default: F) -> (&'a K, &'a mut V) {
+        match self {
+            RawEntryMut::Occupied(entry) => entry.into_key_value(),
+            RawEntryMut::Vacant(entry) => {
+                let (key, value) = default();
+                entry.insert(key, value)
+            }
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts.
+    pub fn and_modify<F: FnOnce(&K, &mut V)>(mut self, f: F) -> Self {
+        if let RawEntryMut::Occupied(entry) = &mut self {
+            let (key, value) = entry.key_value_mut();
+            f(key, value);
+        }
+
+        self
+    }
+}
+
+/// A view into an occupied entry of a `RHMap` found through `RHMap::raw_entry_mut`.
+pub struct RawOccupiedEntryMut<
+    'a,
+    K: Hash + Eq,
+    V,
+    H: BuildHasher + Clone,
+    A: Allocator + Clone = Global,
+> {
+    map: &'a mut RHMap<K, V, H, A>,
+    /// Slot index of the entry. Valid for as long as we hold the mutable borrow of the map.
+    index: usize,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone>
+    RawOccupiedEntryMut<'a, K, V, H, A>
+{
+    /// Gets a reference to the key in the entry.
+    pub fn key(&self) -> &K {
+        &self.map.entry_at(self.index).unwrap().key
+    }
+
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        &self.map.entry_at(self.index).unwrap().value
+    }
+
+    /// Gets a mutable reference to the value in the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.entry_at_mut(self.index).unwrap().value
+    }
+
+    /// Gets the key and a mutable reference to the value in the entry.
+    pub fn key_value_mut(&mut self) -> (&K, &mut V) {
+        let entry = self.map.entry_at_mut(self.index).unwrap();
+        (&entry.key, &mut entry.value)
+    }
+
+    /// Converts the entry into a mutable reference to its value with the lifetime of the map.
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.entry_at_mut(self.index).unwrap().value
+    }
+
+    /// Converts the entry into references to its key and value with the lifetime of the map.
+    pub fn into_key_value(self) -> (&'a K, &'a mut V) {
+        let entry = self.map.entry_at_mut(self.index).unwrap();
+        (&entry.key, &mut entry.value)
+    }
+
+    /// Sets the value of the entry and returns the old value.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Takes the value out of the map, removing the entry.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Takes the key and value out of the map, removing the entry.
+    pub fn remove_entry(self) -> (K, V) {
+        let entry = self.map.remove_at(self.index);
+        self.map.shrink_if_needed();
+        (entry.key, entry.value)
+    }
+}
+
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone, A: Allocator + Clone> Debug
+    for RawOccupiedEntryMut<'_, K, V, H, A>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawOccupiedEntryMut")
+            .field("key", self.key())
+            .field("value", self.get())
+            .finish()
+    }
+}
+
+/// A view into a vacant entry of a `RHMap` found through `RHMap::raw_entry_mut`.
+pub struct RawVacantEntryMut<
+    'a,
+    K: Hash + Eq,
+    V,
+    H: BuildHasher + Clone,
+    A: Allocator + Clone = Global,
+> {
+    map: &'a mut RHMap<K, V, H, A>,
+}
+
+impl<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone>
+    RawVacantEntryMut<'a, K, V, H, A>
+{
+    /// Hashes the key, inserts it with the given value and returns references to both. The key must be the one that
+    /// was looked for, or at least one that isn't in the map yet.
+    pub fn insert(self, key: K, value: V) -> (&'a K, &'a mut V) {
+        let hash = self.map.hash_key(&key);
+        self.insert_hashed_nocheck(hash, key, value)
+    }
+
+    /// Inserts the key under a hash the caller already computed and returns references to the key and value. `hash`
+    /// must be what the map's hasher produces for `key`, otherwise the entry can't be found afterwards. Debug builds
+    /// check this.
+    pub fn insert_hashed_nocheck(self, hash: u64, key: K, value: V) -> (&'a K, &'a mut V) {
+        debug_assert_eq!(
+            hash as usize,
+            self.map.make_hash(&key),
+            "insert_hashed_nocheck was given a hash that doesn't match the key"
+        );
+        let index = self.map.insert_vacant(hash as usize, key, value);
+        let entry = self.map.entry_at_mut(index).unwrap();
+        (&entry.key, &mut entry.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fx_build_hasher::FxBuildHasher;
+    use super::*;
+
+    #[test]
+    fn it_interns_through_raw_entries() {
+        let mut map: RHMap<String, usize, FxBuildHasher> = RHMap::new();
+        let mut intern = |name: &str| {
+            let next = map.len();
+            *map.raw_entry_mut()
+                .from_key(name)
+                .or_insert_with(|| (name.to_string(), next))
+                .1
+        };
+        assert_eq!(intern("a"), 0);
+        assert_eq!(intern("b"), 1);
+        assert_eq!(intern("a"), 0);
+
+        let hash = map.hash_key("b");
+        assert_eq!(
+            map.raw_entry().from_hash(hash, |k| k == "b"),
+            Some((&"b".to_string(), &1))
+        );
+        assert_eq!(map.raw_entry().from_key("c"), None);
+
+        match map.raw_entry_mut().from_key("a") {
+            RawEntryMut::Occupied(entry) => assert_eq!(entry.remove_entry(), ("a".to_string(), 0)),
+            RawEntryMut::Vacant(_) => panic!("a should be interned"),
+        }
+        let hash = map.hash_key("a");
+        match map.raw_entry_mut().from_key_hashed_nocheck(hash, "a") {
+            RawEntryMut::Occupied(_) => panic!("a should be gone"),
+            RawEntryMut::Vacant(entry) => {
+                entry.insert_hashed_nocheck(hash, "a".to_string(), 2);
+            }
+        }
+        assert_eq!(map.get(&"a".to_string()), Some(&2));
+    }
+
+    #[test]
+    fn it_inserts_and_replaces_through_from_hash() {
+        let mut map: RHMap<String, usize, FxBuildHasher> = RHMap::new();
+        let hash = map.hash_key("a");
+        match map.raw_entry_mut().from_hash(hash, |k| k == "a") {
+            RawEntryMut::Occupied(_) => panic!("the map is empty"),
+            RawEntryMut::Vacant(entry) => {
+                let (key, value) = entry.insert("a".to_string(), 1);
+                assert_eq!((key.as_str(), *value), ("a", 1));
+            }
+        }
+
+        match map.raw_entry_mut().from_hash(hash, |k| k == "a") {
+            RawEntryMut::Occupied(mut entry) => {
+                assert_eq!(entry.key(), "a");
+                assert_eq!(entry.insert(2), 1);
+                *entry.get_mut() += 1;
+            }
+            RawEntryMut::Vacant(_) => panic!("a was just inserted"),
+        }
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&"a".to_string()), Some(&3));
+
+        // A closure that never matches sees the vacant side even though the hash is taken.
+        assert!(matches!(
+            map.raw_entry_mut().from_hash(hash, |_| false),
+            RawEntryMut::Vacant(_)
+        ));
+    }
+
+    #[test]
+    fn it_misses_when_given_the_wrong_hash() {
+        let mut map: RHMap<String, usize, FxBuildHasher> = RHMap::new();
+        for (i, name) in ["a", "b", "c", "d"].iter().enumerate() {
+            map.insert(name.to_string(), i);
+        }
+        let wrong = map.hash_key("b").wrapping_add(1);
+        assert_eq!(map.raw_entry().from_hash(wrong, |k| k == "b"), None);
+        assert_eq!(map.raw_entry().from_key_hashed_nocheck(wrong, "b"), None);
+        assert!(matches!(
+            map.raw_entry_mut().from_key_hashed_nocheck(wrong, "b"),
+            RawEntryMut::Vacant(_)
+        ));
+        assert_eq!(map.raw_entry().from_key("b"), Some((&"b".to_string(), &1)));
+    }
+}
@@ -0,0 +1,99 @@
+use super::hashmap::RHMap;
+use allocator_api2::alloc::Allocator;
+use core::hash::{BuildHasher, Hash};
+use rkyv::{
+    collections::swiss_table::{ArchivedHashMap, HashMapResolver},
+    rancor::{Fallible, Source},
+    ser::{Allocator as ArenaAllocator, Writer},
+    Archive, Archived, Deserialize, Place, Serialize,
+};
+
+/// The archived form of a `RHMap<K, V, H>`: a hash table laid out in the archive bytes themselves, hashed with rkyv's
+/// 64-bit Fx hasher whatever `H` the map had. Lookups run directly against the buffer returned by `rkyv::access`, so
+/// a large precomputed table can be loaded (or memory mapped) and queried without deserializing anything.
+pub type ArchivedFxHashMap<K, V> = ArchivedHashMap<Archived<K>, Archived<V>>;
+
+/// The archived table is filled to the same 7/8 rkyv uses for the std and hashbrown maps.
+const ARCHIVED_LOAD_FACTOR: (usize, usize) = (7, 8);
+
+impl<K: Archive + Hash + Eq, V: Archive, H: BuildHasher + Clone, A: Allocator + Clone> Archive
+    for RHMap<K, V, H, A>
+where
+    K::Archived: Hash + Eq,
+{
+    type Archived = ArchivedHashMap<K::Archived, V::Archived>;
+    type Resolver = HashMapResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedHashMap::resolve_from_len(self.len(), ARCHIVED_LOAD_FACTOR, resolver, out);
+    }
+}
+
+impl<K, V, H, A, S> Serialize<S> for RHMap<K, V, H, A>
+where
+    K: Serialize<S> + Hash + Eq,
+    K::Archived: Hash + Eq,
+    V: Serialize<S>,
+    H: BuildHasher + Clone,
+    A: Allocator + Clone,
+    S: Fallible + Writer + ArenaAllocator + ?Sized,
+    S::Error: Source,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        ArchivedHashMap::<K::Archived, V::Archived>::serialize_from_iter::<_, _, _, K, V, _>(
+            self.iter(),
+            ARCHIVED_LOAD_FACTOR,
+            serializer,
+        )
+    }
+}
+
+impl<K, V, H, D> Deserialize<RHMap<K, V, H>, D> for ArchivedHashMap<K::Archived, V::Archived>
+where
+    K: Archive + Hash + Eq,
+    K::Archived: Deserialize<K, D> + Hash + Eq,
+    V: Archive,
+    V::Archived: Deserialize<V, D>,
+    H: BuildHasher + Clone + Default,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<RHMap<K, V, H>, D::Error> {
+        let mut map = RHMap::with_capacity_and_hasher(self.len(), H::default());
+        for (key, value) in self.iter() {
+            map.insert(
+                key.deserialize(deserializer)?,
+                value.deserialize(deserializer)?,
+            );
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use rkyv::{rancor::Error, string::ArchivedString};
+
+    #[test]
+    fn it_looks_up_keys_in_the_archive_and_round_trips() {
+        let mut map: FxHashMap<String, u32> = FxHashMap::new();
+        for x in 0..1000 {
+            map.insert(format!("key{}", x), x);
+        }
+        let bytes = rkyv::to_bytes::<Error>(&map).unwrap();
+
+        let archived = rkyv::access::<ArchivedFxHashMap<String, u32>, Error>(&bytes).unwrap();
+        assert_eq!(archived.len(), 1000);
+        assert_eq!(
+            archived.get("key42").map(|value| value.to_native()),
+            Some(42)
+        );
+        assert!(archived.get("missing").is_none());
+        assert!(archived.keys().any(|key: &ArchivedString| key == "key999"));
+
+        let back: FxHashMap<String, u32> = rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(back.len(), 1000);
+        assert_eq!(back.get("key7"), Some(&7));
+    }
+}
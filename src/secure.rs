@@ -0,0 +1,30 @@
+use super::hashmap::RHMap;
+
+/// std's SipHash-1-3 builder, keyed with random keys from the OS. Slower than Fx but resistant to HashDoS: untrusted
+/// input can't be picked to collide without knowing the keys.
+pub use std::collections::hash_map::RandomState as SecureBuildHasher;
+
+/// A `RHMap` hashing with SipHash-1-3 instead of Fx: the same Robin Hood table and probing, with std's flood
+/// resistance. Create one with `SecureHashMap::default()`.
+pub type SecureHashMap<K, V> = RHMap<K, V, SecureBuildHasher>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::hash::BuildHasher;
+
+    #[test]
+    fn it_hashes_with_random_keys() {
+        let mut map: SecureHashMap<&str, i32> = SecureHashMap::default();
+        map.insert("a", 1);
+        map.insert("b", 2);
+
+        assert_eq!(map.get(&"a"), Some(&1));
+        assert_eq!(map.len(), 2);
+        // Each builder draws its own keys.
+        assert_ne!(
+            map.hasher().hash_one("a"),
+            SecureBuildHasher::new().hash_one("a")
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use super::hashmap::RHMap;
+use super::hashset::RHSet;
+use allocator_api2::alloc::{Allocator, Global};
+use core::{
+    cmp::min,
+    fmt,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+    mem,
+};
+use serde::{
+    de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// The most memory a length announced by the input may reserve up front. Inputs can claim any length, so beyond this
+/// the table grows as entries actually arrive.
+const MAX_PRESIZE_BYTES: usize = 1024 * 1024;
+
+/// How many elements of type `T` to reserve room for, given the length the input announced.
+pub(crate) fn presize<T>(hint: Option<usize>) -> usize {
+    min(
+        hint.unwrap_or(0),
+        MAX_PRESIZE_BYTES / mem::size_of::<T>().max(1),
+    )
+}
+
+/// Serializes as a map, in the map's iteration order.
+impl<K: Hash + Eq + Serialize, V: Serialize, H: BuildHasher + Clone, A: Allocator + Clone> Serialize
+    for RHMap<K, V, H, A>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self)
+    }
+}
+
+/// Serializes as a sequence, in the set's iteration order.
+impl<T: Hash + Eq + Serialize, H: BuildHasher + Clone> Serialize for RHSet<T, H> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self)
+    }
+}
+
+/// Serializes only the keys of a map, as a sequence in the map's iteration order. This is the same format a `RHSet`
+/// serializes to, so the output loads back as a set for sharing membership without the values.
+pub struct KeysOnly<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone = Global>(
+    pub &'a RHMap<K, V, H, A>,
+);
+
+impl<K: Hash + Eq + Serialize, V, H: BuildHasher + Clone, A: Allocator + Clone> Serialize
+    for KeysOnly<'_, K, V, H, A>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.keys())
+    }
+}
+
+/// Serializes only the values of a map, as a sequence in the map's iteration order, i.e. the same order `KeysOnly`
+/// writes the keys in. `AttachValues` loads them back onto those keys.
+pub struct ValuesOnly<'a, K: Hash + Eq, V, H: BuildHasher + Clone, A: Allocator + Clone = Global>(
+    pub &'a RHMap<K, V, H, A>,
+);
+
+impl<K: Hash + Eq, V: Serialize, H: BuildHasher + Clone, A: Allocator + Clone> Serialize
+    for ValuesOnly<'_, K, V, H, A>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.values())
+    }
+}
+
+/// Deserializes a sequence written by `ValuesOnly` and pairs each value with the next key from an existing key set,
+/// building a `RHMap` with the given hasher builder. The keys must come in the order `KeysOnly` wrote them, e.g. from
+/// a `Vec<K>` it was loaded into; a `RHSet` of them needn't iterate in that order. Fails unless there's exactly one
+/// value per key.
+pub struct AttachValues<I, V, H> {
+    keys: I,
+    hasher_builder: H,
+    _marker: PhantomData<V>,
+}
+
+impl<I: Iterator, V, H> AttachValues<I, V, H> {
+    /// Creates a seed that attaches the values it reads to `keys`, in order.
+    pub fn new<T: IntoIterator<IntoIter = I>>(keys: T, hasher_builder: H) -> Self {
+        Self {
+            keys: keys.into_iter(),
+            hasher_builder,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, K: Hash + Eq, I: Iterator<Item = K>, V: Deserialize<'de>, H: BuildHasher + Clone>
+    DeserializeSeed<'de> for AttachValues<I, V, H>
+{
+    type Value = RHMap<K, V, H>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, K: Hash + Eq, I: Iterator<Item = K>, V: Deserialize<'de>, H: BuildHasher + Clone>
+    Visitor<'de> for AttachValues<I, V, H>
+{
+    type Value = RHMap<K, V, H>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequence with one value per key")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(mut self, mut access: S) -> Result<Self::Value, S::Error> {
+        let capacity = presize::<(K, V)>(access.size_hint());
+        let mut map = RHMap::with_capacity_and_hasher(capacity, self.hasher_builder.clone());
+        let mut count = 0;
+        while let Some(key) = self.keys.next() {
+            let value = access
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(count, &self))?;
+            map.insert(key, value);
+            count += 1;
+        }
+        if access.next_element::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(count + 1, &self));
+        }
+        Ok(map)
+    }
+}
+
+/// Deserializes a `RHMap` with the given hasher builder, for hashers without a `Default` (e.g. seeded ones). The table
+/// is sized for the number of entries the input announces up front, so it doesn't grow while they are inserted.
+pub struct MapSeed<K, V, H> {
+    hasher_builder: H,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K, V, H> MapSeed<K, V, H> {
+    /// Creates a seed that deserializes into a map with the given hasher builder.
+    pub fn new(hasher_builder: H) -> Self {
+        Self {
+            hasher_builder,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, K: Hash + Eq + Deserialize<'de>, V: Deserialize<'de>, H: BuildHasher + Clone>
+    DeserializeSeed<'de> for MapSeed<K, V, H>
+{
+    type Value = RHMap<K, V, H>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, K: Hash + Eq + Deserialize<'de>, V: Deserialize<'de>, H: BuildHasher + Clone> Visitor<'de>
+    for MapSeed<K, V, H>
+{
+    type Value = RHMap<K, V, H>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Self::Value, M::Error> {
+        let capacity = presize::<(K, V)>(access.size_hint());
+        let mut map = RHMap::with_capacity_and_hasher(capacity, self.hasher_builder);
+        while let Some((key, value)) = access.next_entry()? {
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+impl<
+        'de,
+        K: Hash + Eq + Deserialize<'de>,
+        V: Deserialize<'de>,
+        H: BuildHasher + Clone + Default,
+    > Deserialize<'de> for RHMap<K, V, H>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        MapSeed::new(H::default()).deserialize(deserializer)
+    }
+}
+
+/// Deserializes a `RHSet` with the given hasher builder, pre-sized like `MapSeed` does.
+pub struct SetSeed<T, H> {
+    hasher_builder: H,
+    _marker: PhantomData<T>,
+}
+
+impl<T, H> SetSeed<T, H> {
+    /// Creates a seed that deserializes into a set with the given hasher builder.
+    pub fn new(hasher_builder: H) -> Self {
+        Self {
+            hasher_builder,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: Hash + Eq + Deserialize<'de>, H: BuildHasher + Clone> DeserializeSeed<'de>
+    for SetSeed<T, H>
+{
+    type Value = RHSet<T, H>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T: Hash + Eq + Deserialize<'de>, H: BuildHasher + Clone> Visitor<'de> for SetSeed<T, H> {
+    type Value = RHSet<T, H>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequence")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut access: S) -> Result<Self::Value, S::Error> {
+        let capacity = presize::<T>(access.size_hint());
+        let mut set = RHSet::with_capacity_and_hasher(capacity, self.hasher_builder);
+        while let Some(value) = access.next_element()? {
+            set.insert(value);
+        }
+        Ok(set)
+    }
+}
+
+impl<'de, T: Hash + Eq + Deserialize<'de>, H: BuildHasher + Clone + Default> Deserialize<'de>
+    for RHSet<T, H>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SetSeed::new(H::default()).deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn it_round_trips_maps_and_sets() {
+        let mut map: FxHashMap<String, u32> = FxHashMap::new();
+        map.insert("a".to_string(), 1);
+        map.insert("b".to_string(), 2);
+        let json = serde_json::to_string(&map).unwrap();
+        let back: FxHashMap<String, u32> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(back.len(), 2);
+        assert_eq!(back.get("b"), Some(&2));
+        let set: FxHashSet<u8> = serde_json::from_str("[3, 1, 3]").unwrap();
+        assert_eq!(serde_json::to_string(&set).unwrap().len(), "[1,3]".len());
+        assert!(set.contains(&3));
+    }
+
+    #[test]
+    fn it_deserializes_with_a_given_hasher() {
+        let mut source = FxHashMap::new();
+        for x in 0..100u32 {
+            source.insert(x, x);
+        }
+        let json = serde_json::to_string(&source).unwrap();
+        let mut deserializer = serde_json::Deserializer::from_str(&json);
+        let map: FxHashMap<u32, u32> = MapSeed::new(FxBuildHasher::new())
+            .deserialize(&mut deserializer)
+            .unwrap();
+
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get(&42), Some(&42));
+        assert_eq!(presize::<(u64, u64)>(Some(usize::MAX)), 65_536);
+    }
+
+    #[test]
+    fn it_splits_keys_from_values_and_joins_them_back() {
+        let mut map: FxHashMap<String, u32> = FxHashMap::new();
+        for x in 0..50u32 {
+            map.insert(x.to_string(), x * 2);
+        }
+        let keys_json = serde_json::to_string(&KeysOnly(&map)).unwrap();
+        let values_json = serde_json::to_string(&ValuesOnly(&map)).unwrap();
+        assert!(values_json.contains("98") && !keys_json.contains("98"));
+
+        let members: FxHashSet<String> = serde_json::from_str(&keys_json).unwrap();
+        assert_eq!(members.len(), 50);
+        assert!(members.contains("49"));
+
+        let keys: Vec<String> = serde_json::from_str(&keys_json).unwrap();
+        let mut deserializer = serde_json::Deserializer::from_str(&values_json);
+        let back: FxHashMap<String, u32> = AttachValues::new(keys.clone(), FxBuildHasher::new())
+            .deserialize(&mut deserializer)
+            .unwrap();
+        assert_eq!(back.len(), 50);
+        assert!(map.iter().all(|(k, v)| back.get(k) == Some(v)));
+
+        let attach = |keys: Vec<String>, json: &str| {
+            AttachValues::<_, u32, _>::new(keys, FxBuildHasher::new())
+                .deserialize(&mut serde_json::Deserializer::from_str(json))
+        };
+        assert!(attach(keys[1..].to_vec(), &values_json).is_err());
+        assert!(attach(keys, "[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Serializes a `RHMap` as a sequence of `(key, value)` pairs rather than as a map, for formats like JSON that only
+//! allow string keys in maps. Maps keyed by structs or tuples then round-trip without stringifying the keys:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Routes {
+//!     #[serde(with = "rhmap::serde_seq")]
+//!     costs: FxHashMap<(u32, u32), f64>,
+//! }
+//! ```
+
+use super::hashmap::RHMap;
+use super::serde::presize;
+use allocator_api2::alloc::Allocator;
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
+use serde::{
+    de::{SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// Serializes the map as a sequence of `(key, value)` pairs, in the map's iteration order.
+pub fn serialize<K, V, H, A, S>(map: &RHMap<K, V, H, A>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Hash + Eq + Serialize,
+    V: Serialize,
+    H: BuildHasher + Clone,
+    A: Allocator + Clone,
+    S: Serializer,
+{
+    serializer.collect_seq(map)
+}
+
+/// Deserializes a map from a sequence of `(key, value)` pairs. Later pairs win over earlier ones with the same key.
+pub fn deserialize<'de, K, V, H, D>(deserializer: D) -> Result<RHMap<K, V, H>, D::Error>
+where
+    K: Hash + Eq + Deserialize<'de>,
+    V: Deserialize<'de>,
+    H: BuildHasher + Clone + Default,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_seq(PairsVisitor(PhantomData))
+}
+
+struct PairsVisitor<K, V, H>(PhantomData<(K, V, H)>);
+
+impl<
+        'de,
+        K: Hash + Eq + Deserialize<'de>,
+        V: Deserialize<'de>,
+        H: BuildHasher + Clone + Default,
+    > Visitor<'de> for PairsVisitor<K, V, H>
+{
+    type Value = RHMap<K, V, H>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequence of key value pairs")
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut access: S) -> Result<Self::Value, S::Error> {
+        let capacity = presize::<(K, V)>(access.size_hint());
+        let mut map = RHMap::with_capacity_and_hasher(capacity, H::default());
+        while let Some((key, value)) = access.next_element()? {
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn it_round_trips_tuple_keys_through_json() {
+        let mut costs: FxHashMap<(u32, u32), u8> = FxHashMap::new();
+        costs.insert((1, 2), 3);
+        costs.insert((4, 5), 6);
+
+        let mut json = Vec::new();
+        serialize(&costs, &mut serde_json::Serializer::new(&mut json)).unwrap();
+        assert!(serde_json::to_string(&costs).is_err());
+        let back: FxHashMap<(u32, u32), u8> =
+            deserialize(&mut serde_json::Deserializer::from_slice(&json)).unwrap();
+
+        assert_eq!(back.len(), 2);
+        assert_eq!(back.get(&(4, 5)), Some(&6));
+        let pairs: FxHashMap<(u32, u32), u8> = deserialize(
+            &mut serde_json::Deserializer::from_str("[[[1, 2], 3], [[1, 2], 4]]"),
+        )
+        .unwrap();
+        assert_eq!(pairs.get(&(1, 2)), Some(&4));
+    }
+}
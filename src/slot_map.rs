@@ -0,0 +1,342 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::positions::PositionTable;
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    mem,
+};
+
+/// A `SlotMap` with the default Fx Hasher, so it can be named without spelling out the hasher.
+pub type FxSlotMap<K, V> = SlotMap<K, V, FxBuildHasher>;
+
+/// A handle to an entry of a `SlotMap`: the slot it lives in and the generation of that slot when it was handed
+/// out. Once the entry is removed, the slot's generation moves on, so the handle stops resolving even after the slot
+/// is reused for another entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SlotHandle {
+    index: u32,
+    generation: u32,
+}
+
+enum Slot<K, V> {
+    Occupied {
+        hash: usize,
+        key: K,
+        value: V,
+    },
+    /// Links the free slots into a list, `NO_FREE` at the end.
+    Vacant {
+        next_free: u32,
+    },
+}
+
+/// End of the free list.
+const NO_FREE: u32 = u32::MAX;
+
+struct GenerationalSlot<K, V> {
+    /// Bumped every time the slot is vacated.
+    generation: u32,
+    slot: Slot<K, V>,
+}
+
+/// A map whose entries can be found both by key and by a small `Copy` handle that `insert` returns. Handles stay
+/// valid as other entries come and go, and a handle to a removed entry never resolves to whatever entry reuses its
+/// slot, e.g. for entity stores where other structures refer to entities by handle.
+///
+/// Entries live in a slab of slots, reused through a free list, and a Robin Hood table of slot positions finds them
+/// by key.
+pub struct SlotMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    slots: Vec<GenerationalSlot<K, V>>,
+    /// Finds the slot of a key.
+    positions: PositionTable,
+    /// The first vacant slot to reuse, `NO_FREE` if every slot is occupied.
+    free_head: u32,
+    num_items: usize,
+    hasher_builder: H,
+}
+
+impl<K: Hash + Eq, V> SlotMap<K, V, FxBuildHasher> {
+    /// Creates an empty `SlotMap` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> Default for SlotMap<K, V, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> SlotMap<K, V, H> {
+    /// Creates an empty `SlotMap` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            slots: Vec::new(),
+            positions: PositionTable::new(),
+            free_head: NO_FREE,
+            num_items: 0,
+            hasher_builder,
+        }
+    }
+
+    /// Inserts a key value pair and returns the handle of its entry. An existing key keeps its handle and only has its
+    /// value replaced.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map would need more than `u32::MAX` slots.
+    pub fn insert(&mut self, key: K, value: V) -> SlotHandle {
+        let hash = self.hasher_builder.hash_one(&key) as usize;
+        if let Some(i) = self.find(hash, &key) {
+            if let Slot::Occupied { value: old, .. } = &mut self.slots[i].slot {
+                *old = value;
+            }
+            return self.handle_of(i);
+        }
+
+        let occupied = Slot::Occupied { hash, key, value };
+        let i = if self.free_head != NO_FREE {
+            let i = self.free_head as usize;
+            if let Slot::Vacant { next_free } = mem::replace(&mut self.slots[i].slot, occupied) {
+                self.free_head = next_free;
+            }
+            i
+        } else {
+            assert!(
+                self.slots.len() < NO_FREE as usize,
+                "SlotMap can't hold more than u32::MAX entries"
+            );
+            self.slots.push(GenerationalSlot {
+                generation: 0,
+                slot: occupied,
+            });
+            self.slots.len() - 1
+        };
+
+        self.positions.insert(hash, i);
+        self.num_items += 1;
+        self.handle_of(i)
+    }
+
+    /// Gets the value associated with the key.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<&V> {
+        let i = self.find(self.hasher_builder.hash_one(key) as usize, key)?;
+        self.entry_at(i).map(|(_, value)| value)
+    }
+
+    /// Gets a mutable reference to the value associated with the key.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&mut V> {
+        let i = self.find(self.hasher_builder.hash_one(key) as usize, key)?;
+        self.value_at_mut(i)
+    }
+
+    /// Gets the handle of the entry with the given key.
+    pub fn find_handle<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<SlotHandle> {
+        let i = self.find(self.hasher_builder.hash_one(key) as usize, key)?;
+        Some(self.handle_of(i))
+    }
+
+    /// Gets the key and value of the entry behind a handle, `None` once that entry has been removed.
+    pub fn get_by_handle(&self, handle: SlotHandle) -> Option<(&K, &V)> {
+        let i = self.check(handle)?;
+        self.entry_at(i)
+    }
+
+    /// Gets a mutable reference to the value of the entry behind a handle, `None` once that entry has been removed.
+    pub fn get_mut_by_handle(&mut self, handle: SlotHandle) -> Option<&mut V> {
+        let i = self.check(handle)?;
+        self.value_at_mut(i)
+    }
+
+    /// Checks to see if the provided key is associated with any value.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> bool {
+        self.find_handle(key).is_some()
+    }
+
+    /// Checks to see if the entry behind a handle is still in the map.
+    pub fn contains_handle(&self, handle: SlotHandle) -> bool {
+        self.check(handle).is_some()
+    }
+
+    /// Removes the entry with the given key and returns its value, if there was one. Its handle stops resolving.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        let i = self.find(self.hasher_builder.hash_one(key) as usize, key)?;
+        self.remove_at(i).map(|(_, value)| value)
+    }
+
+    /// Removes the entry behind a handle and returns its key and value, `None` if it was already removed.
+    pub fn remove_by_handle(&mut self, handle: SlotHandle) -> Option<(K, V)> {
+        let i = self.check(handle)?;
+        self.remove_at(i)
+    }
+
+    /// Removes every entry. Every handle handed out so far stops resolving, but the slots are kept for reuse.
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.free_head = NO_FREE;
+        for (i, slot) in self.slots.iter_mut().enumerate().rev() {
+            if let Slot::Occupied { .. } = slot.slot {
+                slot.generation = slot.generation.wrapping_add(1);
+            }
+            slot.slot = Slot::Vacant {
+                next_free: self.free_head,
+            };
+            self.free_head = i as u32;
+        }
+        self.num_items = 0;
+    }
+
+    /// Gets the length / number of entries of the map.
+    pub fn len(&self) -> usize {
+        self.num_items
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+
+    /// Gets a reference to the hasher builder.
+    pub fn hasher(&self) -> &H {
+        &self.hasher_builder
+    }
+
+    /// Iterates over every entry along with its handle, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (SlotHandle, &K, &V)> + '_ {
+        (0..self.slots.len()).filter_map(move |i| {
+            self.entry_at(i)
+                .map(|(key, value)| (self.handle_of(i), key, value))
+        })
+    }
+
+    fn find<Q: ?Sized + Equivalent<K>>(&self, hash: usize, key: &Q) -> Option<usize> {
+        let slots = &self.slots;
+        let slot = self.positions.find(
+            hash,
+            |i| matches!(&slots[i].slot, Slot::Occupied { key: k, .. } if key.equivalent(k)),
+        )?;
+        Some(self.positions.position(slot))
+    }
+
+    /// The slot index behind a handle, if the handle is still current.
+    fn check(&self, handle: SlotHandle) -> Option<usize> {
+        let i = handle.index as usize;
+        let slot = self.slots.get(i)?;
+        let occupied = matches!(slot.slot, Slot::Occupied { .. });
+        (occupied && slot.generation == handle.generation).then_some(i)
+    }
+
+    fn handle_of(&self, i: usize) -> SlotHandle {
+        SlotHandle {
+            index: i as u32,
+            generation: self.slots[i].generation,
+        }
+    }
+
+    fn entry_at(&self, i: usize) -> Option<(&K, &V)> {
+        match &self.slots[i].slot {
+            Slot::Occupied { key, value, .. } => Some((key, value)),
+            Slot::Vacant { .. } => None,
+        }
+    }
+
+    fn value_at_mut(&mut self, i: usize) -> Option<&mut V> {
+        match &mut self.slots[i].slot {
+            Slot::Occupied { value, .. } => Some(value),
+            Slot::Vacant { .. } => None,
+        }
+    }
+
+    /// Vacates an occupied slot, bumping its generation and pushing it onto the free list.
+    fn remove_at(&mut self, i: usize) -> Option<(K, V)> {
+        let vacant = Slot::Vacant {
+            next_free: self.free_head,
+        };
+        let slot = &mut self.slots[i];
+        let (hash, key, value) = match mem::replace(&mut slot.slot, vacant) {
+            Slot::Occupied { hash, key, value } => (hash, key, value),
+            Slot::Vacant { next_free } => {
+                slot.slot = Slot::Vacant { next_free };
+                return None;
+            }
+        };
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_head = i as u32;
+
+        let position_slot = self.positions.slot_of(hash, i);
+        self.positions.remove(position_slot);
+        self.num_items -= 1;
+        Some((key, value))
+    }
+}
+
+/// Formats the map like the std maps do (`{key: value, ...}`), in slot order.
+impl<K: Hash + Eq + Debug, V: Debug, H: BuildHasher + Clone> Debug for SlotMap<K, V, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|(_, key, value)| (key, value)))
+            .finish()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> Extend<(K, V)> for SlotMap<K, V, H> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        for (key, value) in pairs {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone + Default> FromIterator<(K, V)> for SlotMap<K, V, H> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let mut map = Self::default();
+        map.extend(pairs);
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_finds_entries_by_key_and_handle() {
+        let mut players = FxSlotMap::new();
+        let alice = players.insert("alice", 10);
+        let bob = players.insert("bob", 20);
+
+        assert_eq!(players.insert("alice", 11), alice);
+        assert_eq!(players.get_by_handle(alice), Some((&"alice", &11)));
+        assert_eq!(players.find_handle("bob"), Some(bob));
+        *players.get_mut_by_handle(bob).unwrap() += 1;
+        assert_eq!(players.get("bob"), Some(&21));
+        assert_eq!(players.remove_by_handle(alice), Some(("alice", 11)));
+        assert_eq!(players.get("alice"), None);
+        assert_eq!(players.len(), 1);
+    }
+
+    #[test]
+    fn it_never_resolves_stale_handles_to_reused_slots() {
+        let mut map = FxSlotMap::new();
+        let old = map.insert(1, 'a');
+        assert_eq!(map.remove(&1), Some('a'));
+
+        // The new entry reuses the slot of the old one, under a new generation.
+        let new = map.insert(2, 'b');
+        assert_ne!(old, new);
+        assert!(!map.contains_handle(old));
+        assert_eq!(map.get_by_handle(old), None);
+        assert_eq!(map.remove_by_handle(old), None);
+        assert_eq!(map.get_by_handle(new), Some((&2, &'b')));
+
+        map.clear();
+        assert!(!map.contains_handle(new));
+        let newest = map.insert(3, 'c');
+        assert_eq!(map.get_by_handle(newest), Some((&3, &'c')));
+        assert_eq!(map.iter().count(), 1);
+    }
+}
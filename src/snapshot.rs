@@ -0,0 +1,321 @@
+use super::hashmap::{RHMap, MAX_LOAD_FACTOR, MIN_LOAD_FACTOR};
+use super::map_entry::Entry;
+use allocator_api2::alloc::Global;
+use std::{
+    convert::TryInto,
+    hash::{BuildHasher, Hash},
+    io::{self, Read, Write},
+};
+
+/// The first bytes of every snapshot.
+pub(crate) const MAGIC: [u8; 8] = *b"RHMAPSNP";
+/// Bumped whenever the layout below changes, so old readers refuse new snapshots rather than misreading them.
+pub(crate) const VERSION: u32 = 1;
+/// Size of the header: magic, version, key size, value size, load factor, bucket count, item count and hasher
+/// fingerprint.
+pub(crate) const HEADER_LEN: usize = 48;
+/// The key whose hash is stored in the header, so a snapshot can't be loaded with a hasher that places keys
+/// differently (e.g. a differently seeded one).
+const FINGERPRINT_KEY: u64 = 0x7268_6d61_705f_736e;
+/// How many bytes of records are buffered before being handed to the writer.
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Types that snapshots store at a fixed width, in little endian. The fixed width gives every slot of the table a
+/// record of the same size, so the record of any slot can be found in a snapshot file by multiplying.
+pub trait FixedSize: Sized {
+    /// The number of bytes the value takes up.
+    const SIZE: usize;
+
+    /// Writes the value into `out`, which is exactly `SIZE` bytes long.
+    fn write_le(&self, out: &mut [u8]);
+
+    /// Reads a value back from `bytes`, which is exactly `SIZE` bytes long.
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_fixed_size {
+    ($($t:ty),*) => {
+        $(
+            impl FixedSize for $t {
+                const SIZE: usize = core::mem::size_of::<$t>();
+
+                fn write_le(&self, out: &mut [u8]) {
+                    out.copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_le(bytes: &[u8]) -> Self {
+                    <$t>::from_le_bytes(bytes.try_into().expect("a record field has the size of its type"))
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_size!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl<const N: usize> FixedSize for [u8; N] {
+    const SIZE: usize = N;
+
+    fn write_le(&self, out: &mut [u8]) {
+        out.copy_from_slice(self);
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        bytes
+            .try_into()
+            .expect("a record field has the size of its type")
+    }
+}
+
+/// The layout metadata at the start of a snapshot.
+pub(crate) struct Header {
+    pub key_size: usize,
+    pub value_size: usize,
+    pub load_factor: f32,
+    pub buckets: usize,
+    pub num_items: usize,
+    pub fingerprint: u64,
+}
+
+impl Header {
+    fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0; HEADER_LEN];
+        bytes[0..8].copy_from_slice(&MAGIC);
+        bytes[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[12..16].copy_from_slice(&(self.key_size as u32).to_le_bytes());
+        bytes[16..20].copy_from_slice(&(self.value_size as u32).to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.load_factor.to_le_bytes());
+        bytes[24..32].copy_from_slice(&(self.buckets as u64).to_le_bytes());
+        bytes[32..40].copy_from_slice(&(self.num_items as u64).to_le_bytes());
+        bytes[40..48].copy_from_slice(&self.fingerprint.to_le_bytes());
+        bytes
+    }
+
+    /// Parses and validates the header of a snapshot of a `K` to `V` map that is to be read with `hasher_builder`.
+    pub(crate) fn parse<K: FixedSize + Hash, V: FixedSize, H: BuildHasher>(
+        bytes: &[u8],
+        hasher_builder: &H,
+    ) -> io::Result<Self> {
+        if bytes.len() < HEADER_LEN || bytes[0..8] != MAGIC {
+            return Err(invalid("not a rhmap snapshot"));
+        }
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        if u32_at(8) != VERSION {
+            return Err(invalid("unsupported snapshot format version"));
+        }
+
+        let header = Header {
+            key_size: u32_at(12) as usize,
+            value_size: u32_at(16) as usize,
+            load_factor: f32::from_bits(u32_at(20)),
+            buckets: u64_at(24)
+                .try_into()
+                .map_err(|_| invalid("snapshot too large for this platform"))?,
+            num_items: u64_at(32)
+                .try_into()
+                .map_err(|_| invalid("snapshot too large for this platform"))?,
+            fingerprint: u64_at(40),
+        };
+        if header.key_size != K::SIZE || header.value_size != V::SIZE {
+            return Err(invalid(
+                "snapshot was written for different key or value types",
+            ));
+        }
+        if header.fingerprint != fingerprint(hasher_builder) {
+            return Err(invalid("snapshot was written with a different hasher"));
+        }
+        if !(MIN_LOAD_FACTOR..=MAX_LOAD_FACTOR).contains(&header.load_factor) {
+            return Err(invalid("snapshot has an out of range load factor"));
+        }
+        if (header.buckets != 0 && !header.buckets.is_power_of_two())
+            || header.num_items > header.buckets
+        {
+            return Err(invalid("snapshot has an inconsistent table size"));
+        }
+        Ok(header)
+    }
+
+    /// The size of one slot's record: an occupied flag, the stored hash, the key and the value.
+    pub(crate) fn record_len(&self) -> usize {
+        1 + 8 + self.key_size + self.value_size
+    }
+}
+
+pub(crate) fn fingerprint<H: BuildHasher>(hasher_builder: &H) -> u64 {
+    hasher_builder.hash_one(FINGERPRINT_KEY)
+}
+
+pub(crate) fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl<K: Hash + Eq + FixedSize, V: FixedSize, H: BuildHasher + Clone> RHMap<K, V, H> {
+    /// Writes a snapshot of the map: a versioned header with the table layout, followed by one fixed size record per
+    /// slot. `read_from` puts every entry straight back into its slot, which is much faster than inserting them.
+    ///
+    /// Stored hashes are part of the snapshot, so it can only be read back with a hasher that hashes the same way;
+    /// this is checked when reading. Keep in mind that with the `random-seed` feature every `FxBuildHasher` differs.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let header = Header {
+            key_size: K::SIZE,
+            value_size: V::SIZE,
+            load_factor: self.load_factor(),
+            buckets: self.bucket_count(),
+            num_items: self.len(),
+            fingerprint: fingerprint(self.hasher()),
+        };
+        writer.write_all(&header.to_bytes())?;
+
+        let record_len = header.record_len();
+        let mut chunk = Vec::with_capacity(CHUNK_LEN + record_len);
+        for i in 0..header.buckets {
+            let start = chunk.len();
+            chunk.resize(start + record_len, 0);
+            if let Some(entry) = self.entry_at(i) {
+                let record = &mut chunk[start..];
+                record[0] = 1;
+                record[1..9].copy_from_slice(&(entry.hash as u64).to_le_bytes());
+                entry.key.write_le(&mut record[9..9 + K::SIZE]);
+                entry.value.write_le(&mut record[9 + K::SIZE..]);
+            }
+            if chunk.len() >= CHUNK_LEN {
+                writer.write_all(&chunk)?;
+                chunk.clear();
+            }
+        }
+        writer.write_all(&chunk)
+    }
+
+    /// Reads a snapshot written by `write_to`, hashing with the given hasher builder. Fails with `InvalidData` if the
+    /// snapshot is of another format version, key or value type or hasher, or if its table is inconsistent.
+    ///
+    /// The records are read in full before the table is allocated, so a corrupt header can't make this allocate more
+    /// than the input actually holds.
+    pub fn read_from_with_hasher<R: Read>(reader: &mut R, hasher_builder: H) -> io::Result<Self> {
+        let mut header = [0; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        let header = Header::parse::<K, V, H>(&header, &hasher_builder)?;
+
+        let record_len = header.record_len();
+        let records_len = header
+            .buckets
+            .checked_mul(record_len)
+            .ok_or_else(|| invalid("snapshot has an inconsistent table size"))?;
+        // `read_to_end` grows the buffer as data arrives rather than trusting the length up front.
+        let mut records = Vec::new();
+        reader.take(records_len as u64).read_to_end(&mut records)?;
+        if records.len() != records_len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "snapshot is truncated",
+            ));
+        }
+
+        let mut map = RHMap::with_buckets_in(header.buckets, hasher_builder, Global);
+        map.set_load_factor(header.load_factor);
+        for (i, record) in records.chunks_exact(record_len).enumerate() {
+            match record[0] {
+                0 => {}
+                1 => {
+                    let hash = u64::from_le_bytes(record[1..9].try_into().unwrap()) as usize;
+                    let key = K::read_le(&record[9..9 + K::SIZE]);
+                    // An entry under the wrong hash would sit in the table but never be found.
+                    if map.make_hash(&key) != hash {
+                        return Err(invalid(
+                            "snapshot has a record whose hash doesn't match its key",
+                        ));
+                    }
+                    let value = V::read_le(&record[9 + K::SIZE..]);
+                    map.restore_at(i, Entry::new(key, value, hash));
+                }
+                _ => return Err(invalid("snapshot has a corrupt record")),
+            }
+        }
+
+        if map.len() != header.num_items {
+            return Err(invalid("snapshot item count doesn't match its records"));
+        }
+        map.check_invariants().map_err(invalid)?;
+        Ok(map)
+    }
+}
+
+impl<K: Hash + Eq + FixedSize, V: FixedSize, H: BuildHasher + Clone + Default> RHMap<K, V, H> {
+    /// Reads a snapshot written by `write_to`, hashing with a default hasher builder. See `read_from_with_hasher`.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Self::read_from_with_hasher(reader, H::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::collections::hash_map::RandomState;
+
+    #[test]
+    fn it_restores_a_snapshot_slot_for_slot() {
+        // A fixed seed, since with `random-seed` every default `FxBuildHasher` places keys differently.
+        let mut map = FxHashMap::with_hasher(FxBuildHasher::deterministic());
+        for x in 0..10_000u64 {
+            map.insert(x, x as f64 / 2.0);
+        }
+        map.remove(&123).unwrap();
+        let mut bytes = Vec::new();
+        map.write_to(&mut bytes).unwrap();
+
+        let restored: FxHashMap<u64, f64> =
+            RHMap::read_from_with_hasher(&mut bytes.as_slice(), FxBuildHasher::deterministic())
+                .unwrap();
+        assert_eq!(restored.len(), 9_999);
+        assert_eq!(restored.bucket_count(), map.bucket_count());
+        assert_eq!(restored.get(&4_000), Some(&2_000.0));
+        assert_eq!(restored.get(&123), None);
+        assert!(map.iter().eq(restored.iter()));
+    }
+
+    #[test]
+    fn it_rejects_mismatched_or_corrupt_snapshots() {
+        let read = |bytes: &[u8]| {
+            FxHashMap::<u32, [u8; 3]>::read_from_with_hasher(
+                &mut &bytes[..],
+                FxBuildHasher::deterministic(),
+            )
+        };
+        let mut map = FxHashMap::with_hasher(FxBuildHasher::deterministic());
+        map.insert(1u32, [7u8; 3]);
+        let mut bytes = Vec::new();
+        map.write_to(&mut bytes).unwrap();
+        assert!(read(&bytes).is_ok());
+
+        let wrong_types = RHMap::<u64, [u8; 3], FxBuildHasher>::read_from(&mut bytes.as_slice());
+        assert_eq!(wrong_types.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let wrong_hasher = RHMap::<u32, [u8; 3], RandomState>::read_from(&mut bytes.as_slice());
+        assert!(wrong_hasher.is_err());
+        let truncated = read(&bytes[..bytes.len() - 1]);
+        assert_eq!(truncated.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        // A record whose stored hash was tampered with.
+        let record = (0..map.bucket_count())
+            .find(|&i| map.entry_at(i).is_some())
+            .unwrap();
+        let mut tampered = bytes.clone();
+        tampered[HEADER_LEN + record * (1 + 8 + 4 + 3) + 1] ^= 0x80;
+        assert_eq!(
+            read(&tampered).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        // A header announcing a huge table fails on the missing records instead of allocating the table.
+        let mut huge = bytes.clone();
+        huge[24..32].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        assert_eq!(
+            read(&huge).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        bytes[8] = 2;
+        assert!(read(&bytes).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use super::fx_build_hasher::FxBuildHasher;
+use alloc::{vec, vec::Vec};
+use core::{
+    hash::{BuildHasher, Hash},
+    mem,
+};
+
+const INITIAL_SIZE: usize = 8;
+
+/// PSL of a vacant slot.
+const VACANT: usize = usize::MAX;
+
+/// Robinhood HashMap that keeps hashes, PSLs, keys and values in four parallel arrays (structure of arrays) instead of
+/// one array of entries. Probing only walks the dense hash and PSL arrays and touches a key once its hash matches,
+/// and values are only touched once the key matched. Suits large values that are rarely read during lookups, and
+/// `keys()` scans that would otherwise drag every value through the cache.
+pub struct SoaMap<K: Hash + Eq, V, H: BuildHasher + Clone> {
+    hashes: Vec<usize>,
+    /// The PSL of each slot, `VACANT` for vacant ones. Doubles as the occupancy map.
+    psls: Vec<usize>,
+    keys: Vec<Option<K>>,
+    values: Vec<Option<V>>,
+    hasher_builder: H,
+    num_items: usize,
+}
+
+impl<K: Hash + Eq, V> SoaMap<K, V, FxBuildHasher> {
+    /// Creates a `SoaMap` with the default Fx Hasher and an initial capacity of 0.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V> Default for SoaMap<K, V, FxBuildHasher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone> SoaMap<K, V, H> {
+    /// Creates a `SoaMap` with a custom hasher builder which overrides the default fx hasher.
+    pub fn with_hasher(hasher_builder: H) -> Self {
+        Self {
+            hashes: Vec::new(),
+            psls: Vec::new(),
+            keys: Vec::new(),
+            values: Vec::new(),
+            hasher_builder,
+            num_items: 0,
+        }
+    }
+
+    /// Inserts a value with its associated key into the map, overwriting any previous value.
+    pub fn insert(&mut self, key: K, value: V) {
+        // Load Factor of 0.75
+        if self.psls.is_empty() || self.num_items >= 3 * self.psls.len() / 4 {
+            self.resize();
+        }
+
+        let hash = self.hasher_builder.hash_one(&key) as usize;
+        if self.insert_hashed(hash, key, value) {
+            self.num_items += 1;
+        }
+    }
+
+    /// Gets the value associated with the key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.find_index(key).and_then(|i| self.values[i].as_ref())
+    }
+
+    /// Gets a mutable reference to the value associated with the key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let i = self.find_index(key)?;
+        self.values[i].as_mut()
+    }
+
+    /// Checks to see if the provided key is associated with any value. Never touches the values.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find_index(key).is_some()
+    }
+
+    /// Deletes the entry with the given key. Returns an `Err` if no such entry with the provided key exists.
+    pub fn remove(&mut self, key: &K) -> Result<(), &'static str> {
+        let mut i = self.find_index(key).ok_or("Entry not found")?;
+        let mask = self.psls.len() - 1;
+        self.keys[i] = None;
+        self.values[i] = None;
+
+        // Backward shift deletion: pull the rest of the cluster one slot closer to home until we hit a vacancy or an
+        // entry that already sits in its home slot.
+        loop {
+            let next = (i + 1) & mask;
+            if self.psls[next] == VACANT || self.psls[next] == 0 {
+                break;
+            }
+
+            self.move_slot(next, i);
+            self.psls[i] -= 1;
+            i = next;
+        }
+
+        self.psls[i] = VACANT;
+        self.num_items -= 1;
+        Ok(())
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.psls.iter_mut().for_each(|psl| *psl = VACANT);
+        self.keys.iter_mut().for_each(|key| *key = None);
+        self.values.iter_mut().for_each(|value| *value = None);
+        self.num_items = 0;
+    }
+
+    /// Gets the length / number of entries of the map.
+    pub fn len(&self) -> usize {
+        self.num_items
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+
+    /// Gets the capacity (number of slots) of the map.
+    pub fn capacity(&self) -> usize {
+        self.psls.len()
+    }
+
+    /// Iterates over every key in arbitrary order, without touching the values.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.keys.iter().flatten()
+    }
+
+    /// Iterates over every value in arbitrary order, without touching the keys.
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.values.iter().flatten()
+    }
+
+    /// Iterates over every key value pair in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.keys.iter().flatten().zip(self.values.iter().flatten())
+    }
+
+    fn find_index(&self, key: &K) -> Option<usize> {
+        if self.psls.is_empty() {
+            return None;
+        }
+
+        let mask = self.psls.len() - 1;
+        let hash = self.hasher_builder.hash_one(key) as usize;
+        let mut i = hash & mask;
+        let mut psl = 0;
+
+        loop {
+            // Stop at a vacancy, or at an entry richer than us: the robinhood invariant means our key would have
+            // evicted it.
+            let cur_psl = self.psls[i];
+            if cur_psl == VACANT || psl > cur_psl {
+                return None;
+            }
+            if self.hashes[i] == hash && self.keys[i].as_ref() == Some(key) {
+                return Some(i);
+            }
+
+            psl += 1;
+            i = (i + 1) & mask;
+        }
+    }
+
+    /// Places a key value pair and returns `true` if the key wasn't already present.
+    fn insert_hashed(&mut self, mut hash: usize, key: K, value: V) -> bool {
+        let mask = self.psls.len() - 1;
+        let (mut key, mut value) = (Some(key), Some(value));
+        let mut i = hash & mask;
+        let mut psl = 0;
+        // Once the pair we were given has been placed we're only carrying evicted entries, which can't be updates.
+        let mut displaced = false;
+
+        loop {
+            let cur_psl = self.psls[i];
+            if cur_psl == VACANT {
+                self.hashes[i] = hash;
+                self.psls[i] = psl;
+                self.keys[i] = key;
+                self.values[i] = value;
+                return true;
+            }
+
+            if !displaced && self.hashes[i] == hash && self.keys[i] == key {
+                self.values[i] = value;
+                return false;
+            }
+
+            if psl > cur_psl {
+                hash = mem::replace(&mut self.hashes[i], hash);
+                psl = mem::replace(&mut self.psls[i], psl);
+                mem::swap(&mut self.keys[i], &mut key);
+                mem::swap(&mut self.values[i], &mut value);
+                displaced = true;
+            }
+
+            psl += 1;
+            i = (i + 1) & mask;
+        }
+    }
+
+    /// Moves the entry in slot `from` into the vacant slot `to`.
+    fn move_slot(&mut self, from: usize, to: usize) {
+        self.hashes[to] = self.hashes[from];
+        self.psls[to] = self.psls[from];
+        self.keys[to] = self.keys[from].take();
+        self.values[to] = self.values[from].take();
+    }
+
+    fn resize(&mut self) {
+        let target_size = match self.psls.len() {
+            0 => INITIAL_SIZE,
+            n => 2 * n,
+        };
+
+        let hashes = mem::replace(&mut self.hashes, vec![0; target_size]);
+        self.psls = vec![VACANT; target_size];
+        let keys = mem::replace(&mut self.keys, (0..target_size).map(|_| None).collect());
+        let values = mem::replace(&mut self.values, (0..target_size).map(|_| None).collect());
+        // Vacant slots hold neither a key nor a value.
+        for ((hash, key), value) in hashes.into_iter().zip(keys).zip(values) {
+            if let (Some(key), Some(value)) = (key, value) {
+                self.insert_hashed(hash, key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::no_hash::NoHashBuilder;
+    use super::*;
+
+    /// Checks that every slot's hash, PSL, key and value describe the same entry.
+    fn assert_slots_in_step(map: &SoaMap<u64, u64, NoHashBuilder>) {
+        let mask = map.capacity() - 1;
+        for i in 0..map.capacity() {
+            match (map.keys[i], map.values[i]) {
+                (Some(key), Some(value)) => {
+                    assert_eq!(value, key * 10);
+                    assert_eq!(map.hashes[i], key as usize);
+                    assert_eq!(map.psls[i], i.wrapping_sub(key as usize) & mask);
+                }
+                (None, None) => assert_eq!(map.psls[i], VACANT),
+                _ => panic!(
+                    "slot {} has a key without a value or the other way round",
+                    i
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn it_keeps_entries_in_parallel_arrays() {
+        let mut map = SoaMap::new();
+        for x in 0..1000 {
+            map.insert(x, [x; 32]);
+        }
+        for x in (0..1000).step_by(2) {
+            assert!(map.remove(&x).is_ok());
+        }
+        map.get_mut(&1).unwrap()[0] = 0;
+
+        assert_eq!(map.len(), 500);
+        assert_eq!(map.get(&1).map(|value| value[0]), Some(0));
+        assert_eq!(map.get(&3), Some(&[3; 32]));
+        assert!(!map.contains_key(&2));
+        assert!(map.remove(&2).is_err());
+        assert_eq!(map.keys().filter(|&&k| k % 2 == 0).count(), 0);
+        assert!(map.iter().all(|(k, v)| v[1] == *k));
+        assert_eq!(map.values().count(), 500);
+    }
+
+    #[test]
+    fn it_shifts_back_across_the_end_of_the_table() {
+        let mut map = SoaMap::with_hasher(NoHashBuilder);
+        // 6, 14 and 22 all start at slot 6 of the 8, so the cluster wraps into slots 0 and 1, where 7 ends up too.
+        for key in [6, 14, 22, 7].iter().copied() {
+            map.insert(key, key * 10);
+        }
+        assert_eq!(map.capacity(), 8);
+        assert_eq!(map.keys[0..2], [Some(22), Some(7)]);
+
+        assert!(map.remove(&6).is_ok());
+        assert_eq!(map.keys[6..], [Some(14), Some(22)]);
+        assert_eq!(map.keys[0..2], [Some(7), None]);
+        assert_eq!(map.psls[0..2], [1, VACANT]);
+        assert_slots_in_step(&map);
+
+        assert!(map.remove(&14).is_ok());
+        assert_eq!(map.keys[6..], [Some(22), Some(7)]);
+        assert_eq!(map.get(&7), Some(&70));
+        assert_eq!(map.get(&22), Some(&220));
+        assert_slots_in_step(&map);
+    }
+
+    #[test]
+    fn it_keeps_the_arrays_in_step_while_growing() {
+        let mut map = SoaMap::with_hasher(NoHashBuilder);
+        let mut capacity = 0;
+        for key in 0..200 {
+            // Keys 64 apart share a home slot until the table outgrows 64, so clusters break up on the later resizes.
+            map.insert(key / 2 * 64 + key % 2, key / 2 * 640 + key % 2 * 10);
+            if map.capacity() != capacity {
+                capacity = map.capacity();
+                assert_slots_in_step(&map);
+            }
+        }
+        assert_eq!(map.len(), 200);
+        assert!(capacity >= 256);
+        assert_slots_in_step(&map);
+        assert!(map.iter().all(|(k, v)| *v == k * 10));
+    }
+}
@@ -0,0 +1,416 @@
+use super::equivalent::Equivalent;
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use super::hashset::RHSet;
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    mem,
+    ops::{Deref, DerefMut, Index},
+};
+
+/// Drop-in replacement for `std::collections::HashMap` backed by a `RHMap` with the Fx hasher, so migrating is a
+/// matter of swapping `use std::collections::HashMap;` for `use rhmap::std_compat::HashMap;`. `insert` and `remove`
+/// hand back the previous value like std's do, and every other method (`entry`, `get_mut`, `get_key_value`,
+/// `remove_entry`, `retain`, `drain`, `iter`, `keys`, `values`, ...) is the `RHMap` one, reached through `Deref`.
+pub struct HashMap<K: Hash + Eq, V, S: BuildHasher + Clone = FxBuildHasher> {
+    map: RHMap<K, V, S>,
+}
+
+impl<K: Hash + Eq, V> HashMap<K, V, FxBuildHasher> {
+    /// Creates an empty `HashMap` with the default Fx Hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher::new())
+    }
+
+    /// Creates an empty `HashMap` with the default Fx Hasher and room for at least `capacity` entries.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, FxBuildHasher::new())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone> HashMap<K, V, S> {
+    /// Creates an empty `HashMap` with a custom hasher builder.
+    pub fn with_hasher(hasher_builder: S) -> Self {
+        Self {
+            map: RHMap::with_hasher(hasher_builder),
+        }
+    }
+
+    /// Creates an empty `HashMap` with a custom hasher builder and room for at least `capacity` entries.
+    pub fn with_capacity_and_hasher(capacity: usize, hasher_builder: S) -> Self {
+        Self {
+            map: RHMap::with_capacity_and_hasher(capacity, hasher_builder),
+        }
+    }
+
+    /// Inserts a key value pair, returning the previous value of the key, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.map.make_hash(&key);
+        match self.map.probe_hashed(hash, &key).0 {
+            Some(i) => Some(mem::replace(
+                &mut self.map.entry_at_mut(i).unwrap().value,
+                value,
+            )),
+            None => {
+                self.map.insert_vacant(hash, key, value);
+                None
+            }
+        }
+    }
+
+    /// Removes the entry with the given key and returns its value, if there was one.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        self.map.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Unwraps the underlying `RHMap`.
+    pub fn into_inner(self) -> RHMap<K, V, S> {
+        self.map
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone> Deref for HashMap<K, V, S> {
+    type Target = RHMap<K, V, S>;
+
+    fn deref(&self) -> &RHMap<K, V, S> {
+        &self.map
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone> DerefMut for HashMap<K, V, S> {
+    fn deref_mut(&mut self) -> &mut RHMap<K, V, S> {
+        &mut self.map
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher + Clone> Clone for HashMap<K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V: PartialEq, S: BuildHasher + Clone> PartialEq for HashMap<K, V, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K: Hash + Eq, V: Eq, S: BuildHasher + Clone> Eq for HashMap<K, V, S> {}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone + Default> Default for HashMap<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+/// Formats the map like the std maps do (`{key: value, ...}`).
+impl<K: Hash + Eq + Debug, V: Debug, S: BuildHasher + Clone> Debug for HashMap<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.map.fmt(f)
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone> From<RHMap<K, V, S>> for HashMap<K, V, S> {
+    fn from(map: RHMap<K, V, S>) -> Self {
+        Self { map }
+    }
+}
+
+impl<K: Hash + Eq, V, const N: usize> From<[(K, V); N]> for HashMap<K, V, FxBuildHasher> {
+    fn from(pairs: [(K, V); N]) -> Self {
+        RHMap::from(pairs).into()
+    }
+}
+
+/// Panics if the key is missing, like indexing a std `HashMap` does.
+impl<K: Hash + Eq, Q: ?Sized + Hash + Equivalent<K>, V, S: BuildHasher + Clone> Index<&Q>
+    for HashMap<K, V, S>
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.map.get(key).expect("key not found in HashMap")
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone> Extend<(K, V)> for HashMap<K, V, S> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        for (key, value) in pairs {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone + Default> FromIterator<(K, V)> for HashMap<K, V, S> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let mut map = Self::default();
+        map.extend(pairs);
+        map
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Clone> IntoIterator for HashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = <RHMap<K, V, S> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
+
+impl<'a, K: Hash + Eq, V, S: BuildHasher + Clone> IntoIterator for &'a HashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = <&'a RHMap<K, V, S> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        (&self.map).into_iter()
+    }
+}
+
+impl<'a, K: Hash + Eq, V, S: BuildHasher + Clone> IntoIterator for &'a mut HashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = <&'a mut RHMap<K, V, S> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        (&mut self.map).into_iter()
+    }
+}
+
+/// Drop-in name for `std::collections::HashSet` backed by a `RHSet` with the Fx hasher. `insert`, `remove`,
+/// `contains`, `take` and `get` return what their std counterparts do.
+pub type HashSet<T> = RHSet<T, FxBuildHasher>;
+
+/// Moves the entries of a std `HashMap` over, reserving room for all of them up front so the table is sized once.
+#[cfg(feature = "std")]
+impl<K: Hash + Eq, V, S, H: BuildHasher + Clone + Default> From<std::collections::HashMap<K, V, S>>
+    for RHMap<K, V, H>
+{
+    fn from(map: std::collections::HashMap<K, V, S>) -> Self {
+        let mut rh_map = RHMap::with_capacity_and_hasher(map.len(), H::default());
+        for (key, value) in map {
+            rh_map.insert(key, value);
+        }
+        rh_map
+    }
+}
+
+/// Moves the entries back into a std `HashMap`, for the parts of a codebase that haven't migrated yet.
+#[cfg(feature = "std")]
+impl<K: Hash + Eq, V, H: BuildHasher + Clone, S: BuildHasher + Default> From<RHMap<K, V, H>>
+    for std::collections::HashMap<K, V, S>
+{
+    fn from(map: RHMap<K, V, H>) -> Self {
+        let mut std_map =
+            std::collections::HashMap::with_capacity_and_hasher(map.len(), S::default());
+        std_map.extend(map);
+        std_map
+    }
+}
+
+/// Moves the values of a std `HashSet` over, reserving room for all of them up front.
+#[cfg(feature = "std")]
+impl<T: Hash + Eq, S, H: BuildHasher + Clone + Default> From<std::collections::HashSet<T, S>>
+    for RHSet<T, H>
+{
+    fn from(set: std::collections::HashSet<T, S>) -> Self {
+        let mut rh_set = RHSet::with_capacity_and_hasher(set.len(), H::default());
+        for value in set {
+            rh_set.insert(value);
+        }
+        rh_set
+    }
+}
+
+/// Moves the values back into a std `HashSet`.
+#[cfg(feature = "std")]
+impl<T: Hash + Eq, H: BuildHasher + Clone, S: BuildHasher + Default> From<RHSet<T, H>>
+    for std::collections::HashSet<T, S>
+{
+    fn from(set: RHSet<T, H>) -> Self {
+        let mut std_set =
+            std::collections::HashSet::with_capacity_and_hasher(set.len(), S::default());
+        std_set.extend(set);
+        std_set
+    }
+}
+
+/// Moves the entries of a `hashbrown` map over, reserving room for all of them up front.
+#[cfg(feature = "hashbrown")]
+impl<K: Hash + Eq, V, S, H: BuildHasher + Clone + Default> From<hashbrown::HashMap<K, V, S>>
+    for RHMap<K, V, H>
+{
+    fn from(map: hashbrown::HashMap<K, V, S>) -> Self {
+        let mut rh_map = RHMap::with_capacity_and_hasher(map.len(), H::default());
+        for (key, value) in map {
+            rh_map.insert(key, value);
+        }
+        rh_map
+    }
+}
+
+/// Moves the entries back into a `hashbrown` map.
+#[cfg(feature = "hashbrown")]
+impl<K: Hash + Eq, V, H: BuildHasher + Clone, S: BuildHasher + Default> From<RHMap<K, V, H>>
+    for hashbrown::HashMap<K, V, S>
+{
+    fn from(map: RHMap<K, V, H>) -> Self {
+        let mut hb_map = hashbrown::HashMap::with_capacity_and_hasher(map.len(), S::default());
+        hb_map.extend(map);
+        hb_map
+    }
+}
+
+/// Moves the values of a `hashbrown` set over, reserving room for all of them up front.
+#[cfg(feature = "hashbrown")]
+impl<T: Hash + Eq, S, H: BuildHasher + Clone + Default> From<hashbrown::HashSet<T, S>>
+    for RHSet<T, H>
+{
+    fn from(set: hashbrown::HashSet<T, S>) -> Self {
+        let mut rh_set = RHSet::with_capacity_and_hasher(set.len(), H::default());
+        for value in set {
+            rh_set.insert(value);
+        }
+        rh_set
+    }
+}
+
+/// Moves the values back into a `hashbrown` set.
+#[cfg(feature = "hashbrown")]
+impl<T: Hash + Eq, H: BuildHasher + Clone, S: BuildHasher + Default> From<RHSet<T, H>>
+    for hashbrown::HashSet<T, S>
+{
+    fn from(set: RHSet<T, H>) -> Self {
+        let mut hb_set = hashbrown::HashSet::with_capacity_and_hasher(set.len(), S::default());
+        hb_set.extend(set);
+        hb_set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_supports_the_std_method_names() {
+        let mut map: HashMap<&str, i32> = HashMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+        map.insert("c", 3);
+
+        *map.get_mut(&"a").unwrap() += 10;
+        assert_eq!(map.get_key_value(&"a"), Some((&"a", &11)));
+        assert_eq!(map.remove_entry(&"b"), Some(("b", 2)));
+        assert_eq!(map.remove_entry(&"b"), None);
+
+        for v in map.values_mut() {
+            *v *= 2;
+        }
+        let mut keys: Vec<_> = map.keys().copied().collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["a", "c"]);
+        assert_eq!(map.values().sum::<i32>(), 28);
+        assert_eq!(map.iter().count(), 2);
+
+        map.retain(|_, v| *v > 10);
+        let mut drained: Vec<_> = map.drain().collect();
+        drained.sort_unstable();
+        assert_eq!(drained, vec![("a", 22)]);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn it_returns_what_std_returns_from_insert_and_remove() {
+        let mut map: HashMap<&str, i32> = vec![("a", 1)].into_iter().collect();
+        assert!(map.insert("b", 2).is_none());
+        assert_eq!(map.insert("b", 3), Some(2));
+        if let Some(value) = map.remove("a") {
+            assert_eq!(value, 1);
+        }
+        assert_eq!(map.remove("a"), None);
+
+        map.extend(vec![("c", 4)]);
+        assert_eq!(map["c"], 4);
+        assert_eq!(format!("{:?}", HashMap::from([("x", 0)])), r#"{"x": 0}"#);
+        assert_eq!((&map).into_iter().count(), 2);
+        assert_eq!(map.into_inner().len(), 2);
+    }
+
+    #[test]
+    fn it_runs_typical_std_map_code() {
+        let text = "the cat sat on the mat the end";
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in text.split(' ') {
+            *counts.entry(word.to_string()).or_insert(0) += 1;
+        }
+        assert_eq!(counts["the"], 3);
+
+        let mut lengths: HashMap<usize, Vec<&str>> = HashMap::with_capacity(8);
+        for word in text.split(' ') {
+            lengths.entry(word.len()).or_default().push(word);
+        }
+        lengths
+            .entry(3)
+            .and_modify(|words| words.dedup())
+            .or_insert_with(|| vec!["new"]);
+        assert_eq!(
+            lengths
+                .entry(9)
+                .or_insert_with_key(|len| vec!["x"; *len])
+                .len(),
+            9
+        );
+        assert_eq!(lengths[&2], vec!["on"]);
+
+        let snapshot = counts.clone();
+        assert_eq!(snapshot, counts);
+        counts.entry("cat".to_string()).and_modify(|n| *n += 1);
+        assert_ne!(snapshot, counts);
+        match counts.entry("dog".to_string()) {
+            crate::entry::Entry::Occupied(_) => panic!("no dog in the text"),
+            crate::entry::Entry::Vacant(entry) => assert_eq!(entry.into_key(), "dog"),
+        }
+
+        let seen: HashSet<&str> = text.split(' ').collect();
+        let copy = seen.clone();
+        assert_eq!(seen, copy);
+        assert_eq!(seen.len(), 6);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_converts_to_and_from_std_collections() {
+        let mut std_map = std::collections::HashMap::new();
+        std_map.insert("a", 1);
+        std_map.insert("b", 2);
+        let map: RHMap<&str, i32, FxBuildHasher> = std_map.into();
+        assert_eq!(map.get(&"b"), Some(&2));
+
+        let std_map: std::collections::HashMap<_, _> = map.into();
+        assert_eq!(std_map.len(), 2);
+        assert_eq!(std_map["a"], 1);
+        let set: HashSet<u8> = std::collections::HashSet::from([1, 2, 3]).into();
+        let std_set: std::collections::HashSet<u8> = set.into();
+        assert!(std_set.contains(&3));
+    }
+
+    #[cfg(feature = "hashbrown")]
+    #[test]
+    fn it_converts_to_and_from_hashbrown() {
+        let mut hb_map = hashbrown::HashMap::with_hasher(FxBuildHasher::new());
+        hb_map.insert(1u32, "one");
+        let map: RHMap<u32, &str, FxBuildHasher> = hb_map.into();
+        assert_eq!(map.get(&1), Some(&"one"));
+
+        let hb_map: hashbrown::HashMap<u32, &str, FxBuildHasher> = map.into();
+        assert_eq!(hb_map.get(&1), Some(&"one"));
+        let set: HashSet<u32> = vec![4, 5]
+            .into_iter()
+            .collect::<hashbrown::HashSet<u32, FxBuildHasher>>()
+            .into();
+        assert!(set.contains(&5));
+    }
+}
@@ -0,0 +1,31 @@
+/// Read access shared by the map backends in this crate, so tooling like `BenchContext` can be written once and run
+/// against any of them.
+pub trait MapRead<K, V> {
+    /// Gets the value associated with the key, if any.
+    fn get(&self, key: &K) -> Option<&V>;
+
+    /// Gets the number of entries in the map.
+    fn len(&self) -> usize;
+
+    /// Gets the number of slots a lookup of `key` inspects, whether or not the key is present.
+    fn probe_count(&self, key: &K) -> usize;
+
+    /// Checks to see if the provided key is associated with any value.
+    fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Write access shared by the map backends in this crate.
+pub trait MapWrite<K, V>: MapRead<K, V> {
+    /// Inserts a value with its associated key, overwriting any previous value.
+    fn insert(&mut self, key: K, value: V);
+
+    /// Deletes the entry with the given key. Returns `false` if there was no such entry.
+    fn remove(&mut self, key: &K) -> bool;
+}
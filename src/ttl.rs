@@ -0,0 +1,242 @@
+use super::equivalent::Equivalent;
+#[cfg(feature = "std")]
+use super::fx_build_hasher::FxBuildHasher;
+use super::hashmap::RHMap;
+use core::{
+    hash::{BuildHasher, Hash},
+    time::Duration,
+};
+
+/// The time source of a `TtlMap`. Times are durations since an arbitrary fixed point, so tests can hand out whatever
+/// they like and `no_std` targets can plug in their own tick counter.
+pub trait Clock {
+    /// The current time. Must never go backwards.
+    fn now(&self) -> Duration;
+}
+
+/// The monotonic OS clock, measured from when the `MonotonicClock` was created.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct MonotonicClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl MonotonicClock {
+    /// Starts a clock that reads zero right now.
+    pub fn new() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A `TtlMap` with the default Fx Hasher on the OS clock, so it can be named without spelling out either.
+#[cfg(feature = "std")]
+pub type FxTtlMap<K, V> = TtlMap<K, V, FxBuildHasher, MonotonicClock>;
+
+struct Expiring<V> {
+    value: V,
+    /// When the entry stops being visible, `None` for entries that never expire.
+    deadline: Option<Duration>,
+}
+
+impl<V> Expiring<V> {
+    fn is_expired(&self, now: Duration) -> bool {
+        matches!(self.deadline, Some(deadline) if now >= deadline)
+    }
+}
+
+/// A `RHMap` whose entries can carry a time to live. Expired entries are invisible right away and purged lazily: a
+/// lookup that runs into one removes it, and `purge_expired` sweeps the whole map, e.g. from a periodic background
+/// task. Until then they still count towards `len` and hold on to their memory.
+pub struct TtlMap<K: Hash + Eq, V, H: BuildHasher + Clone, C: Clock> {
+    map: RHMap<K, Expiring<V>, H>,
+    clock: C,
+}
+
+#[cfg(feature = "std")]
+impl<K: Hash + Eq, V> TtlMap<K, V, FxBuildHasher, MonotonicClock> {
+    /// Creates a `TtlMap` with the default Fx Hasher on the OS clock.
+    pub fn new() -> Self {
+        Self::with_hasher_and_clock(FxBuildHasher::new(), MonotonicClock::new())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Hash + Eq, V> Default for TtlMap<K, V, FxBuildHasher, MonotonicClock> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V, H: BuildHasher + Clone, C: Clock> TtlMap<K, V, H, C> {
+    /// Creates a `TtlMap` with a custom hasher builder and time source.
+    pub fn with_hasher_and_clock(hasher_builder: H, clock: C) -> Self {
+        Self {
+            map: RHMap::with_hasher(hasher_builder),
+            clock,
+        }
+    }
+
+    /// Inserts a key value pair that never expires, overwriting any previous value and its deadline.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.map.insert(
+            key,
+            Expiring {
+                value,
+                deadline: None,
+            },
+        );
+    }
+
+    /// Inserts a key value pair that expires once `ttl` has passed, overwriting any previous value and its deadline.
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) {
+        let deadline = self.clock.now().checked_add(ttl);
+        self.map.insert(key, Expiring { value, deadline });
+    }
+
+    /// Gets the value associated with the key, purging it if it has expired.
+    pub fn get<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&V> {
+        self.purge_if_expired(key);
+        self.map.get(key).map(|entry| &entry.value)
+    }
+
+    /// Gets a mutable reference to the value associated with the key, purging it if it has expired.
+    pub fn get_mut<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<&mut V> {
+        self.purge_if_expired(key);
+        self.map.get_mut(key).map(|entry| &mut entry.value)
+    }
+
+    /// Checks to see if the provided key is associated with a live value, purging it if it has expired.
+    pub fn contains_key<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Gets how long the entry with the given key has left to live, `None` if it never expires or isn't there.
+    pub fn ttl<Q: ?Sized + Hash + Equivalent<K>>(&self, key: &Q) -> Option<Duration> {
+        let deadline = self.map.get(key)?.deadline?;
+        Some(deadline.saturating_sub(self.clock.now()))
+    }
+
+    /// Removes the entry with the given key and returns its value, if it was still live.
+    pub fn remove<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) -> Option<V> {
+        let now = self.clock.now();
+        let (_, entry) = self.map.remove_entry(key)?;
+        (!entry.is_expired(now)).then_some(entry.value)
+    }
+
+    /// Removes every entry that has expired by `now`, returning how many were removed.
+    pub fn purge_expired(&mut self, now: Duration) -> usize {
+        let before = self.map.len();
+        self.map.retain(|_, entry| !entry.is_expired(now));
+        before - self.map.len()
+    }
+
+    /// Clears all entries but preserves the allocated memory for use later.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Gets the number of entries, including expired ones that haven't been purged yet.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map holds no entries, live or expired.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Gets the time source of the map.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Iterates over every live key value pair in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let now = self.clock.now();
+        self.map
+            .iter()
+            .filter(move |(_, entry)| !entry.is_expired(now))
+            .map(|(key, entry)| (key, &entry.value))
+    }
+
+    fn purge_if_expired<Q: ?Sized + Hash + Equivalent<K>>(&mut self, key: &Q) {
+        let now = self.clock.now();
+        if matches!(self.map.get(key), Some(entry) if entry.is_expired(now)) {
+            self.map.remove_entry(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fx_build_hasher::FxBuildHasher;
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    /// A clock that only moves when the test says so.
+    #[derive(Clone, Default)]
+    struct FakeClock(Rc<Cell<Duration>>);
+
+    impl FakeClock {
+        fn advance(&self, by: Duration) {
+            self.0.set(self.0.get() + by);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn it_expires_entries_lazily() {
+        let clock = FakeClock::default();
+        let mut map = TtlMap::with_hasher_and_clock(FxBuildHasher::new(), clock.clone());
+        map.insert_with_ttl("session", 1, Duration::from_secs(10));
+        map.insert("config", 2);
+
+        clock.advance(Duration::from_secs(4));
+        assert_eq!(map.ttl(&"session"), Some(Duration::from_secs(6)));
+        assert_eq!(map.get(&"session"), Some(&1));
+
+        clock.advance(Duration::from_secs(6));
+        assert_eq!(map.iter().count(), 1);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&"session"), None);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&"config"), Some(&2));
+    }
+
+    #[test]
+    fn it_purges_expired_entries() {
+        let clock = FakeClock::default();
+        let mut map = TtlMap::with_hasher_and_clock(FxBuildHasher::new(), clock.clone());
+        for x in 0..100u64 {
+            map.insert_with_ttl(x, x, Duration::from_secs(x));
+        }
+
+        assert_eq!(map.purge_expired(Duration::from_secs(50)), 51);
+        assert_eq!(map.len(), 49);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(map.remove(&55), None);
+        assert_eq!(map.remove(&70), Some(70));
+    }
+}
@@ -0,0 +1,114 @@
+use super::hashmap::RHMap;
+use core::hash::{BuildHasher, Hasher};
+
+const P0: u64 = 0xa076_1d64_78bd_642f;
+const P1: u64 = 0xe703_7ed1_a0b4_28db;
+const P2: u64 = 0x8ebc_6af0_9c88_c6e3;
+const P3: u64 = 0x5899_65cc_7537_4cc3;
+
+/// wyhash's mixing step: a full 64 x 64 -> 128-bit multiply, folded back into 64 bits so every input bit reaches
+/// both the high and the low bits of the result.
+#[inline(always)]
+fn mum(a: u64, b: u64) -> u64 {
+    let product = (a as u128) * (b as u128);
+    (product as u64) ^ ((product >> 64) as u64)
+}
+
+/// A hasher mixing every word in with wyhash's multiply and fold. A little slower than Fx on integer keys, but every
+/// bit of the key affects every bit of the hash, so keys that only differ in their high bits (or share their low
+/// bits) still spread over the table.
+#[derive(Clone, Copy, Debug)]
+pub struct WyHasher {
+    state: u64,
+}
+
+impl Hasher for WyHasher {
+    fn finish(&self) -> u64 {
+        mum(self.state ^ P2, P3)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            self.write_u64(u64::from_le_bytes(word));
+        }
+
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            let mut word = [0; 8];
+            word[..rest.len()].copy_from_slice(rest);
+            // The length goes in the unused top byte, so trailing zero bytes aren't lost in the padding.
+            self.write_u64(u64::from_le_bytes(word) ^ (rest.len() as u64) << 56);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.state = mum(self.state ^ P0, i ^ P1);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// Builds `WyHasher`s, optionally seeded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WyBuildHasher {
+    seed: u64,
+}
+
+impl WyBuildHasher {
+    /// Creates an unseeded builder, hashing the same way in every process.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a builder that mixes `seed` into every hash.
+    pub fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl BuildHasher for WyBuildHasher {
+    type Hasher = WyHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        WyHasher { state: self.seed }
+    }
+}
+
+/// A `RHMap` hashing with `WyHasher`. Create one with `WyHashMap::default()`.
+pub type WyHashMap<K, V> = RHMap<K, V, WyBuildHasher>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_spreads_keys_that_only_differ_in_high_bits() {
+        let builder = WyBuildHasher::new();
+        let mut map: WyHashMap<u64, u64> = WyHashMap::default();
+        for x in 0..1000 {
+            map.insert(x << 40, x);
+        }
+
+        assert_eq!(map.get(&(7 << 40)), Some(&7));
+        assert!(map.max_probe_length() < 16);
+        assert_ne!(builder.hash_one("ab"), builder.hash_one("ab\0"));
+        assert_ne!(builder.hash_one(1), WyBuildHasher::with_seed(1).hash_one(1));
+    }
+}